@@ -10,27 +10,229 @@ impl<'a> ToTokens for InjectedBody<'a> {
             fields,
         } = self;
         for field in fields {
-            let ident = &field.ident;
+            let ident = &field.field.ident;
+            if !field.injected {
+                stream.extend(quote! { #ident: Default::default(), });
+                continue;
+            }
             let ty = &field.ty;
-            let out = quote! {
-                #ident: #graph_ident
-                    .get_node::<#ty>()
-                    .or_else(|| sept::graph::Graph::search_all(#imported_graph_ident))
-                    .unwrap()
-                    .to_owned(),
+            let name = field.name_tokens();
+            let lookup = if is_multi(ty) {
+                quote! {
+                    <#ty>::collect(
+                        std::iter::once(&*#graph_ident)
+                            .chain(#imported_graph_ident.iter().copied()),
+                    )
+                }
+            } else {
+                quote! {
+                    match sept::graph::Graph::transient::<#ty>(
+                        #graph_ident,
+                        #imported_graph_ident,
+                        #name,
+                    ) {
+                        Some(transient) => Some(transient?),
+                        None => #graph_ident
+                            .get_node_named::<#ty>(#name)
+                            .or_else(|| {
+                                sept::graph::Graph::search_all_named(#imported_graph_ident, #name)
+                            })
+                            .cloned(),
+                    }
+                }
+            };
+            let out = if field.optional {
+                quote! { #ident: #lookup, }
+            } else {
+                quote! {
+                    #ident: #lookup.ok_or_else(|| sept::error::BuildError::MissingDependency {
+                        needed_by: sept::error::TypeInfo::of::<Self>(),
+                        missing: sept::error::TypeInfo::of::<#ty>(),
+                    })?,
+                }
             };
             stream.extend(out);
         }
     }
 }
 
+struct InjectedField {
+    field: syn::Field,
+    /// The injected type, with the `Option` unwrapped for optional fields.
+    ty: syn::Type,
+    optional: bool,
+    name: Option<syn::LitStr>,
+    /// Whether the field carries `#[inject]`.
+    marked: bool,
+    /// Whether the field is resolved from the graph rather than defaulted.
+    injected: bool,
+}
+
+impl InjectedField {
+    fn name_tokens(&self) -> TokenStream2 {
+        match &self.name {
+            Some(name) => quote! { Some(#name) },
+            None => quote! { None },
+        }
+    }
+
+    fn new(field: &syn::Field) -> syn::Result<Self> {
+        let mut name = None;
+        let mut marked = false;
+        for attr in &field.attrs {
+            if attr.path.is_ident("inject") {
+                match attr.parse_meta()? {
+                    syn::Meta::Path(_) => marked = true,
+                    meta => {
+                        return Err(syn::Error::new_spanned(
+                            meta,
+                            "Attribute inject takes no arguments.",
+                        ));
+                    }
+                }
+                continue;
+            }
+            if !attr.path.is_ident("named") {
+                continue;
+            }
+            match attr.parse_meta()? {
+                syn::Meta::List(list) if list.nested.len() == 1 => {
+                    if let Some(syn::NestedMeta::Lit(syn::Lit::Str(lit))) = list.nested.first() {
+                        name = Some(lit.clone());
+                        continue;
+                    }
+                    return Err(syn::Error::new_spanned(
+                        list,
+                        "Attribute named expects a literal string.",
+                    ));
+                }
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "Attribute named expects a single provider name.",
+                    ));
+                }
+            }
+        }
+        let (ty, optional) = match option_inner(&field.ty) {
+            Some(inner) => (inner.to_owned(), true),
+            None => (field.ty.to_owned(), false),
+        };
+        Ok(Self {
+            field: field.to_owned(),
+            ty,
+            optional,
+            name,
+            marked,
+            injected: !is_phantom(&field.ty),
+        })
+    }
+
+    fn validate(&self) -> syn::Result<()> {
+        if !self.injected {
+            return match &self.name {
+                Some(name) => Err(syn::Error::new_spanned(
+                    name,
+                    "Attribute named requires #[inject] on the same field.",
+                )),
+                None => Ok(()),
+            };
+        }
+        if !is_injectable(&self.ty) {
+            return Err(syn::Error::new_spanned(
+                &self.field.ty,
+                format!(
+                    "Injected fields must be `Arc<T>`, `Option<Arc<T>>`, `Multi<Tag>`, \
+                     `Exports<Module>` or `Tokened<K, T>`; wrap `{}` in an `Arc`.",
+                    self.ty.to_token_stream()
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `ty` is a type the graph holds nodes of: `Arc<T>`, `Multi<Tag>`, `Exports<M>` or
+/// `Tokened<K, T>`.
+fn is_injectable(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            ["Arc", "Multi", "Exports", "Tokened"]
+                .iter()
+                .any(|ident| segment.ident == ident)
+        }),
+        _ => false,
+    }
+}
+
+/// Whether `ty` is a `Multi<Tag>`, which merges the collections of every visible graph rather
+/// than taking the first.
+fn is_multi(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Multi"),
+        _ => false,
+    }
+}
+
+/// Whether `ty` is a `PhantomData`, which is always defaulted so generic providers can carry
+/// their type parameters.
+fn is_phantom(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "PhantomData"),
+        _ => false,
+    }
+}
+
+/// The `T` of an `Option<T>` field type.
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first()
+        {
+            Some(syn::GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 pub(crate) struct InjectedBody<'a> {
     graph_ident: &'a Ident,
     imported_graph_ident: &'a Ident,
-    fields: Vec<syn::Field>,
+    fields: Vec<InjectedField>,
 }
 
 impl<'a> InjectedBody<'a> {
+    /// The graph key and type name of every field, for `Injected::dependencies`.
+    pub(crate) fn dependencies(&self) -> TokenStream2 {
+        let deps = self
+            .fields
+            .iter()
+            .filter(|field| field.injected)
+            .map(|field| {
+                let ty = &field.ty;
+                let name = field.name_tokens();
+                quote! {
+                    (sept::graph::Graph::key::<#ty>(#name), std::any::type_name::<#ty>()),
+                }
+            });
+        quote! { vec![#(#deps)*] }
+    }
+
     pub(crate) fn new(
         graph_ident: &'a Ident,
         imported_graph_ident: &'a Ident,
@@ -40,7 +242,7 @@ impl<'a> InjectedBody<'a> {
         match &data.fields {
             syn::Fields::Named(fl) => {
                 for field in fl.named.iter() {
-                    fields.push(field.to_owned());
+                    fields.push(InjectedField::new(field)?);
                 }
             }
             syn::Fields::Unit => {}
@@ -51,6 +253,15 @@ impl<'a> InjectedBody<'a> {
                 ));
             }
         }
+        // Once any field is marked `#[inject]`, the unmarked ones are defaulted.
+        if fields.iter().any(|field| field.marked) {
+            for field in &mut fields {
+                field.injected = field.marked;
+            }
+        }
+        for field in &fields {
+            field.validate()?;
+        }
         Ok(Self {
             graph_ident,
             imported_graph_ident,
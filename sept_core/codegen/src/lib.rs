@@ -88,7 +88,21 @@ pub fn go_test(_: TokenStream, item: TokenStream) -> TokenStream {
 }
 
 /// Derives the `Injectable` trait for dependency injection.
-#[proc_macro_derive(Injectable)]
+///
+/// Each field is resolved from the graph, so it must be an `Arc<T>` of a provided type, a
+/// `Multi<Tag>` collection, the `Exports<M>` of an imported module or a `Tokened<K, T>`
+/// provided under a token; any other field type is a compile error. If any field is marked
+/// `#[inject]`, only the marked fields are resolved and the rest are filled with
+/// `Default::default()`, so plain state can sit alongside injected collaborators.
+///
+/// A field of type `Option<T>` is optional: it's `None` when nothing provides `T`, rather than
+/// failing the build. Within a module, declare an optional dependency's provider before its
+/// consumer, since a consumer that builds without it isn't revisited.
+///
+/// Generic structs derive an impl per monomorphization, so `Repository<User>` and
+/// `Repository<Order>` are separate providers. Type parameters must be `Send + Sync + 'static`;
+/// carry them in `PhantomData` fields, which are always defaulted.
+#[proc_macro_derive(Injectable, attributes(inject, named))]
 pub fn injectable(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
 
@@ -107,17 +121,33 @@ pub fn injectable(input: TokenStream) -> TokenStream {
     };
     match fields {
         Ok(f) => {
+            let dependencies = f.dependencies();
+            // Each monomorphization is a distinct provider, so type parameters must be able to
+            // live in the graph.
+            let mut generics = ast.generics.clone();
+            let params: Vec<_> = generics.type_params().map(|p| p.ident.clone()).collect();
+            let where_clause = generics.make_where_clause();
+            for param in params {
+                where_clause
+                    .predicates
+                    .push(syn::parse_quote!(#param: Send + Sync + 'static));
+            }
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
             let expanded = quote! {
                 #[automatically_derived]
-                impl sept::graph::Injected for #name {
+                impl #impl_generics sept::graph::Injected for #name #ty_generics #where_clause {
                     type Output = Self;
                     fn resolve(
                         #graph_ident: &mut sept::graph::Graph,
                         #context_ident: &[&sept::graph::Graph]
-                    ) -> Self {
-                        Self {
+                    ) -> Result<Self, sept::error::BuildError> {
+                        Ok(Self {
                             #f
-                        }
+                        })
+                    }
+
+                    fn dependencies() -> Vec<(sept::graph::NodeKey, &'static str)> {
+                        #dependencies
                     }
                 }
             };
@@ -127,26 +157,57 @@ pub fn injectable(input: TokenStream) -> TokenStream {
     }
 }
 
+/// Generates the `ModuleFactory` impl of a module, the equivalent of NestJS `@Module`:
+///
+/// ```ignore
+/// #[module(imports = [DatabaseModule], providers = [UserService], exports = [UserService],
+///          controllers = [UserController])]
+/// struct UserModule;
+/// ```
+///
+/// Each export must be listed in `providers`, or in `imports` to re-export everything that module
+/// exports. That's checked as the attribute expands, so a misspelled or forgotten export is a
+/// compile error naming it rather than a `BuildError::ExportNotProvided` at startup; entries are
+/// compared as written, so list a type by the same path in both places. The entries may also be
+/// given as separate `#[imports(..)]`, `#[providers(..)]`, `#[exports(..)]` and `#[clients(..)]`
+/// attributes.
+///
+/// A bare `global` key, or a separate `#[global]` attribute, makes the module global as
+/// `Module::global` does: once it's imported, its exports are visible to every module.
+/// Importing it from several modules still builds it, and merges its exports, just once.
 #[proc_macro_attribute]
-pub fn module(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn module(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = parse_macro_input!(attr as ModuleArgs);
     let mut input = parse_macro_input!(item as ItemStruct);
     let name = &input.ident;
     match ModuleArgs::parse_and_strip(&mut input.attrs) {
-        Ok(ModuleArgs {
-            clients,
-            imports,
-            exports,
-            providers,
-        }) => {
+        Ok(stripped) => {
+            args.merge(stripped);
+            let (exports, export_modules) = match args.split_exports() {
+                Ok(exports) => exports,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let ModuleArgs {
+                clients,
+                imports,
+                providers,
+                global,
+                ..
+            } = &args;
+            let global = global.then(|| quote! { .global() });
             let expanded = quote! {
                 #input
 
                 #[automatically_derived]
                 impl sept::sept_module::ModuleFactory for #name {
                     fn get_module() -> sept::sept_module::Module {
-                        sept::sept_module::Module::new()
-                            #(.import::<#imports>())*
-                            #(.export::<#exports>())*
+                        sept::export_all!(
+                            sept::sept_module::Module::new()
+                                #global
+                                #(.import::<#imports>())*
+                            #(, #exports)*
+                        )
+                            #(.export_module::<#export_modules>())*
                             #(.provide::<#providers>())*
                             #(.client::<#clients>())*
                     }
@@ -174,7 +235,7 @@ impl Method {
             match attr.parse_meta() {
                 Ok(syn::Meta::List(list)) => {
                     if let Some(ident) = list.path.get_ident() {
-                        if let Ok(mt) = MethodType::from_str(&*ident.to_string()) {
+                        if let Ok(mt) = MethodType::from_str(&ident.to_string()) {
                             method_type = Some(mt);
                             match Args::new(list.nested.into_iter().collect()) {
                                 Ok(ar) => {
@@ -188,7 +249,7 @@ impl Method {
                 }
                 Ok(syn::Meta::Path(path)) => {
                     if let Some(ident) = path.get_ident() {
-                        if let Ok(mt) = MethodType::from_str(&*ident.to_string()) {
+                        if let Ok(mt) = MethodType::from_str(&ident.to_string()) {
                             method_type = Some(mt);
                             return false;
                         }
@@ -230,38 +291,96 @@ impl ToTokens for Method {
             impl_item,
         } = self;
         let target = &impl_item.sig.ident;
+        let handler = match impl_item.sig.receiver() {
+            // Methods on `&self` get a shim that extracts the controller alongside the arguments.
+            Some(_) => {
+                let types: Vec<&syn::Type> = impl_item
+                    .sig
+                    .inputs
+                    .iter()
+                    .filter_map(|input| match input {
+                        syn::FnArg::Typed(arg) => Some(&*arg.ty),
+                        syn::FnArg::Receiver(_) => None,
+                    })
+                    .collect();
+                let args: Vec<Ident> = (0..types.len())
+                    .map(|i| format_ident!("arg{}", i))
+                    .collect();
+                let call = match impl_item.sig.asyncness {
+                    Some(_) => quote! { this.#target(#(#args),*).await },
+                    None => quote! { this.#target(#(#args),*) },
+                };
+                quote! { |this: Self, #(#args: #types),*| async move { #call } }
+            }
+            None => quote! { Self::#target },
+        };
         let expanded = quote! {
             #[allow(non_snake_case)]
-            fn #name(&self) -> actix_web::Resource {
+            fn #name(controller: actix_web::web::Data<Self>) -> actix_web::Resource {
                 actix_web::web::resource(#path)
+                    .app_data(controller)
                     .guard(actix_web::guard::#method_type())
                     #(.guard(actix_web::guard::fn_guard(#methods)))*
                     #(.wrap(#wrappers))*
-                    .to(Self::#target)
+                    .to(#handler)
             }
         };
         stream.extend(expanded)
     }
 }
 
-#[proc_macro_attribute]
-pub fn client(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let parsed = parse_macro_input!(attr as syn::AttributeArgs);
-    let mut input = parse_macro_input!(item as ItemImpl);
+/// `RouteMeta` values for `handlers`, relative to the scope they register in.
+fn route_meta(handlers: &[Method]) -> Vec<TokenStream2> {
+    handlers
+        .iter()
+        .map(|handler| {
+            let method = handler.method_type.as_method();
+            let path = &handler.args.path;
+            quote! { sept::sept_module::RouteMeta::new(#method, #path) }
+        })
+        .collect()
+}
+
+/// Collects the route methods of an impl block, stripping their method attributes.
+fn route_methods(input: &mut ItemImpl) -> syn::Result<Vec<Method>> {
     let mut handlers = Vec::new();
     for item in &mut input.items {
         if let syn::ImplItem::Method(ref mut item_method) = item {
-            match Method::new(item_method) {
-                Ok(Some(method)) => {
-                    handlers.push(method);
-                }
-                Ok(None) => {}
-                Err(err) => {
-                    return err.to_compile_error().into();
+            if let Some(method) = Method::new(item_method)? {
+                handlers.push(method);
+            }
+        }
+    }
+    Ok(handlers)
+}
+
+/// Extracts a controller from the app data its routes are registered with.
+fn controller_from_request(name: &impl ToTokens) -> TokenStream2 {
+    quote! {
+        #[automatically_derived]
+        impl actix_web::FromRequest for #name {
+            type Error = actix_web::Error;
+            type Future = futures_util::future::Ready<Result<Self, Self::Error>>;
+
+            #[inline]
+            fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+                match req.app_data::<actix_web::web::Data<#name>>() {
+                    Some(st) => futures_util::future::ok(st.get_ref().clone()),
+                    None => panic!("Failed to extract data class."),
                 }
             }
         }
     }
+}
+
+#[proc_macro_attribute]
+pub fn client(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let parsed = parse_macro_input!(attr as syn::AttributeArgs);
+    let mut input = parse_macro_input!(item as ItemImpl);
+    let handlers = match route_methods(&mut input) {
+        Ok(handlers) => handlers,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
     match args::Args::new(parsed) {
         Ok(args::Args {
@@ -270,7 +389,9 @@ pub fn client(attr: TokenStream, item: TokenStream) -> TokenStream {
             wrappers,
         }) => {
             let route_idents: Vec<&syn::Ident> = handlers.iter().map(|x| &x.name).collect();
+            let route_meta = route_meta(&handlers);
             let name = &input.self_ty;
+            let from_request = controller_from_request(name);
 
             let expanded = quote! {
                 #input
@@ -278,31 +399,23 @@ pub fn client(attr: TokenStream, item: TokenStream) -> TokenStream {
                     #(#handlers)*
                 }
 
-                #[automatically_derived]
-                impl actix_web::FromRequest for #name {
-                    type Error = actix_web::Error;
-                    type Future = futures_util::future::Ready<Result<Self, Self::Error>>;
-
-                    #[inline]
-                    fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
-                        match req.app_data::<actix_web::web::Data<#name>>() {
-                            Some(st) => futures_util::future::ok(st.get_ref().clone()),
-                            None => panic!("Failed to extract data class."),
-                        }
-                    }
-                }
+                #from_request
 
                 #[automatically_derived]
                 impl sept::sept_module::ServiceFactory for #name {
                     fn register(&self, app: &mut actix_web::web::ServiceConfig) {
+                        let controller = actix_web::web::Data::new(self.clone());
                         app.service(
                             actix_web::web::scope(#path)
-                            .app_data(actix_web::web::Data::new(self.clone()))
                             #(.guard(actix_web::guard::fn_guard(#methods)))*
                             #(.wrap(#wrappers))*
-                            #(.service(Self::#route_idents(&self)))*
+                            #(.service(Self::#route_idents(controller.clone())))*
                         );
                     }
+
+                    fn routes(&self) -> Vec<sept::sept_module::RouteMeta> {
+                        vec![#(#route_meta.under(#path)),*]
+                    }
                 }
             };
             TokenStream::from(expanded)
@@ -310,3 +423,176 @@ pub fn client(attr: TokenStream, item: TokenStream) -> TokenStream {
         Err(err) => err.to_compile_error().into(),
     }
 }
+
+/// Generates `ServiceFactory::register` for a controller whose routes are declared in a
+/// `#[routes]` impl block. The controller must be `Clone`; each request extracts a clone.
+///
+/// An optional `#[controller("/api")]` attribute mounts the routes under a scope with that
+/// prefix, and accepts the same `method(...)` guards and `wrap(...)` middleware as `#[client]`.
+///
+/// `#[guard(AuthGuard, ...)]` applies `sept::guard::Guard` types, resolved from the graph with
+/// the controller, to every route. Guards run inside any `wrap(...)` middleware and the module's
+/// exception filters, and the module's interceptors inside the guards.
+#[proc_macro_derive(Controller, attributes(controller, guard))]
+pub fn controller(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let name = &ast.ident;
+    let mut scope = None;
+    let mut guards: Vec<syn::Path> = Vec::new();
+    for attr in &ast.attrs {
+        if attr.path.is_ident("guard") {
+            match attr.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+            ) {
+                Ok(paths) => guards.extend(paths),
+                Err(err) => return err.to_compile_error().into(),
+            }
+            continue;
+        }
+        if !attr.path.is_ident("controller") {
+            continue;
+        }
+        let parsed = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => Args::new(list.nested.into_iter().collect()),
+            Ok(syn::Meta::Path(_)) => Args::new(Vec::new()),
+            Ok(meta) => Err(syn::Error::new_spanned(
+                meta,
+                "Attribute controller expects a path prefix.",
+            )),
+            Err(err) => Err(err),
+        };
+        match parsed {
+            Ok(args) => scope = Some(args),
+            Err(err) => return err.to_compile_error().into(),
+        }
+    }
+    let prefix = match &scope {
+        Some(args) => args.path.clone(),
+        None => syn::LitStr::new("", Span::call_site()),
+    };
+    let register = match scope {
+        Some(Args {
+            path,
+            methods,
+            wrappers,
+        }) => quote! {
+            app.service(
+                actix_web::web::scope(#path)
+                #(.guard(actix_web::guard::fn_guard(#methods)))*
+                .wrap(layers.interceptors.clone())
+                .wrap(layers.guards.clone())
+                .wrap(layers.filters.clone())
+                #(.wrap(#wrappers))*
+                .configure(move |cfg| {
+                    <Self as sept::sept_module::Routes>::routes(
+                        cfg,
+                        controller,
+                        &sept::sept_module::RouteLayers::default(),
+                    )
+                })
+            );
+        },
+        None => quote! {
+            <Self as sept::sept_module::Routes>::routes(app, controller, layers);
+        },
+    };
+    let resolve_guards = if guards.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn guards(
+                graph: &mut sept::graph::Graph,
+                imports: &[&sept::graph::Graph],
+            ) -> Result<sept::guard::Guards, sept::error::BuildError> {
+                let guards = sept::guard::Guards::default();
+                #(let guards = guards.resolve::<#guards>(graph, imports)?;)*
+                Ok(guards)
+            }
+        }
+    };
+    let from_request = controller_from_request(name);
+    let expanded = quote! {
+        #from_request
+
+        #[automatically_derived]
+        impl sept::sept_module::ServiceFactory for #name {
+            fn register(&self, app: &mut actix_web::web::ServiceConfig) {
+                self.register_layered(app, &sept::sept_module::RouteLayers::default());
+            }
+
+            #resolve_guards
+
+            fn register_layered(
+                &self,
+                app: &mut actix_web::web::ServiceConfig,
+                layers: &sept::sept_module::RouteLayers,
+            ) {
+                let controller = actix_web::web::Data::new(self.clone());
+                #register
+            }
+
+            fn routes(&self) -> Vec<sept::sept_module::RouteMeta> {
+                <Self as sept::sept_module::Routes>::route_meta()
+                    .into_iter()
+                    .map(|route| route.under(#prefix))
+                    .collect()
+            }
+        }
+    };
+    TokenStream::from(expanded)
+}
+
+/// Declares a controller's routes: each method marked with a method attribute such as
+/// `#[get("/users/{id}")]` is registered as a handler. Methods may take `&self`, in which case
+/// the controller is extracted for every request, along with any actix extractors as arguments,
+/// such as `web::Path`, `web::Query` and `web::Json`.
+///
+/// The controller is the value the graph resolved, with its `Injected` fields, so handlers reach
+/// providers through them, e.g. `self.users.find(id)`. Each request gets a clone, which only
+/// bumps the reference counts of `Arc` fields. Pair it with `#[derive(Controller)]`, which
+/// registers the collected routes.
+#[proc_macro_attribute]
+pub fn routes(_: TokenStream, item: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(item as ItemImpl);
+    let handlers = match route_methods(&mut input) {
+        Ok(handlers) => handlers,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let route_idents: Vec<&syn::Ident> = handlers.iter().map(|x| &x.name).collect();
+    let route_meta = route_meta(&handlers);
+    let name = &input.self_ty;
+    let expanded = quote! {
+        #input
+        impl #name {
+            #(#handlers)*
+        }
+
+        #[automatically_derived]
+        impl sept::sept_module::Routes for #name {
+            fn routes(
+                config: &mut actix_web::web::ServiceConfig,
+                controller: actix_web::web::Data<Self>,
+                layers: &sept::sept_module::RouteLayers,
+            ) {
+                #(
+                    let resource = Self::#route_idents(controller.clone());
+                    if layers.is_empty() {
+                        config.service(resource);
+                    } else {
+                        config.service(
+                            resource
+                                .wrap(layers.interceptors.clone())
+                                .wrap(layers.guards.clone())
+                                .wrap(layers.filters.clone()),
+                        );
+                    }
+                )*
+            }
+
+            fn route_meta() -> Vec<sept::sept_module::RouteMeta> {
+                vec![#(#route_meta),*]
+            }
+        }
+    };
+    TokenStream::from(expanded)
+}
@@ -1,12 +1,57 @@
 use proc_macro2::{Ident, Span};
+use quote::ToTokens;
 use std::collections::HashMap;
-use syn::Meta;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Meta, Token};
 
+#[derive(Default)]
 pub(crate) struct ModuleArgs {
     pub(crate) clients: Vec<syn::Path>,
     pub(crate) imports: Vec<syn::Path>,
     pub(crate) exports: Vec<syn::Path>,
     pub(crate) providers: Vec<syn::Path>,
+    /// Set by a bare `global` key or a separate `#[global]` attribute.
+    pub(crate) global: bool,
+}
+
+/// Parses `#[module(imports = [A, B], providers = [..], exports = [..], controllers = [..])]`,
+/// optionally with a bare `global` key.
+impl Parse for ModuleArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Self::default();
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if key == "global" {
+                args.global = true;
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+                continue;
+            }
+            input.parse::<Token![=]>()?;
+            let content;
+            syn::bracketed!(content in input);
+            let paths = Punctuated::<syn::Path, Token![,]>::parse_terminated(&content)?;
+            let vec = match key.to_string().as_str() {
+                "clients" | "controllers" => &mut args.clients,
+                "imports" => &mut args.imports,
+                "exports" => &mut args.exports,
+                "providers" => &mut args.providers,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        key,
+                        "Unknown attribute key is specified.",
+                    ))
+                }
+            };
+            vec.extend(paths);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
 }
 
 impl ModuleArgs {
@@ -15,6 +60,7 @@ impl ModuleArgs {
         let mut imports = Vec::new();
         let mut exports = Vec::new();
         let mut providers = Vec::new();
+        let mut global = false;
         let mut path_to_vec = HashMap::new();
         let call_site = Span::call_site();
         path_to_vec.insert(Ident::new("clients", call_site), &mut clients);
@@ -42,6 +88,7 @@ impl ModuleArgs {
                         ));
                     }
                 }
+                Ok(Meta::Path(path)) if path.is_ident("global") => global = true,
                 Ok(Meta::Path(path)) => {
                     return Err(syn::Error::new_spanned(
                         path,
@@ -55,16 +102,57 @@ impl ModuleArgs {
             }
         }
         attrs.retain(|attr| {
-            attr.path
-                .get_ident()
-                .and_then(|x| path_to_vec.get(x))
-                .is_none()
+            !attr.path.is_ident("global")
+                && attr
+                    .path
+                    .get_ident()
+                    .and_then(|x| path_to_vec.get(x))
+                    .is_none()
         });
         Ok(Self {
             clients,
             imports,
             exports,
             providers,
+            global,
         })
     }
+
+    /// Adds the entries declared in `other`.
+    pub(crate) fn merge(&mut self, other: Self) {
+        self.clients.extend(other.clients);
+        self.imports.extend(other.imports);
+        self.exports.extend(other.exports);
+        self.providers.extend(other.providers);
+        self.global |= other.global;
+    }
+
+    /// Splits the exports into provided types, including controllers, and re-exported imports,
+    /// failing on any export that's neither provided nor imported.
+    pub(crate) fn split_exports(&self) -> syn::Result<(Vec<&syn::Path>, Vec<&syn::Path>)> {
+        let listed = |paths: &[syn::Path], export: &syn::Path| {
+            let export = export.to_token_stream().to_string();
+            paths
+                .iter()
+                .any(|path| path.to_token_stream().to_string() == export)
+        };
+        let mut provided = Vec::new();
+        let mut modules = Vec::new();
+        for export in &self.exports {
+            if listed(&self.providers, export) || listed(&self.clients, export) {
+                provided.push(export);
+            } else if listed(&self.imports, export) {
+                modules.push(export);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    export,
+                    format!(
+                        "Exported type `{}` must also be listed in providers, controllers or imports.",
+                        export.to_token_stream()
+                    ),
+                ));
+            }
+        }
+        Ok((provided, modules))
+    }
 }
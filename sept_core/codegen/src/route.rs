@@ -32,6 +32,23 @@ impl MethodType {
     }
 }
 
+impl MethodType {
+    /// The method's name as sent on the wire, for `RouteMeta`.
+    pub(crate) fn as_method(&self) -> &'static str {
+        match self {
+            MethodType::Get => "GET",
+            MethodType::Post => "POST",
+            MethodType::Put => "PUT",
+            MethodType::Delete => "DELETE",
+            MethodType::Head => "HEAD",
+            MethodType::Connect => "CONNECT",
+            MethodType::Options => "OPTIONS",
+            MethodType::Trace => "TRACE",
+            MethodType::Patch => "PATCH",
+        }
+    }
+}
+
 impl FromStr for MethodType {
     type Err = ();
 
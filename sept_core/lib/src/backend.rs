@@ -0,0 +1,188 @@
+//! The web frameworks sept can register clients on, picked at compile time by feature: `actix`
+//! (the default) registers [`ServiceFactory`](crate::sept_module::ServiceFactory) clients on an
+//! actix-web `ServiceConfig`, and `axum` registers [`AxumServiceFactory`] clients on an
+//! `axum::Router`. A controller can implement both traits behind the features to serve under
+//! either:
+//!
+//! ```ignore
+//! #[cfg(feature = "actix")]
+//! impl ServiceFactory for HelloController {
+//!     fn register(&self, app: &mut ServiceConfig) {
+//!         app.route("/hello", web::get().to(|| async { "hello" }));
+//!     }
+//! }
+//!
+//! #[cfg(feature = "axum")]
+//! impl AxumServiceFactory for HelloController {
+//!     fn register(self: Arc<Self>, router: axum::Router) -> axum::Router {
+//!         router.route("/hello", axum::routing::get(|| async { "hello" }))
+//!     }
+//! }
+//! ```
+
+use crate::sept_module::ResolvedModule;
+#[cfg(feature = "axum")]
+use crate::{
+    error::BuildError,
+    sept_module::{ApplicationContext, Module, ModuleFactory},
+};
+use std::sync::Arc;
+
+/// A web framework the clients of a built module tree register on.
+pub trait WebBackend {
+    /// What the framework's clients register their routes on.
+    type Router;
+
+    /// Registers the clients of `module` and of every module it transitively imports on
+    /// `router`, each under its module's mount prefix.
+    fn mount(module: &Arc<ResolvedModule>, router: &mut Self::Router);
+}
+
+/// The actix-web backend, mounting the clients registered with `Module::client`.
+#[cfg(feature = "actix")]
+pub struct Actix;
+
+#[cfg(feature = "actix")]
+impl WebBackend for Actix {
+    type Router = actix_web::web::ServiceConfig;
+
+    fn mount(module: &Arc<ResolvedModule>, config: &mut Self::Router) {
+        use crate::{health::HealthRegistry, scope::ScopeRegistry};
+        use actix_web::web::Data;
+
+        config.app_data(Data::new(ScopeRegistry::collect(module)));
+        config.app_data(Data::new(HealthRegistry::collect(module)));
+        crate::sept_application::SeptApplication::configure(module.clone(), config);
+    }
+}
+
+/// A client whose routes register on an `axum::Router`, with the `axum` feature. Register one
+/// with `Module::axum_client::<T>()`.
+#[cfg(feature = "axum")]
+pub trait AxumServiceFactory: Send + Sync {
+    /// Adds this client's routes to `router`. Handlers that need the client can capture a clone
+    /// of `self`.
+    fn register(self: Arc<Self>, router: axum::Router) -> axum::Router;
+}
+
+/// The axum backend, mounting the clients registered with `Module::axum_client`.
+#[cfg(feature = "axum")]
+pub struct Axum;
+
+#[cfg(feature = "axum")]
+impl Axum {
+    /// Builds the module tree rooted at `T` and returns a router serving every axum client in
+    /// it. Like `sept::bootstrap`, this runs no lifecycle hooks.
+    pub fn router<T: ModuleFactory + 'static>() -> Result<axum::Router, BuildError> {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(Module::from_factory::<T>().try_build(&mut ctx)?);
+        let mut router = axum::Router::new();
+        Self::mount(&module, &mut router);
+        Ok(router)
+    }
+}
+
+#[cfg(feature = "axum")]
+impl WebBackend for Axum {
+    type Router = axum::Router;
+
+    fn mount(module: &Arc<ResolvedModule>, router: &mut Self::Router) {
+        for (prefix, clients) in module.mounted_axum_clients() {
+            let routes = clients
+                .into_iter()
+                .fold(axum::Router::new(), |routes, client| {
+                    client.register(routes)
+                });
+            let mounted = std::mem::take(router);
+            // axum rejects nesting at the root, so unprefixed clients merge instead.
+            *router = if prefix.is_empty() {
+                mounted.merge(routes)
+            } else {
+                mounted.nest(&prefix, routes)
+            };
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "actix", feature = "axum")))]
+mod tests {
+    use super::*;
+    use crate as sept;
+    #[cfg(feature = "actix")]
+    use crate::sept_module::ApplicationContext;
+    use crate::sept_module::{Module, ModuleFactory};
+    use crate::Injectable;
+
+    #[derive(Injectable)]
+    struct Greeting;
+
+    #[derive(Injectable)]
+    struct HelloController {
+        _greeting: Arc<Greeting>,
+    }
+
+    #[cfg(feature = "actix")]
+    impl crate::sept_module::ServiceFactory for HelloController {
+        fn register(&self, app: &mut actix_web::web::ServiceConfig) {
+            app.route("/hello", actix_web::web::get().to(|| async { "hello" }));
+        }
+    }
+
+    #[cfg(feature = "axum")]
+    impl AxumServiceFactory for HelloController {
+        fn register(self: Arc<Self>, router: axum::Router) -> axum::Router {
+            router.route("/hello", axum::routing::get(|| async { "hello" }))
+        }
+    }
+
+    fn hello_module() -> Module {
+        Module::new().provide::<Greeting>().mount_prefix("/v1")
+    }
+
+    #[cfg(feature = "actix")]
+    struct ActixModule;
+    #[cfg(feature = "actix")]
+    impl ModuleFactory for ActixModule {
+        fn get_module() -> Module {
+            hello_module().client::<HelloController>()
+        }
+    }
+
+    #[cfg(feature = "axum")]
+    struct AxumModule;
+    #[cfg(feature = "axum")]
+    impl ModuleFactory for AxumModule {
+        fn get_module() -> Module {
+            hello_module().axum_client::<HelloController>()
+        }
+    }
+
+    #[cfg(feature = "actix")]
+    #[actix_rt::test]
+    async fn test_actix_backend_mounts_clients() {
+        use actix_web::{test, App};
+
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(Module::from_factory::<ActixModule>().build(&mut ctx));
+        let app = test::init_service(App::new().configure(|cfg| Actix::mount(&module, cfg))).await;
+        let req = test::TestRequest::get().uri("/v1/hello").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "hello");
+    }
+
+    #[cfg(feature = "axum")]
+    #[tokio::test]
+    async fn test_axum_backend_mounts_clients() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let router = Axum::router::<AxumModule>().unwrap();
+        let req = Request::get("/v1/hello").body(Body::empty()).unwrap();
+        let res = router.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), 200);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "hello");
+    }
+}
@@ -0,0 +1,343 @@
+use crate::error::{BuildError, TypeInfo};
+use crate::sept_module::Module;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::any::TypeId;
+use std::sync::Arc;
+
+/// Loads a typed configuration struct from environment variables.
+pub struct ConfigModule;
+
+impl ConfigModule {
+    /// A global module providing and exporting `Arc<C>`, deserialized from the environment when
+    /// the module is built. Field names match variable names case-insensitively, so `port`
+    /// reads `PORT`. A missing or malformed variable fails the build with
+    /// [`BuildError::InvalidConfig`].
+    pub fn from_env<C>() -> Module
+    where
+        C: DeserializeOwned + Send + Sync + 'static,
+    {
+        Self::load::<C>("", envy::from_env::<C>)
+    }
+
+    /// Like [`ConfigModule::from_env`], but only considers variables starting with `prefix`,
+    /// which is stripped before matching, so with `"APP_"` the field `port` reads `APP_PORT`.
+    pub fn from_env_prefixed<C>(prefix: &'static str) -> Module
+    where
+        C: DeserializeOwned + Send + Sync + 'static,
+    {
+        Self::load::<C>(prefix, move || envy::prefixed(prefix).from_env::<C>())
+    }
+
+    /// A module contributing `part` to the `C` that [`ConfigModule::for_root`] provides, so each
+    /// feature module can declare the section of an application-wide config it owns:
+    ///
+    /// ```ignore
+    /// let part = json!({ "cache": { "ttl": 60 } });
+    /// Module::new()
+    ///     .import_module(ConfigModule::for_feature::<AppConfig>(part))
+    ///     .provide::<CacheService>()
+    /// ```
+    ///
+    /// `part` may be any value that serializes to an object, such as a struct holding one
+    /// section. It's contributed when this module is built, once per context for equal parts; a
+    /// part that doesn't serialize, or that's contributed after `for_root::<C>` was built and
+    /// could no longer reach `C`, fails the build with [`BuildError::InvalidConfig`].
+    pub fn for_feature<C: 'static>(part: impl Serialize) -> Module {
+        let part = serde_json::to_value(part);
+        let key = match &part {
+            Ok(part) => part.to_string(),
+            Err(err) => err.to_string(),
+        };
+        Module::dynamic::<Self, _>(&(std::any::type_name::<C>(), key)).on_build(|ctx| {
+            let part = part.map_err(|err| BuildError::InvalidConfig {
+                config: TypeInfo::of::<C>(),
+                message: err.to_string(),
+            })?;
+            let parts = ctx.config_parts.entry(TypeId::of::<C>()).or_default();
+            if parts.rooted {
+                return Err(BuildError::InvalidConfig {
+                    config: TypeInfo::of::<C>(),
+                    message: "a part was contributed after ConfigModule::for_root built the config"
+                        .to_string(),
+                });
+            }
+            merge(&mut parts.merged, part);
+            Ok(())
+        })
+    }
+
+    /// A global module providing and exporting `Arc<C>`, deserialized from the parts that
+    /// [`ConfigModule::for_feature`] modules built before it contributed, so import it after
+    /// them, e.g. last among the root module's imports. Parts merge in the order their modules
+    /// are built, which is import order with each module's own imports first:
+    ///
+    /// - two objects merge key by key, recursively, so parts contributing distinct sections, or
+    ///   distinct keys of one section, all make it into `C`;
+    /// - any other pair of values, including two arrays, an object and a non-object, or `null`
+    ///   and anything, resolves to the later value, which replaces the earlier one whole.
+    ///
+    /// With no parts, `C` is deserialized from an empty object. A missing or malformed field
+    /// fails the build with [`BuildError::InvalidConfig`].
+    pub fn for_root<C>() -> Module
+    where
+        C: DeserializeOwned + Send + Sync + 'static,
+    {
+        Module::dynamic::<Self, _>(&("for_root", std::any::type_name::<C>()))
+            .global()
+            .on_build(|ctx| {
+                ctx.config_parts
+                    .entry(TypeId::of::<C>())
+                    .or_default()
+                    .rooted = true;
+                Ok(())
+            })
+            .provide_val_with(|ctx| {
+                let merged = ctx.config_parts[&TypeId::of::<C>()].merged.clone();
+                serde_json::from_value::<C>(merged)
+                    .map(Arc::new)
+                    .map_err(|err| BuildError::InvalidConfig {
+                        config: TypeInfo::of::<C>(),
+                        message: err.to_string(),
+                    })
+            })
+            .export::<C>()
+    }
+
+    fn load<C>(prefix: &'static str, read: impl FnOnce() -> envy::Result<C> + 'static) -> Module
+    where
+        C: Send + Sync + 'static,
+    {
+        Module::dynamic::<Self, _>(&(std::any::type_name::<C>(), prefix))
+            .global()
+            .provide_val_with(|_| {
+                read()
+                    .map(Arc::new)
+                    .map_err(|err| BuildError::InvalidConfig {
+                        config: TypeInfo::of::<C>(),
+                        message: err.to_string(),
+                    })
+            })
+            .export::<C>()
+    }
+}
+
+/// The parts contributed to one config type.
+#[derive(Clone)]
+pub(crate) struct ConfigParts {
+    merged: Value,
+    /// Whether `ConfigModule::for_root` has built the config, after which parts are rejected.
+    rooted: bool,
+}
+
+impl Default for ConfigParts {
+    fn default() -> Self {
+        Self {
+            merged: Value::Object(Default::default()),
+            rooted: false,
+        }
+    }
+}
+
+/// Merges `part` into `merged` as [`ConfigModule::for_root`] describes.
+fn merge(merged: &mut Value, part: Value) {
+    match (merged, part) {
+        (Value::Object(merged), Value::Object(part)) => {
+            for (key, value) in part {
+                match merged.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        merged.insert(key, value);
+                    }
+                }
+            }
+        }
+        (merged, part) => *merged = part,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_module::{ApplicationContext, ModuleFactory};
+    use crate::Injectable;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize)]
+    struct AppConfig {
+        port: u16,
+        host: String,
+    }
+
+    #[derive(Clone, Injectable)]
+    struct Server {
+        config: Arc<AppConfig>,
+    }
+
+    #[test]
+    fn test_from_env_prefixed_provides_global_config() {
+        std::env::set_var("SEPT_TEST_OK_PORT", "8080");
+        std::env::set_var("SEPT_TEST_OK_HOST", "localhost");
+        std::env::set_var("PORT", "1");
+
+        let mut ctx = ApplicationContext::new();
+        let resolved = Module::new()
+            .import_module(ConfigModule::from_env_prefixed::<AppConfig>(
+                "SEPT_TEST_OK_",
+            ))
+            .provide::<Server>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let server = resolved.graph.get_node::<Arc<Server>>().unwrap();
+        assert_eq!(server.config.port, 8080);
+        assert_eq!(server.config.host, "localhost");
+    }
+
+    #[test]
+    fn test_missing_variable_is_a_build_error() {
+        std::env::set_var("SEPT_TEST_MISSING_PORT", "8080");
+
+        let mut ctx = ApplicationContext::new();
+        let err = Module::new()
+            .import_module(ConfigModule::from_env_prefixed::<AppConfig>(
+                "SEPT_TEST_MISSING_",
+            ))
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        match err.root_cause() {
+            BuildError::InvalidConfig { config, message } => {
+                assert_eq!(config.id, std::any::TypeId::of::<AppConfig>());
+                assert!(message.contains("host"));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    struct ListenAddr(String);
+
+    #[test]
+    fn test_provide_from_config_derives_from_the_loaded_values() {
+        std::env::set_var("SEPT_TEST_A_PORT", "8080");
+        std::env::set_var("SEPT_TEST_A_HOST", "localhost");
+        std::env::set_var("SEPT_TEST_B_PORT", "9090");
+        std::env::set_var("SEPT_TEST_B_HOST", "0.0.0.0");
+
+        let listen_addr = |prefix| {
+            let resolved = Module::new()
+                .import_module(ConfigModule::from_env_prefixed::<AppConfig>(prefix))
+                .provide_from_config(|config: &AppConfig| {
+                    ListenAddr(format!("{}:{}", config.host, config.port))
+                })
+                .try_build(&mut ApplicationContext::new())
+                .unwrap();
+            let addr = resolved.graph.get_node::<Arc<ListenAddr>>().unwrap();
+            addr.0.clone()
+        };
+
+        assert_eq!(listen_addr("SEPT_TEST_A_"), "localhost:8080");
+        assert_eq!(listen_addr("SEPT_TEST_B_"), "0.0.0.0:9090");
+    }
+
+    #[derive(Deserialize)]
+    struct Settings {
+        database: DatabaseSettings,
+        cache: CacheSettings,
+        http: HttpSettings,
+    }
+
+    #[derive(Deserialize)]
+    struct DatabaseSettings {
+        url: String,
+        pool: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct CacheSettings {
+        ttl: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct HttpSettings {
+        port: u16,
+    }
+
+    #[derive(Clone, Injectable)]
+    struct Gateway {
+        settings: Arc<Settings>,
+    }
+
+    struct DatabaseModule;
+    impl ModuleFactory for DatabaseModule {
+        fn get_module() -> Module {
+            let part = json!({ "database": { "url": "postgres://db", "pool": 10 } });
+            Module::new().import_module(ConfigModule::for_feature::<Settings>(part))
+        }
+    }
+
+    struct CacheModule;
+    impl ModuleFactory for CacheModule {
+        fn get_module() -> Module {
+            let part = json!({ "cache": { "ttl": 60 } });
+            Module::new().import_module(ConfigModule::for_feature::<Settings>(part))
+        }
+    }
+
+    struct HttpModule;
+    impl ModuleFactory for HttpModule {
+        fn get_module() -> Module {
+            let part = json!({ "http": { "port": 8080 }, "database": { "pool": 20 } });
+            Module::new().import_module(ConfigModule::for_feature::<Settings>(part))
+        }
+    }
+
+    #[test]
+    fn test_for_root_merges_the_parts_of_each_feature() {
+        let resolved = Module::new()
+            .import::<DatabaseModule>()
+            .import::<CacheModule>()
+            .import::<HttpModule>()
+            .import_module(ConfigModule::for_root::<Settings>())
+            .provide::<Gateway>()
+            .try_build(&mut ApplicationContext::new())
+            .unwrap();
+        let settings = &resolved.graph.get_node::<Arc<Gateway>>().unwrap().settings;
+        assert_eq!(settings.database.url, "postgres://db");
+        assert_eq!(settings.database.pool, 20);
+        assert_eq!(settings.cache.ttl, 60);
+        assert_eq!(settings.http.port, 8080);
+
+        let err = Module::new()
+            .import::<DatabaseModule>()
+            .import_module(ConfigModule::for_root::<Settings>())
+            .try_build(&mut ApplicationContext::new())
+            .err()
+            .unwrap();
+        assert!(matches!(err.root_cause(), BuildError::InvalidConfig { .. }));
+    }
+
+    #[test]
+    fn test_parts_contributed_after_for_root_are_a_build_error() {
+        #[derive(Deserialize)]
+        struct Settings {}
+
+        struct LateModule;
+        impl ModuleFactory for LateModule {
+            fn get_module() -> Module {
+                let part = json!({ "late": true });
+                Module::new().import_module(ConfigModule::for_feature::<Settings>(part))
+            }
+        }
+
+        let err = Module::new()
+            .import_module(ConfigModule::for_root::<Settings>())
+            .import::<LateModule>()
+            .try_build(&mut ApplicationContext::new())
+            .err()
+            .unwrap();
+        assert!(matches!(err.root_cause(), BuildError::InvalidConfig { .. }));
+    }
+}
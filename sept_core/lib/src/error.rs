@@ -0,0 +1,219 @@
+use std::any::TypeId;
+use std::fmt;
+
+/// A `TypeId` paired with its human-readable name, used to describe failures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TypeInfo {
+    pub id: TypeId,
+    pub name: &'static str,
+}
+
+impl TypeInfo {
+    pub fn of<T: ?Sized + 'static>() -> Self {
+        Self {
+            id: TypeId::of::<T>(),
+            name: std::any::type_name::<T>(),
+        }
+    }
+}
+
+impl fmt::Display for TypeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+#[derive(Debug)]
+pub enum BuildError {
+    /// A provider declared in `module` could not be resolved from the graph.
+    UnresolvedProvider {
+        provider: TypeInfo,
+        module: &'static str,
+    },
+    /// `needed_by` depends on `missing`, which no visible module provides.
+    MissingDependency {
+        needed_by: TypeInfo,
+        missing: TypeInfo,
+    },
+    /// `module` registers `provider` more than once without `Module::replace`.
+    DuplicateProvider {
+        provider: TypeInfo,
+        module: &'static str,
+    },
+    /// The configuration struct `config` couldn't be loaded.
+    InvalidConfig { config: TypeInfo, message: String },
+    /// A module declaring async providers was built synchronously.
+    AsyncProvider {
+        provider: TypeInfo,
+        module: &'static str,
+    },
+    /// `module` exports `export` without providing it or importing a module that exports it.
+    ExportNotProvided {
+        export: TypeInfo,
+        module: &'static str,
+    },
+    /// Providers depend on each other in a loop; `cycle` starts and ends with the same provider.
+    CircularDependency { cycle: Vec<&'static str> },
+    /// `Graph::merge` found `provider` in both graphs under `MergePolicy::Error`.
+    MergeConflict { provider: TypeInfo },
+    /// A module transitively imports itself; `cycle` starts and ends with the same module.
+    CircularImport { cycle: Vec<&'static str> },
+    /// The async factory of `provider` failed on each of its `attempts`; `source` is the last
+    /// failure.
+    ProviderFailed {
+        provider: TypeInfo,
+        attempts: u32,
+        source: crate::lifecycle::InitError,
+    },
+    /// The `OnModuleInit` hook of `provider` failed.
+    InitFailed {
+        provider: TypeInfo,
+        source: crate::lifecycle::InitError,
+    },
+    /// The `ModuleFactory::get_module` of `module` panicked with `message`.
+    ModulePanicked {
+        module: &'static str,
+        message: String,
+    },
+    /// `importer` imports `module` with the version requirement `required`, which the version
+    /// `module` declares doesn't match. `version` is `None` if it declares none.
+    VersionMismatch {
+        importer: &'static str,
+        module: &'static str,
+        required: String,
+        version: Option<String>,
+    },
+    /// `module` declares a version with `Module::version`, or imports with a requirement with
+    /// `Module::import_versioned`, that isn't valid semver; `version` is the string as given.
+    InvalidVersion {
+        module: &'static str,
+        version: String,
+        message: String,
+    },
+    /// Building an imported module failed.
+    ImportFailed {
+        module: &'static str,
+        source: Box<Self>,
+    },
+}
+
+impl BuildError {
+    /// Names of the imported modules leading to the module where the failure occurred.
+    pub fn import_chain(&self) -> Vec<&'static str> {
+        let mut chain = Vec::new();
+        let mut current = self;
+        while let Self::ImportFailed { module, source } = current {
+            chain.push(*module);
+            current = source;
+        }
+        chain
+    }
+
+    /// The innermost error, with any import wrapping removed.
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::ImportFailed { source, .. } => source.root_cause(),
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnresolvedProvider { provider, module } => write!(
+                f,
+                "provider `{}` declared in module `{}` could not be resolved",
+                provider, module
+            ),
+            Self::MissingDependency { needed_by, missing } => write!(
+                f,
+                "`{}` depends on `{}`, which is not provided by any visible module",
+                needed_by, missing
+            ),
+            Self::DuplicateProvider { provider, module } => write!(
+                f,
+                "provider `{}` is registered more than once in module `{}`",
+                provider, module
+            ),
+            Self::InvalidConfig { config, message } => {
+                write!(f, "failed to load config `{}`: {}", config, message)
+            }
+            Self::AsyncProvider { provider, module } => write!(
+                f,
+                "async provider `{}` declared in module `{}` requires an async build",
+                provider, module
+            ),
+            Self::ExportNotProvided { export, module } => write!(
+                f,
+                "module `{}` exports `{}`, which it neither provides nor imports",
+                module, export
+            ),
+            Self::CircularDependency { cycle } => {
+                write!(f, "circular provider dependency: {}", cycle.join(" -> "))
+            }
+            Self::MergeConflict { provider } => {
+                write!(f, "`{}` is provided by both merged graphs", provider)
+            }
+            Self::CircularImport { cycle } => {
+                write!(f, "circular module import: {}", cycle.join(" -> "))
+            }
+            Self::ProviderFailed {
+                provider,
+                attempts,
+                source,
+            } => write!(
+                f,
+                "async provider `{}` failed after {} attempts: {}",
+                provider, attempts, source
+            ),
+            Self::InitFailed { provider, source } => {
+                write!(f, "failed to initialize `{}`: {}", provider, source)
+            }
+            Self::ModulePanicked { module, message } => {
+                write!(f, "declaring module `{}` panicked: {}", module, message)
+            }
+            Self::VersionMismatch {
+                importer,
+                module,
+                required,
+                version,
+            } => write!(
+                f,
+                "module `{}` requires `{}` {}, but it declares {}",
+                importer,
+                module,
+                required,
+                version
+                    .as_ref()
+                    .map_or("no version".to_string(), |v| format!("version {}", v))
+            ),
+            Self::InvalidVersion {
+                module,
+                version,
+                message,
+            } => write!(
+                f,
+                "module `{}` declares the invalid version `{}`: {}",
+                module, version, message
+            ),
+            Self::ImportFailed { .. } => write!(
+                f,
+                "failed to build import {}: {}",
+                self.import_chain().join(" -> "),
+                self.root_cause()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ImportFailed { source, .. } => Some(&**source),
+            Self::InitFailed { source, .. } => Some(&**source),
+            Self::ProviderFailed { source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
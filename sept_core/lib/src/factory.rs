@@ -0,0 +1,85 @@
+use crate::error::{BuildError, TypeInfo};
+use crate::graph::{Graph, Injected};
+use std::sync::{Arc, OnceLock};
+
+/// A handle registered with `Module::provide_on_demand`, constructing a new `T` each time it's
+/// called, e.g. a fresh buffer or builder per request.
+///
+/// Inject it as an `Arc<Factory<T>>` field and call [`Factory::create`] where a `T` is needed.
+/// Unlike a transient, which is constructed once for each consumer as that consumer resolves, the
+/// consumer decides when and how many instances are made. `T`'s dependencies resolve from
+/// everything visible to the declaring module once that module is built, so they're shared
+/// between the instances; only `T` itself is new each time.
+pub struct Factory<T> {
+    view: OnceLock<Arc<Graph>>,
+    module: &'static str,
+    _marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: Injected<Output = T> + 'static> Factory<T> {
+    pub(crate) fn new(module: &'static str) -> Self {
+        Self {
+            view: OnceLock::new(),
+            module,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub(crate) fn bind(&self, view: Arc<Graph>) {
+        let _ = self.view.set(view);
+    }
+
+    /// A new `T`, failing if the declaring module hasn't been built or a dependency is missing.
+    pub fn try_create(&self) -> Result<T, BuildError> {
+        let view = self.view.get().ok_or(BuildError::UnresolvedProvider {
+            provider: TypeInfo::of::<T>(),
+            module: self.module,
+        })?;
+        T::resolve(&mut Graph::new(), &[view])
+    }
+
+    /// Like [`Factory::try_create`], but panics with the error's description on failure.
+    pub fn create(&self) -> T {
+        self.try_create().unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_module::{ApplicationContext, Module};
+    use crate::Injectable;
+
+    #[derive(Injectable)]
+    struct Config;
+
+    #[derive(Injectable)]
+    struct Widget {
+        #[inject]
+        config: Arc<Config>,
+        parts: Vec<&'static str>,
+    }
+
+    #[derive(Injectable)]
+    struct Workshop {
+        widgets: Arc<Factory<Widget>>,
+    }
+
+    #[test]
+    fn test_factory_creates_a_new_instance_per_call() {
+        let mut ctx = ApplicationContext::new();
+        let resolved = Module::new()
+            .provide::<Config>()
+            .provide_on_demand::<Widget>()
+            .provide::<Workshop>()
+            .build(&mut ctx);
+        let workshop = resolved.graph.get_node::<Arc<Workshop>>().unwrap();
+
+        let mut first = workshop.widgets.create();
+        let second = workshop.widgets.create();
+        first.parts.push("gear");
+        assert!(second.parts.is_empty());
+        assert!(Arc::ptr_eq(&first.config, &second.config));
+    }
+}
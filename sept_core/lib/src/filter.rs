@@ -0,0 +1,187 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpResponse};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Maps errors raised while handling a request to responses, e.g. domain errors to status codes
+/// and JSON bodies, so handlers needn't map them one by one.
+///
+/// Register one with `Module::exception_filter::<F>()`; it's resolved like a provider, so it can
+/// depend on other providers, and applies to every route of the module's controllers. Use
+/// [`Error::as_error`] to recognize the error types a filter handles.
+///
+/// A module's filters are tried in declaration order, and the first to return a response wins,
+/// so declare specific filters before catch-alls. Errors no filter handles respond as usual.
+pub trait ExceptionFilter: Send + Sync + 'static {
+    /// The response for `err`, or `None` to leave it to the next filter.
+    fn catch(&self, err: &Error) -> Option<HttpResponse>;
+}
+
+/// The exception filters of a module, applied to its controllers' routes as middleware.
+#[derive(Clone, Default)]
+pub struct ExceptionFilters(Vec<Arc<dyn ExceptionFilter>>);
+
+impl ExceptionFilters {
+    pub(crate) fn push<F: ExceptionFilter>(&mut self, filter: Arc<F>) {
+        self.0.push(filter);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn catch(&self, err: &Error) -> Option<HttpResponse> {
+        self.0.iter().find_map(|filter| filter.catch(err))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ExceptionFilters
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Transform = ExceptionFilterService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, ()>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ExceptionFilterService {
+            service: Rc::new(service),
+            filters: self.clone(),
+        }))
+    }
+}
+
+#[doc(hidden)]
+pub struct ExceptionFilterService<S> {
+    service: Rc<S>,
+    filters: ExceptionFilters,
+}
+
+impl<S, B> Service<ServiceRequest> for ExceptionFilterService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let filters = self.filters.clone();
+        Box::pin(async move {
+            // Handler and extractor errors arrive as responses that carry them.
+            let res = service.call(req).await?;
+            let caught = res.response().error().and_then(|err| filters.catch(err));
+            Ok(match caught {
+                Some(caught) => res.into_response(caught),
+                None => res.map_into_boxed_body(),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_application::SeptApplication;
+    use crate::sept_module::{ApplicationContext, Module};
+    use crate::{routes, Controller, Injectable};
+    use actix_web::{test, web, App, ResponseError};
+
+    #[derive(Debug)]
+    struct UserNotFound(u32);
+
+    impl std::fmt::Display for UserNotFound {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "no user {}", self.0)
+        }
+    }
+
+    impl ResponseError for UserNotFound {}
+
+    struct Messages {
+        not_found: &'static str,
+    }
+
+    #[derive(Injectable)]
+    struct NotFoundFilter {
+        messages: Arc<Messages>,
+    }
+
+    impl ExceptionFilter for NotFoundFilter {
+        fn catch(&self, err: &Error) -> Option<HttpResponse> {
+            let err = err.as_error::<UserNotFound>()?;
+            Some(HttpResponse::NotFound().json(serde_json::json!({
+                "error": self.messages.not_found,
+                "id": err.0,
+            })))
+        }
+    }
+
+    #[derive(Injectable)]
+    struct CatchAll;
+
+    impl ExceptionFilter for CatchAll {
+        fn catch(&self, _: &Error) -> Option<HttpResponse> {
+            Some(HttpResponse::InternalServerError().body("oops"))
+        }
+    }
+
+    #[derive(Clone, Injectable, Controller)]
+    struct UserController;
+
+    #[routes]
+    impl UserController {
+        #[get("/users/{id}")]
+        async fn get_user(&self, id: web::Path<u32>) -> Result<String, UserNotFound> {
+            Err(UserNotFound(id.into_inner()))
+        }
+
+        #[get("/broken")]
+        async fn broken(&self) -> Result<String, Error> {
+            Err(actix_web::error::ErrorBadRequest("bad"))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_first_matching_filter_maps_the_error() {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(
+            Module::new()
+                .provide_val(Arc::new(Messages {
+                    not_found: "user not found",
+                }))
+                .exception_filter::<NotFoundFilter>()
+                .exception_filter::<CatchAll>()
+                .client::<UserController>()
+                .build(&mut ctx),
+        );
+        let app = test::init_service(
+            App::new().configure(|cfg| SeptApplication::configure(module.clone(), cfg)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/users/7").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 404);
+        let body: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "user not found", "id": 7 })
+        );
+
+        let req = test::TestRequest::get().uri("/broken").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 500);
+        assert_eq!(test::read_body(res).await, "oops");
+    }
+}
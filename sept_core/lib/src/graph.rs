@@ -1,7 +1,45 @@
+//! The dependency graph modules resolve providers into, and [`Injected`], the trait every
+//! provider implements. `#[derive(Injectable)]` implements it for structs of injected fields;
+//! implement it by hand for types the derive can't describe.
+
+use crate::error::{BuildError, TypeInfo};
+use crate::inspect::GraphDescription;
+use crate::sept_module::Scope;
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
 use std::sync::Arc;
 
+thread_local! {
+    /// Providers being constructed on this thread, outermost first, whatever graph they resolve
+    /// from, so a provider that transitively depends on itself fails before overflowing the stack.
+    static RESOLVING: RefCell<Vec<(NodeKey, &'static str)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks `key` as being constructed until dropped.
+struct Resolving;
+
+impl Resolving {
+    fn enter(key: NodeKey, name: &'static str) -> Result<Self, BuildError> {
+        RESOLVING.with_borrow_mut(|stack| {
+            if let Some(start) = stack.iter().position(|(k, _)| *k == key) {
+                let mut cycle: Vec<_> = stack[start..].iter().map(|(_, name)| *name).collect();
+                cycle.push(name);
+                return Err(BuildError::CircularDependency { cycle });
+            }
+            stack.push((key, name));
+            Ok(Self)
+        })
+    }
+}
+
+impl Drop for Resolving {
+    fn drop(&mut self) {
+        RESOLVING.with_borrow_mut(|stack| stack.pop());
+    }
+}
+
 #[derive(Debug)]
 pub struct Value<T>(pub T);
 
@@ -19,51 +57,573 @@ impl<T: Clone> Clone for Value<T> {
     }
 }
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be provided or injected",
+    label = "`{Self}` doesn't implement `Injected`",
+    note = "add `#[derive(Injectable)]` to `{Self}`, or implement `Injected` for it by hand",
+    note = "values built elsewhere can be registered with `Module::provide_val` instead"
+)]
+/// Constructs a provider from the graph of the module providing it.
+///
+/// `#[derive(Injectable)]` implements this for structs whose fields are injected; implement it by
+/// hand for anything else, such as a newtype around a foreign type that's built from other
+/// providers:
+///
+/// ```
+/// use sept::error::{BuildError, TypeInfo};
+/// use sept::graph::{Graph, Injected, NodeKey};
+/// use sept::sept_module::{ApplicationContext, Module, Scope};
+/// use std::net::SocketAddr;
+/// use std::sync::Arc;
+///
+/// struct Settings {
+///     upstream: &'static str,
+/// }
+///
+/// /// `SocketAddr` is foreign, so it's provided wrapped.
+/// struct Upstream(SocketAddr);
+///
+/// impl Injected for Upstream {
+///     type Output = Self;
+///
+///     fn resolve(graph: &mut Graph, imported_graphs: &[&Graph]) -> Result<Self, BuildError> {
+///         let settings = graph
+///             .get_node::<Arc<Settings>>()
+///             .or_else(|| Graph::search_all::<Arc<Settings>>(imported_graphs))
+///             .ok_or(BuildError::MissingDependency {
+///                 needed_by: TypeInfo::of::<Self>(),
+///                 missing: TypeInfo::of::<Settings>(),
+///             })?;
+///         Ok(Self(settings.upstream.parse().unwrap()))
+///     }
+///
+///     fn dependencies() -> Vec<(NodeKey, &'static str)> {
+///         vec![Graph::dependency::<Settings>()]
+///     }
+/// }
+///
+/// let module = Module::new()
+///     .provide::<Upstream>()
+///     .provide_val(Arc::new(Settings { upstream: "10.0.0.1:80" }))
+///     .try_build(&mut ApplicationContext::new())
+///     .unwrap();
+/// assert_eq!(module.scope_of::<Upstream>(), Some(Scope::Singleton));
+/// ```
+///
+/// The graph owns what a provider constructs: `resolve` returns the value, and the module stores
+/// it as the `Arc<T>` node that consumers share. Dependencies are borrowed from the graphs, so a
+/// hand-written impl clones the `Arc`s it keeps and never removes nodes.
 pub trait Injected: Send + Sync {
+    /// What `resolve` constructs, normally `Self`.
     type Output: Injected;
-    fn resolve(graph: &mut Graph, imported_graphs: &[&Graph]) -> Self::Output
+
+    /// Constructs the value, looking its dependencies up first in `graph`, then in each of
+    /// `imported_graphs` in order, taking the first match.
+    ///
+    /// `graph` holds the providers of the declaring module resolved so far. `imported_graphs`
+    /// holds what's visible from outside it: the exports of each of its imports in import order,
+    /// followed by the global providers. `graph` is mutable so that a provider may construct
+    /// a dependency on the spot with [`Graph::resolve`], which inserts it into `graph`.
+    ///
+    /// Return `BuildError::MissingDependency` if a dependency isn't in any of the graphs. The
+    /// build then retries `resolve` once the rest of the module's providers have resolved, so it
+    /// may run more than once and shouldn't have side effects before it succeeds; any other
+    /// error fails the build.
+    fn resolve(graph: &mut Graph, imported_graphs: &[&Graph]) -> Result<Self::Output, BuildError>
     where
         Self: Sized;
+
+    /// The graph key and type name of everything `resolve` looks up, recorded as edges of the
+    /// dependency graph. Providers of these in the same module are resolved first, and the build
+    /// report counts them as used, so a hand-written impl should list what it reads, e.g. with
+    /// [`Graph::dependency`].
+    fn dependencies() -> Vec<(NodeKey, &'static str)>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
 }
 
-impl<T: Send + Sync> Injected for Value<T> {
+// The framework's own impls are marked `do_not_recommend`, so a type that isn't `Injected` gets
+// the `on_unimplemented` notes without a list of impls that are never the one missing.
+#[diagnostic::do_not_recommend]
+impl<T: Send + Sync + 'static> Injected for Value<T> {
     type Output = Self;
-    fn resolve(_graph: &mut Graph, _imported_graphs: &[&Graph]) -> Self::Output {
-        panic!(
-            "Data type has not been provided: {}",
-            std::any::type_name::<T>()
-        )
+    /// Values are only ever provided directly, so resolving one means it was never provided.
+    fn resolve(_graph: &mut Graph, _imported_graphs: &[&Graph]) -> Result<Self, BuildError> {
+        Err(BuildError::MissingDependency {
+            needed_by: TypeInfo::of::<Self>(),
+            missing: TypeInfo::of::<T>(),
+        })
     }
 }
 
+#[diagnostic::do_not_recommend]
 impl<T: Injected<Output = T>> Injected for Arc<T> {
     type Output = Self;
-    fn resolve(graph: &mut Graph, imported_graphs: &[&Graph]) -> Self::Output {
-        T::resolve(graph, imported_graphs).into()
+    fn resolve(graph: &mut Graph, imported_graphs: &[&Graph]) -> Result<Self, BuildError> {
+        T::resolve(graph, imported_graphs).map(Into::into)
+    }
+
+    fn dependencies() -> Vec<(NodeKey, &'static str)> {
+        T::dependencies()
+    }
+}
+
+/// Converts an `Arc<Self>` into an `Arc<U>`, usually an unsizing coercion to a trait object.
+///
+/// Implement it with [`upcast!`](crate::upcast), e.g.
+/// `sept::upcast!(PostgresRepo => dyn Repository)`.
+pub trait Upcast<U: ?Sized> {
+    fn upcast(self: Arc<Self>) -> Arc<U>;
+}
+
+#[macro_export]
+macro_rules! upcast {
+    ($concrete:ty => $($target:ty),+ $(,)?) => {
+        $(
+            impl $crate::graph::Upcast<$target> for $concrete {
+                fn upcast(self: ::std::sync::Arc<Self>) -> ::std::sync::Arc<$target> {
+                    self
+                }
+            }
+        )+
+    };
+}
+
+/// Names a collection of providers contributed with `Module::provide_multi`.
+pub trait MultiToken: 'static {
+    type Item: ?Sized + Send + Sync + 'static;
+}
+
+/// Every provider contributed to the collection named by `Tag`, injectable as a field. Items are
+/// sorted by the order they were contributed with, lowest first.
+pub struct Multi<Tag: MultiToken> {
+    items: Vec<Arc<Tag::Item>>,
+    /// The order of each item, parallel to `items`.
+    orders: Vec<i32>,
+}
+
+impl<Tag: MultiToken> std::ops::Deref for Multi<Tag> {
+    type Target = [Arc<Tag::Item>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<Tag: MultiToken> Clone for Multi<Tag> {
+    fn clone(&self) -> Self {
+        Self {
+            items: self.items.clone(),
+            orders: self.orders.clone(),
+        }
+    }
+}
+
+impl<Tag: MultiToken> Multi<Tag> {
+    pub fn into_vec(self) -> Vec<Arc<Tag::Item>> {
+        self.items
+    }
+
+    /// The `Tag` collections of `graphs` merged into one, or `None` if none of them holds one.
+    /// A `Multi<Tag>` field resolves this way from its graph and imports, so a module sees
+    /// every import's items whether or not it contributes any itself.
+    pub fn collect<'a>(graphs: impl IntoIterator<Item = &'a Graph>) -> Option<Self> {
+        let mut found = false;
+        let mut multi = Self {
+            items: Vec::new(),
+            orders: Vec::new(),
+        };
+        for graph in graphs {
+            if let Some(other) = graph.get_node::<Self>() {
+                multi.merge(other);
+                found = true;
+            }
+        }
+        found.then_some(multi)
+    }
+
+    /// Inserts the items of `other` that aren't already in the collection, by pointer, keeping
+    /// their orders.
+    fn merge(&mut self, other: &Self) {
+        for (order, item) in other.orders.iter().zip(&other.items) {
+            if !self.items.iter().any(|i| Arc::ptr_eq(i, item)) {
+                self.insert(*order, item.clone());
+            }
+        }
+    }
+
+    /// Inserts `item` after every item whose order is at most `order`, so items of equal order
+    /// keep the order they were contributed in.
+    fn insert(&mut self, order: i32, item: Arc<Tag::Item>) {
+        let at = self.orders.partition_point(|&o| o <= order);
+        self.items.insert(at, item);
+        self.orders.insert(at, order);
+    }
+}
+
+/// Marks a zero-sized type as an injection token, which tells providers of the same type apart
+/// like a provider name, but checked by the compiler: `struct CacheRedis; impl Token for
+/// CacheRedis {}`.
+pub trait Token: 'static {}
+
+/// The `T` provided under the token `K` with `Module::provide_with_token::<K, T>()`, injectable
+/// as a field of this type. Dereferences to `T`.
+pub struct Tokened<K: Token, T> {
+    value: Arc<T>,
+    token: PhantomData<fn() -> K>,
+}
+
+impl<K: Token, T> Tokened<K, T> {
+    pub fn get(&self) -> &Arc<T> {
+        &self.value
+    }
+}
+
+impl<K: Token, T> std::ops::Deref for Tokened<K, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<K: Token, T> Clone for Tokened<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            token: PhantomData,
+        }
+    }
+}
+
+#[diagnostic::do_not_recommend]
+impl<K: Token, T: Injected<Output = T>> Injected for Tokened<K, T> {
+    type Output = Self;
+
+    fn resolve(graph: &mut Graph, imported_graphs: &[&Graph]) -> Result<Self, BuildError> {
+        Ok(Self {
+            value: Arc::new(T::resolve(graph, imported_graphs)?),
+            token: PhantomData,
+        })
+    }
+
+    fn dependencies() -> Vec<(NodeKey, &'static str)> {
+        T::dependencies()
+    }
+}
+
+/// Everything the module `M` exports, injectable as a field by providers of a module that
+/// imports `M` with `Module::import::<M>()`, e.g. for a façade that aggregates whatever `M`
+/// offers. Exports added to `M` later show up without changing the consumer.
+pub struct Exports<M> {
+    graph: Graph,
+    module: PhantomData<fn() -> M>,
+}
+
+impl<M: 'static> Exports<M> {
+    pub(crate) fn new(graph: Graph) -> Self {
+        Self {
+            graph,
+            module: PhantomData,
+        }
+    }
+
+    /// The exported `Arc<T>`, if `M` exports one.
+    pub fn get<T: ?Sized + Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.graph.get_node::<Arc<T>>().cloned()
+    }
+
+    /// Type names of the exports, in the order they were resolved.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.graph.provided_types()
+    }
+
+    /// Each export's type name with its node, which downcasts to the exported `Arc<T>`.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &(dyn Any + Send + Sync))> + '_ {
+        self.graph
+            .order
+            .iter()
+            .map(|key| (self.graph.labels[key], &*self.graph.map[key]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.graph.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graph.order.is_empty()
+    }
+}
+
+impl<M> Clone for Exports<M> {
+    fn clone(&self) -> Self {
+        Self {
+            graph: self.graph.clone(),
+            module: PhantomData,
+        }
+    }
+}
+
+type Constructor = dyn Fn(&mut Graph, &[&Graph]) -> Result<Box<dyn Any>, BuildError> + Send + Sync;
+
+/// Constructs a transient provider afresh each time it's injected.
+#[derive(Clone)]
+struct Transient {
+    constructor: Arc<Constructor>,
+    /// The keys the constructor resolves, which must stay reachable wherever the transient goes.
+    dependencies: Vec<NodeKey>,
+}
+
+impl std::fmt::Debug for Transient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Transient")
     }
 }
 
+/// Graph nodes are keyed by type, optionally qualified by a provider name.
+pub type NodeKey = (TypeId, Option<&'static str>);
+
+/// How [`Graph::merge`] treats a node both graphs hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Fail with [`BuildError::MergeConflict`], leaving the graph unchanged.
+    Error,
+    /// Keep the node of the graph merged in.
+    LastWins,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Graph {
-    map: HashMap<TypeId, Arc<(dyn Send + Sync + Any)>>,
+    map: HashMap<NodeKey, Arc<dyn Send + Sync + Any>>,
+    /// Keys in the order they were first inserted, which is also dependency order.
+    order: Vec<NodeKey>,
+    /// Type names of the inserted nodes and of the dependencies they were resolved from.
+    labels: HashMap<NodeKey, &'static str>,
+    /// `(dependent, dependency)` pairs recorded as nodes are resolved.
+    edges: Vec<(NodeKey, NodeKey)>,
+    /// Constructors of transient providers, keyed like the nodes they stand in for. Ordered by
+    /// key, so iterating them doesn't vary from run to run the way a `HashMap` would.
+    transients: BTreeMap<NodeKey, Transient>,
 }
 
 impl Graph {
     pub fn new() -> Self {
         Self {
             map: HashMap::new(),
+            order: Vec::new(),
+            labels: HashMap::new(),
+            edges: Vec::new(),
+            transients: BTreeMap::new(),
+        }
+    }
+
+    fn insert<T: Send + Sync + 'static>(&mut self, name: Option<&'static str>, node: Arc<T>) {
+        self.insert_node(Self::key::<T>(name), std::any::type_name::<T>(), node);
+    }
+
+    /// Inserts `node` as `T` without listing it among this graph's providers or passing it on to
+    /// graphs layered over this one.
+    pub(crate) fn provide_hidden<T: Send + Sync + 'static>(&mut self, node: Arc<T>) {
+        let key = Self::key::<T>(None);
+        self.labels.insert(key, std::any::type_name::<T>());
+        self.map.insert(key, node);
+    }
+
+    fn insert_node(&mut self, key: NodeKey, label: &'static str, node: Arc<dyn Send + Sync + Any>) {
+        self.labels.insert(key, label);
+        if self.map.insert(key, node).is_none() {
+            self.order.push(key);
+        }
+    }
+
+    pub(crate) fn node(&self, key: &NodeKey) -> Option<&Arc<dyn Send + Sync + Any>> {
+        self.map.get(key)
+    }
+
+    /// Records that the node at `from`, labelled `label`, was built from `T`'s dependencies.
+    pub(crate) fn record_dependencies<T: Injected>(&mut self, from: NodeKey, label: &'static str) {
+        self.labels.entry(from).or_insert(label);
+        for (dependency, label) in T::dependencies() {
+            self.labels.entry(dependency).or_insert(label);
+            self.edges.push((from, dependency));
+        }
+    }
+
+    /// Node keys in insertion order, followed by the dependents recorded without a node of their
+    /// own, such as clients.
+    pub(crate) fn dependents(&self) -> Vec<NodeKey> {
+        let mut keys = self.order.clone();
+        for (from, _) in &self.edges {
+            if !keys.contains(from) {
+                keys.push(*from);
+            }
         }
+        keys
+    }
+
+    /// Records that the node at `from` was built from the node at `to` without `from` injecting
+    /// it as a field, such as a trait object provided from its implementation.
+    pub(crate) fn record_edge(&mut self, from: NodeKey, to: NodeKey) {
+        self.edges.push((from, to));
+    }
+
+    /// Whether `key` is a node constructed once or a transient constructed per consumer.
+    pub(crate) fn scope_of(&self, key: &NodeKey) -> Option<Scope> {
+        if self.map.contains_key(key) {
+            Some(Scope::Singleton)
+        } else if self.transients.contains_key(key) {
+            Some(Scope::Transient)
+        } else {
+            None
+        }
+    }
+
+    /// Keys of the nodes in this graph, in insertion order.
+    pub(crate) fn node_keys(&self) -> &[NodeKey] {
+        &self.order
+    }
+
+    /// Keys of every node and transient in this graph.
+    pub(crate) fn keys(&self) -> impl Iterator<Item = NodeKey> + '_ {
+        self.order.iter().chain(self.transients.keys()).copied()
+    }
+
+    /// The type name of the node or transient `key`.
+    pub(crate) fn label(&self, key: &NodeKey) -> &'static str {
+        self.labels.get(key).copied().unwrap_or("?")
+    }
+
+    /// The number of nodes provided, the same as the length of [`Graph::provided_types`]. Each
+    /// named instance counts separately from the unnamed one, a [`Multi`] collection counts once
+    /// however many items it holds and transient providers don't count, as the graph holds no
+    /// node for them.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Whether no nodes have been provided.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Type names of the nodes in this graph, in insertion order.
+    pub fn provided_types(&self) -> Vec<&'static str> {
+        self.order.iter().map(|key| self.labels[key]).collect()
+    }
+
+    pub(crate) fn edges(&self) -> &[(NodeKey, NodeKey)] {
+        &self.edges
+    }
+
+    /// The nodes and recorded dependency edges of this graph, with the dependencies resolved
+    /// from other graphs marked as external.
+    pub fn describe(&self) -> GraphDescription {
+        GraphDescription::of_graph(self)
+    }
+
+    /// Renders the nodes and recorded dependency edges in Graphviz `dot` syntax. Dependencies
+    /// resolved from other graphs appear as nodes without a box.
+    pub fn to_dot(&self) -> String {
+        self.describe().to_dot()
+    }
+
+    /// Serializes [`Graph::describe`] as JSON.
+    pub fn to_json(&self) -> String {
+        self.describe().to_json()
+    }
+
+    /// Where `key` falls in insertion order; dependencies always precede their dependents.
+    pub(crate) fn position(&self, key: &NodeKey) -> Option<usize> {
+        self.order.iter().position(|k| k == key)
+    }
+
+    /// The graph key and type name of an injected `Arc<T>`, as listed by
+    /// `Injected::dependencies`.
+    pub fn dependency<T: ?Sized + 'static>() -> (NodeKey, &'static str) {
+        (Self::key::<Arc<T>>(None), std::any::type_name::<Arc<T>>())
+    }
+
+    pub fn key<T: 'static>(name: Option<&'static str>) -> NodeKey {
+        (TypeId::of::<T>(), name)
     }
 
-    pub fn filter_by(&self, set: std::collections::HashSet<TypeId>) -> Self {
+    /// Keeps exactly the nodes and transients in `set`. See [`Graph::dependency_closure`] for
+    /// what a transient in `set` needs alongside it to stay constructible.
+    pub fn filter_by(&self, set: std::collections::HashSet<NodeKey>) -> Self {
         let mut new = self.clone();
-        new.map.retain(|&k, _| set.contains(&k));
+        new.map.retain(|k, _| set.contains(k));
+        new.order.retain(|k| set.contains(k));
+        new.edges.retain(|(from, _)| set.contains(from));
+        new.transients.retain(|k, _| set.contains(k));
         new
     }
 
+    /// Adds the nodes, transients and dependency edges of `other`, which was built separately.
+    /// Nodes only `other` holds follow this graph's in `other`'s order; `policy` decides what
+    /// happens to a node both hold, matched by key, so differently named providers of one type
+    /// don't conflict.
+    pub fn merge(&mut self, mut other: Self, policy: MergePolicy) -> Result<(), BuildError> {
+        let conflicts: Vec<NodeKey> = other
+            .keys()
+            .filter(|key| self.map.contains_key(key) || self.transients.contains_key(key))
+            .collect();
+        if let (MergePolicy::Error, Some(key)) = (policy, conflicts.first()) {
+            return Err(BuildError::MergeConflict {
+                provider: TypeInfo {
+                    id: key.0,
+                    name: other.label(key),
+                },
+            });
+        }
+        for key in &conflicts {
+            self.map.remove(key);
+            self.transients.remove(key);
+            self.order.retain(|k| k != key);
+        }
+        self.edges.retain(|(from, _)| !conflicts.contains(from));
+        for key in std::mem::take(&mut other.order) {
+            let node = other.map.remove(&key).unwrap();
+            self.insert_node(key, other.labels[&key], node);
+        }
+        for (key, label) in other.labels {
+            self.labels.entry(key).or_insert(label);
+        }
+        self.edges.extend(other.edges);
+        self.transients.extend(other.transients);
+        Ok(())
+    }
+
+    /// Copies this graph, filling in any nodes it lacks from `fallbacks` in order.
+    pub(crate) fn layered(&self, fallbacks: &[&Self]) -> Self {
+        let mut view = self.clone();
+        for graph in fallbacks {
+            for key in &graph.order {
+                if !view.map.contains_key(key) {
+                    view.insert_node(*key, graph.labels[key], graph.map[key].clone());
+                }
+            }
+            for (key, transient) in &graph.transients {
+                view.transients
+                    .entry(*key)
+                    .or_insert_with(|| transient.clone());
+            }
+        }
+        view
+    }
+
     pub fn search_all<'a, T: 'static>(graphs: &'a [&Self]) -> Option<&'a T> {
+        Self::search_all_named(graphs, None)
+    }
+
+    pub fn search_all_named<'a, T: 'static>(
+        graphs: &'a [&Self],
+        name: Option<&'static str>,
+    ) -> Option<&'a T> {
         for graph in graphs {
-            if let Some(ret) = graph.get_node::<T>() {
+            if let Some(ret) = graph.get_node_named::<T>(name) {
                 return Some(ret);
             }
         }
@@ -71,46 +631,278 @@ impl Graph {
     }
 
     pub fn get_node<T: 'static>(&self) -> Option<&T> {
+        self.get_node_named(None)
+    }
+
+    /// Looks up the node registered under `name`, or the unnamed node if `name` is `None`.
+    pub fn get_node_named<T: 'static>(&self, name: Option<&'static str>) -> Option<&T> {
         self.map
-            .get(&TypeId::of::<T>())
+            .get(&Self::key::<T>(name))
             .and_then(|boxed| (&**boxed as &(dyn Any + Send + 'static)).downcast_ref())
     }
 
     pub fn get_ptr<T: 'static>(&self) -> Option<Arc<T>> {
-        self.map.get(&TypeId::of::<T>()).and_then(|boxed| {
+        self.map.get(&Self::key::<T>(None)).and_then(|boxed| {
             (&**boxed as &(dyn Any + Send + 'static))
                 .downcast_ref::<Arc<T>>()
                 .cloned()
         })
     }
 
+    /// Whether the graph holds an unnamed `T` node, e.g. `contains::<Arc<Cache>>()`.
     pub fn contains<T: 'static>(&self) -> bool {
-        self.map.get(&TypeId::of::<T>()).is_some()
+        self.contains_named::<T>(None)
+    }
+
+    /// Whether the graph holds a `T` node provided under `name`.
+    pub fn contains_named<T: 'static>(&self, name: Option<&'static str>) -> bool {
+        self.map.contains_key(&Self::key::<T>(name))
     }
 
     pub fn provide<T: Send + Sync + 'static>(&mut self, t: Arc<T>) -> &T {
-        let exists = self.contains::<T>();
+        self.provide_named(None, t)
+    }
+
+    pub fn provide_named<T: Send + Sync + 'static>(
+        &mut self,
+        name: Option<&'static str>,
+        t: Arc<T>,
+    ) -> &T {
+        let exists = self.contains_named::<T>(name);
         if !exists {
-            self.map.insert(TypeId::of::<T>(), t);
+            self.insert(name, t);
+        }
+        self.get_node_named::<T>(name).unwrap()
+    }
+
+    /// Deletes the unnamed `T` node or transient, along with the dependency edges recorded from
+    /// it, returning whether there was one. Edges recorded towards it stay, so a dependent built
+    /// from it still shows the dependency. Kept to the crate, since removing a node that other
+    /// providers were built from leaves them holding an instance the graph no longer has; the
+    /// testing harness uses it to tear down and replace providers.
+    pub(crate) fn remove<T: 'static>(&mut self) -> bool {
+        let key = Self::key::<T>(None);
+        let existed = self.map.remove(&key).is_some() || self.transients.remove(&key).is_some();
+        if existed {
+            self.order.retain(|k| *k != key);
+            self.edges.retain(|(from, _)| *from != key);
+        }
+        existed
+    }
+
+    /// Adds `item` to the `Tag` collection at `order`, after any items of a lower or equal order.
+    /// The first contribution in a graph also collects whatever `imports` already hold for `Tag`,
+    /// keeping their orders, so collections accumulate across modules.
+    pub fn provide_multi<Tag: MultiToken>(
+        &mut self,
+        order: i32,
+        item: Arc<Tag::Item>,
+        imports: &[&Self],
+    ) {
+        let mut multi = match self.get_node::<Multi<Tag>>() {
+            Some(existing) => existing.clone(),
+            None => Multi::collect(imports.iter().copied()).unwrap_or(Multi {
+                items: Vec::new(),
+                orders: Vec::new(),
+            }),
+        };
+        multi.insert(order, item);
+        self.insert(None, Arc::new(multi));
+    }
+
+    /// Registers `T` as transient: rather than holding a node, the graph constructs a new `Arc<T>`
+    /// for every [`Graph::transient`] lookup, resolving `T`'s own dependencies as it goes.
+    pub fn provide_transient<T: Injected<Output = T> + 'static>(&mut self) {
+        self.transients.insert(
+            Self::key::<Arc<T>>(None),
+            Transient {
+                constructor: Arc::new(|graph, imports| {
+                    Ok(Box::new(Arc::new(T::resolve(graph, imports)?)))
+                }),
+                dependencies: T::dependencies().into_iter().map(|(key, _)| key).collect(),
+            },
+        );
+    }
+
+    /// `keys` along with everything a transient among them resolves from this graph, transitively.
+    ///
+    /// A singleton is constructed once, so its dependencies are already baked into its node and
+    /// the closure stops there; a transient is constructed on every injection, so its
+    /// dependencies, and theirs if they are transient too, must come along with it.
+    pub fn dependency_closure(
+        &self,
+        keys: impl IntoIterator<Item = NodeKey>,
+    ) -> std::collections::HashSet<NodeKey> {
+        let mut closure = std::collections::HashSet::new();
+        let mut pending: Vec<NodeKey> = keys.into_iter().collect();
+        while let Some(key) = pending.pop() {
+            if !closure.insert(key) {
+                continue;
+            }
+            if let Some(transient) = self.transients.get(&key) {
+                pending.extend(transient.dependencies.iter().copied());
+            }
+        }
+        closure
+    }
+
+    pub(crate) fn has_transients(&self) -> bool {
+        !self.transients.is_empty()
+    }
+
+    /// Makes this graph's transient constructors resolve their dependencies from `view` rather
+    /// than from the graph that injects them, for exporting transients out of their module.
+    /// `view` only needs the [`Graph::dependency_closure`] of the transients.
+    pub(crate) fn bind_transients(&mut self, view: Arc<Self>) {
+        for transient in self.transients.values_mut() {
+            let constructor = transient.constructor.clone();
+            let view = view.clone();
+            transient.constructor = Arc::new(move |_, _| constructor(&mut (*view).clone(), &[]));
         }
-        self.get_node::<T>().unwrap()
     }
 
+    /// Constructs a new instance of the transient `T` registered in `graph` or in the first of
+    /// `imports` that has one, or returns `None` if `T` isn't transient.
+    pub fn transient<T: 'static>(
+        graph: &mut Self,
+        imports: &[&Self],
+        name: Option<&'static str>,
+    ) -> Option<Result<T, BuildError>> {
+        let key = Self::key::<T>(name);
+        let transient = std::iter::once(&*graph)
+            .chain(imports.iter().copied())
+            .find_map(|g| g.transients.get(&key))?
+            .clone();
+        let _resolving = match Resolving::enter(key, std::any::type_name::<T>()) {
+            Ok(resolving) => resolving,
+            Err(err) => return Some(Err(err)),
+        };
+        let _span = crate::trace::provider(std::any::type_name::<T>());
+        Some((transient.constructor)(graph, imports).map(|value| *value.downcast::<T>().unwrap()))
+    }
+
+    /// The `T` node, constructing it from `imports` only if neither this graph nor any of them
+    /// holds one yet, so a provider with several dependents is constructed once.
     pub fn resolve<'a, T: Injected + Sync + Send + 'static>(
         &'a mut self,
         imports: &'a [&Self],
-    ) -> &'a T {
-        let exists = self.contains::<T>();
+    ) -> Result<&'a T, BuildError> {
+        self.resolve_named(None, imports)
+    }
+
+    pub fn resolve_named<'a, T: Injected + Sync + Send + 'static>(
+        &'a mut self,
+        name: Option<&'static str>,
+        imports: &'a [&Self],
+    ) -> Result<&'a T, BuildError> {
+        if self.contains_named::<T>(name) {
+            return Ok(self.get_node_named::<T>(name).unwrap());
+        }
         for graph in imports {
-            let exists = graph.contains::<T>();
-            if exists {
-                return graph.get_node::<T>().unwrap();
+            if let Some(node) = graph.get_node_named::<T>(name) {
+                return Ok(node);
             }
         }
-        if !exists {
-            let new = T::resolve(self, imports);
-            self.map.insert(TypeId::of::<T>(), Arc::new(new));
+        self.resolve_own(name, imports)
+    }
+
+    /// Like [`Graph::resolve_named`], but a `T` in `imports` doesn't count: unless this graph
+    /// already holds one, `T` is constructed here and shadows the imported node. Its
+    /// dependencies still resolve from `imports`.
+    pub(crate) fn resolve_own<'a, T: Injected + Sync + Send + 'static>(
+        &'a mut self,
+        name: Option<&'static str>,
+        imports: &'a [&Self],
+    ) -> Result<&'a T, BuildError> {
+        if self.contains_named::<T>(name) {
+            return Ok(self.get_node_named::<T>(name).unwrap());
+        }
+        let new = {
+            let _resolving = Resolving::enter(Self::key::<T>(name), std::any::type_name::<T>())?;
+            let _span = crate::trace::provider(std::any::type_name::<T>());
+            T::resolve(self, imports)?
+        };
+        self.insert(name, Arc::new(new));
+        self.record_dependencies::<T>(Self::key::<T>(name), std::any::type_name::<T>());
+        Ok(self.get_node_named::<T>(name).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Config(&'static str);
+    struct Cache;
+    struct Queue;
+
+    fn graph(config: &'static str) -> Graph {
+        let mut graph = Graph::new();
+        graph.provide(Arc::new(Arc::new(Config(config))));
+        graph
+    }
+
+    #[test]
+    fn test_merge_applies_the_conflict_policy() {
+        let mut base = graph("base");
+        base.provide(Arc::new(Arc::new(Cache)));
+        let mut other = graph("other");
+        other.provide(Arc::new(Arc::new(Queue)));
+        other.provide_named(Some("backup"), Arc::new(Arc::new(Cache)));
+
+        let mut merged = base.clone();
+        let err = merged.merge(other.clone(), MergePolicy::Error).unwrap_err();
+        assert!(matches!(
+            err,
+            BuildError::MergeConflict { provider } if provider.id == TypeId::of::<Arc<Config>>()
+        ));
+        assert_eq!(merged.provided_types(), base.provided_types());
+
+        merged.merge(other, MergePolicy::LastWins).unwrap();
+        assert_eq!(merged.get_node::<Arc<Config>>().unwrap().0, "other");
+        assert!(merged.contains::<Arc<Cache>>());
+        assert!(merged.contains_named::<Arc<Cache>>(Some("backup")));
+        assert!(merged.contains::<Arc<Queue>>());
+        assert_eq!(merged.provided_types().len(), 4);
+    }
+
+    #[test]
+    fn test_len_counts_provided_nodes() {
+        struct Clock;
+        impl Injected for Clock {
+            type Output = Self;
+            fn resolve(_: &mut Graph, _: &[&Graph]) -> Result<Self, BuildError> {
+                Ok(Self)
+            }
+        }
+
+        struct Queues;
+        impl MultiToken for Queues {
+            type Item = Queue;
         }
-        self.get_node::<T>().unwrap()
+
+        let mut graph = graph("base");
+        assert!(!graph.is_empty());
+        graph.provide(Arc::new(Arc::new(Cache)));
+        graph.provide_named(Some("backup"), Arc::new(Arc::new(Cache)));
+        graph.provide_multi::<Queues>(0, Arc::new(Queue), &[]);
+        graph.provide_multi::<Queues>(1, Arc::new(Queue), &[]);
+        graph.provide_transient::<Clock>();
+        assert_eq!(graph.len(), 4);
+        assert!(Graph::new().is_empty());
+    }
+
+    #[test]
+    fn test_remove_deletes_the_node() {
+        let mut graph = graph("base");
+        graph.provide(Arc::new(Arc::new(Cache)));
+        assert!(graph.remove::<Arc<Config>>());
+        assert!(graph.get_node::<Arc<Config>>().is_none());
+        assert!(!graph.remove::<Arc<Config>>());
+        assert_eq!(graph.len(), 1);
+        assert!(graph.get_node::<Arc<Cache>>().is_some());
+
+        graph.provide(Arc::new(Arc::new(Config("replaced"))));
+        assert_eq!(graph.get_node::<Arc<Config>>().unwrap().0, "replaced");
     }
 }
@@ -0,0 +1,226 @@
+use crate::error::BuildError;
+use crate::graph::{Graph, Injected};
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpResponse};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Decides whether a request may reach a controller's routes, e.g. by validating its token.
+///
+/// Declare guards with `#[guard(AuthGuard)]` on a `#[derive(Controller)]` struct. They are
+/// resolved like providers when the controller is, so they can depend on other providers, and
+/// run before the handler's extractors. A request failing any guard gets `403 Forbidden`.
+pub trait Guard: Send + Sync + 'static {
+    fn can_activate(&self, req: &ServiceRequest) -> impl Future<Output = bool>;
+}
+
+trait ErasedGuard: Send + Sync {
+    fn can_activate<'a>(&'a self, req: &'a ServiceRequest) -> LocalBoxFuture<'a, bool>;
+}
+
+impl<G: Guard> ErasedGuard for G {
+    fn can_activate<'a>(&'a self, req: &'a ServiceRequest) -> LocalBoxFuture<'a, bool> {
+        Box::pin(Guard::can_activate(self, req))
+    }
+}
+
+/// The guards of a controller, applied to its routes as middleware. A request must pass every
+/// guard, checked in declaration order; the first failure stops the rest from running.
+#[derive(Clone, Default)]
+pub struct Guards(Vec<Arc<dyn ErasedGuard>>);
+
+impl Guards {
+    /// Adds the guard `G`, reusing a visible `Arc<G>` provider or constructing one in `graph`.
+    pub fn resolve<G>(mut self, graph: &mut Graph, imports: &[&Graph]) -> Result<Self, BuildError>
+    where
+        G: Guard + Injected<Output = G>,
+    {
+        let guard = graph.resolve::<Arc<G>>(imports)?.clone();
+        self.0.push(guard);
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Guards
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = GuardService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, ()>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(GuardService {
+            service: Rc::new(service),
+            guards: self.clone(),
+        }))
+    }
+}
+
+#[doc(hidden)]
+pub struct GuardService<S> {
+    service: Rc<S>,
+    guards: Guards,
+}
+
+impl<S, B> Service<ServiceRequest> for GuardService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let guards = self.guards.clone();
+        Box::pin(async move {
+            for guard in &guards.0 {
+                if !guard.can_activate(&req).await {
+                    let res = HttpResponse::Forbidden().finish();
+                    return Ok(req.into_response(res).map_into_right_body());
+                }
+            }
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_application::SeptApplication;
+    use crate::sept_module::{ApplicationContext, Module};
+    use crate::{routes, Controller, Injectable};
+    use actix_web::{test, App};
+    use std::any::TypeId;
+
+    #[derive(Clone, Injectable)]
+    struct TokenService;
+
+    impl TokenService {
+        fn is_valid(&self, token: &str) -> bool {
+            token == "secret"
+        }
+    }
+
+    #[derive(Injectable)]
+    struct AuthGuard {
+        tokens: Arc<TokenService>,
+    }
+
+    impl Guard for AuthGuard {
+        async fn can_activate(&self, req: &ServiceRequest) -> bool {
+            req.headers()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|token| self.tokens.is_valid(token))
+        }
+    }
+
+    #[derive(Injectable)]
+    struct GetOnly;
+
+    impl Guard for GetOnly {
+        async fn can_activate(&self, req: &ServiceRequest) -> bool {
+            req.method() == actix_web::http::Method::GET
+        }
+    }
+
+    #[derive(Clone, Injectable, Controller)]
+    #[guard(AuthGuard, GetOnly)]
+    struct SecretController;
+
+    #[routes]
+    impl SecretController {
+        #[get("/secret")]
+        fn secret(&self) -> &'static str {
+            "the secret"
+        }
+
+        #[post("/secret")]
+        fn update(&self) -> &'static str {
+            "updated"
+        }
+    }
+
+    #[derive(Clone, Injectable, Controller)]
+    struct PublicController;
+
+    #[routes]
+    impl PublicController {
+        #[get("/public")]
+        fn public(&self) -> &'static str {
+            "public"
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_guards_must_all_pass() {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(
+            Module::new()
+                .provide::<TokenService>()
+                .client::<SecretController>()
+                .client::<PublicController>()
+                .build(&mut ctx),
+        );
+        let app = test::init_service(
+            App::new().configure(|cfg| SeptApplication::configure(module.clone(), cfg)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/secret")
+            .insert_header(("authorization", "secret"))
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "the secret");
+
+        let req = test::TestRequest::get()
+            .uri("/secret")
+            .insert_header(("authorization", "wrong"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::post()
+            .uri("/secret")
+            .insert_header(("authorization", "secret"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::get().uri("/public").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "public");
+    }
+
+    #[actix_rt::test]
+    async fn test_missing_guard_dependencies_name_the_client() {
+        let err = Module::new()
+            .client::<SecretController>()
+            .try_build(&mut ApplicationContext::new())
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            BuildError::MissingDependency { needed_by, missing }
+                if needed_by.id == TypeId::of::<SecretController>()
+                    && missing.name.contains("TokenService")
+        ));
+    }
+}
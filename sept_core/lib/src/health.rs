@@ -0,0 +1,242 @@
+use crate::error::BuildError;
+use crate::graph::{Graph, Injected};
+use crate::sept_module::{Module, ModuleFactory, ResolvedModule, RouteMeta, ServiceFactory};
+use actix_web::{web, HttpResponse};
+use futures_util::future::{join_all, FutureExt, LocalBoxFuture};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The outcome of a [`HealthCheck`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "error", rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down(String),
+}
+
+impl HealthStatus {
+    pub fn is_up(&self) -> bool {
+        matches!(self, Self::Up)
+    }
+}
+
+/// Reports whether a provider can do its job, e.g. whether a connection pool reaches its database.
+///
+/// Register the check for a provider with `Module::health_check::<T>()`; [`HealthModule`] serves
+/// the results of every registered check at `/health`.
+pub trait HealthCheck: Send + Sync + 'static {
+    fn check(&self) -> impl Future<Output = HealthStatus>;
+
+    /// The name the check is reported under.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// How long `check` may run before it's reported down, so a hung dependency doesn't stall
+    /// the whole report.
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+}
+
+type RunCheck = dyn Fn() -> LocalBoxFuture<'static, HealthStatus> + Send + Sync;
+
+/// A health check registered for a resolved provider.
+#[derive(Clone)]
+pub(crate) struct RegisteredCheck {
+    name: &'static str,
+    timeout: Duration,
+    check: Arc<RunCheck>,
+}
+
+impl RegisteredCheck {
+    pub(crate) fn new<T: HealthCheck>(provider: Arc<T>) -> Self {
+        Self {
+            name: provider.name(),
+            timeout: provider.timeout(),
+            check: Arc::new(move || {
+                let provider = provider.clone();
+                async move { provider.check().await }.boxed_local()
+            }),
+        }
+    }
+
+    async fn run(&self) -> CheckReport {
+        let status = match actix_rt::time::timeout(self.timeout, (self.check)()).await {
+            Ok(status) => status,
+            Err(_) => HealthStatus::Down(format!("timed out after {:?}", self.timeout)),
+        };
+        CheckReport {
+            name: self.name,
+            status,
+        }
+    }
+}
+
+/// The result of one check in a [`HealthReport`].
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckReport {
+    pub name: &'static str,
+    #[serde(flatten)]
+    pub status: HealthStatus,
+}
+
+/// The results of every registered health check, in registration order.
+#[derive(Clone, Debug, Serialize)]
+pub struct HealthReport {
+    /// `"up"` if every check is up, `"down"` otherwise.
+    pub status: &'static str,
+    pub checks: Vec<CheckReport>,
+}
+
+impl HealthReport {
+    pub fn is_up(&self) -> bool {
+        self.checks.iter().all(|check| check.status.is_up())
+    }
+}
+
+/// Every health check in the application, registered as app data so [`HealthModule`] can run
+/// them.
+pub struct HealthRegistry {
+    checks: Vec<RegisteredCheck>,
+}
+
+impl HealthRegistry {
+    /// Collects the health checks of `root` and everything it transitively imports.
+    pub fn collect(root: &ResolvedModule) -> Self {
+        Self {
+            checks: root
+                .tree()
+                .into_iter()
+                .flat_map(|module| module.health_checks.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// Runs every check concurrently, each bounded by its own timeout.
+    pub async fn report(&self) -> HealthReport {
+        let checks = join_all(self.checks.iter().map(RegisteredCheck::run)).await;
+        let up = checks.iter().all(|check| check.status.is_up());
+        HealthReport {
+            status: if up { "up" } else { "down" },
+            checks,
+        }
+    }
+}
+
+async fn health(registry: web::Data<HealthRegistry>) -> HttpResponse {
+    let report = registry.report().await;
+    if report.is_up() {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
+
+struct HealthController;
+
+impl Injected for HealthController {
+    type Output = Self;
+
+    fn resolve(_graph: &mut Graph, _imported_graphs: &[&Graph]) -> Result<Self, BuildError> {
+        Ok(Self)
+    }
+}
+
+impl ServiceFactory for HealthController {
+    fn register(&self, app: &mut web::ServiceConfig) {
+        app.route("/health", web::get().to(health));
+    }
+
+    fn routes(&self) -> Vec<RouteMeta> {
+        vec![RouteMeta::new("GET", "/health")]
+    }
+}
+
+/// Serves `GET /health`, responding `200 OK` with a JSON [`HealthReport`] when every check is up
+/// and `503 Service Unavailable` otherwise.
+pub struct HealthModule;
+
+impl ModuleFactory for HealthModule {
+    fn get_module() -> Module {
+        Module::new().client::<HealthController>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_application::SeptApplication;
+    use crate::sept_module::ApplicationContext;
+    use crate::Injectable;
+    use actix_web::{test, App};
+
+    #[derive(Injectable)]
+    struct Database;
+
+    impl HealthCheck for Database {
+        async fn check(&self) -> HealthStatus {
+            HealthStatus::Up
+        }
+
+        fn name(&self) -> &'static str {
+            "database"
+        }
+    }
+
+    #[derive(Injectable)]
+    struct Cache;
+
+    impl HealthCheck for Cache {
+        async fn check(&self) -> HealthStatus {
+            actix_rt::time::sleep(Duration::from_secs(60)).await;
+            HealthStatus::Up
+        }
+
+        fn name(&self) -> &'static str {
+            "cache"
+        }
+
+        fn timeout(&self) -> Duration {
+            Duration::from_millis(20)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_health_endpoint_aggregates_checks() {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(
+            Module::new()
+                .import::<HealthModule>()
+                .provide::<Database>()
+                .provide::<Cache>()
+                .health_check::<Database>()
+                .health_check::<Cache>()
+                .build(&mut ctx),
+        );
+        let registry = web::Data::new(HealthRegistry::collect(&module));
+        let app = test::init_service(
+            App::new()
+                .app_data(registry)
+                .configure(|cfg| SeptApplication::configure(module.clone(), cfg)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/health").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 503);
+        let body: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "status": "down",
+                "checks": [
+                    { "name": "database", "status": "up" },
+                    { "name": "cache", "status": "down", "error": "timed out after 20ms" },
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Machine-readable descriptions of dependency graphs, collected once and rendered either as
+//! Graphviz `dot` or as JSON for external tooling.
+
+use crate::graph::{Graph, NodeKey};
+use crate::sept_module::{ApplicationContext, ResolvedModule, Scope};
+use std::sync::Arc;
+
+/// The [`GraphDescription::version`] of descriptions built by this release. Bumped whenever a
+/// field is renamed, removed or changes meaning, so consumers can reject shapes they don't know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The nodes and dependency edges of a [`Graph`] or of a whole module tree, as
+/// [`Graph::describe`] and [`ApplicationContext::describe`] collect them.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GraphDescription {
+    /// The schema version, [`SCHEMA_VERSION`] when built by this release.
+    pub version: u32,
+    /// The modules of the tree, root first. Empty when describing a single graph.
+    pub modules: Vec<ModuleDescription>,
+    pub nodes: Vec<NodeDescription>,
+    pub edges: Vec<EdgeDescription>,
+}
+
+/// A module of a described tree.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ModuleDescription {
+    /// The type name of the declaring type, or `<root>`.
+    pub name: String,
+    /// The version declared with `Module::version`.
+    pub version: Option<String>,
+}
+
+/// A provider, or a dependent without a node of its own such as a client.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct NodeDescription {
+    /// The type name of the node, e.g. `alloc::sync::Arc<app::Service>`.
+    pub type_name: String,
+    /// The provider name, for named providers.
+    pub name: Option<String>,
+    /// `None` for dependents without a node and for dependencies resolved from other graphs.
+    pub scope: Option<Scope>,
+    /// The index in [`GraphDescription::modules`] of the module that provides the node. `None`
+    /// for global providers registered outside any module, and when describing a single graph.
+    pub module: Option<usize>,
+    /// Whether the node is only a dependency, resolved from a graph other than the one
+    /// described. Always `false` when describing a module tree.
+    pub external: bool,
+}
+
+/// A dependent and the dependency it was built from, as indices in
+/// [`GraphDescription::nodes`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EdgeDescription {
+    pub from: usize,
+    pub to: usize,
+}
+
+impl NodeDescription {
+    fn new(graph: &Graph, key: &NodeKey, module: Option<usize>, external: bool) -> Self {
+        Self {
+            type_name: graph.label(key).to_string(),
+            name: key.1.map(str::to_string),
+            scope: graph.scope_of(key),
+            module,
+            external,
+        }
+    }
+
+    /// A Graphviz label, with the provider name appended if it has one.
+    fn dot_label(&self) -> String {
+        let label = match &self.name {
+            Some(name) => format!("{} ({})", self.type_name, name),
+            None => self.type_name.clone(),
+        };
+        label.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+}
+
+impl GraphDescription {
+    /// Describes the nodes of `graph` in insertion order, followed by the dependents recorded
+    /// without a node and then the dependencies resolved from other graphs.
+    pub(crate) fn of_graph(graph: &Graph) -> Self {
+        let mut keys = graph.dependents();
+        let owned = keys.len();
+        for (_, to) in graph.edges() {
+            if !keys.contains(to) {
+                keys.push(*to);
+            }
+        }
+        let id = |key: &NodeKey| keys.iter().position(|k| k == key).unwrap();
+        Self {
+            version: SCHEMA_VERSION,
+            modules: Vec::new(),
+            nodes: keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| NodeDescription::new(graph, key, None, i >= owned))
+                .collect(),
+            edges: graph
+                .edges()
+                .iter()
+                .map(|(from, to)| EdgeDescription {
+                    from: id(from),
+                    to: id(to),
+                })
+                .collect(),
+        }
+    }
+
+    /// Describes `root` and every module it transitively imports. Edges point at the provider
+    /// actually injected, across module boundaries; dependencies that didn't resolve are left
+    /// out.
+    pub(crate) fn of_tree(ctx: &ApplicationContext, root: &ResolvedModule) -> Self {
+        let modules = root.tree();
+        let mut description = Self {
+            version: SCHEMA_VERSION,
+            modules: modules
+                .iter()
+                .map(|module| ModuleDescription {
+                    name: module.name.to_string(),
+                    version: module.version.as_ref().map(ToString::to_string),
+                })
+                .collect(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        let mut offsets = Vec::new();
+        for (i, module) in modules.iter().enumerate() {
+            offsets.push(description.nodes.len());
+            for key in module.graph.dependents() {
+                let node = NodeDescription::new(&module.graph, &key, Some(i), false);
+                description.nodes.push(node);
+            }
+        }
+
+        let module_nodes = description.nodes.len();
+        let mut globals: Vec<NodeKey> = Vec::new();
+        for (i, module) in modules.iter().enumerate() {
+            let dependents = module.graph.dependents();
+            for (from, to) in module.graph.edges() {
+                let from = offsets[i] + dependents.iter().position(|k| k == from).unwrap();
+                let Some(node) = std::iter::once(&module.graph)
+                    .chain(crate::sept_module::visible(&module.imports, ctx))
+                    .find_map(|graph| graph.node(to))
+                else {
+                    continue;
+                };
+                let owner = modules.iter().enumerate().find_map(|(m, owner)| {
+                    owner
+                        .graph
+                        .node(to)
+                        .filter(|n| Arc::ptr_eq(n, node))
+                        .map(|_| (m, owner.graph.dependents().iter().position(|k| k == to)))
+                });
+                let to = match owner {
+                    Some((m, Some(n))) => offsets[m] + n,
+                    _ => {
+                        if !globals.contains(to) {
+                            globals.push(*to);
+                            let node = NodeDescription::new(&ctx.global_providers, to, None, false);
+                            description.nodes.push(node);
+                        }
+                        module_nodes + globals.iter().position(|k| k == to).unwrap()
+                    }
+                };
+                description.edges.push(EdgeDescription { from, to });
+            }
+        }
+        description
+    }
+
+    /// Renders the description in Graphviz `dot` syntax. A module tree gets a cluster per
+    /// module, plus one for global providers; a single graph draws the dependencies resolved
+    /// from other graphs as nodes without a box.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph sept {\n");
+        if self.modules.is_empty() {
+            for (i, node) in self.nodes.iter().enumerate() {
+                let shape = if node.external { "plaintext" } else { "box" };
+                out.push_str(&format!(
+                    "  n{} [label=\"{}\", shape={}];\n",
+                    i,
+                    node.dot_label(),
+                    shape
+                ));
+            }
+            for edge in &self.edges {
+                out.push_str(&format!("  n{} -> n{};\n", edge.from, edge.to));
+            }
+            out.push_str("}\n");
+            return out;
+        }
+
+        let ids = self.dot_ids();
+        for (i, module) in self.modules.iter().enumerate() {
+            out.push_str(&format!(
+                "  subgraph cluster_{} {{\n    label=\"{}\";\n",
+                i, module.name
+            ));
+            self.push_cluster_nodes(&mut out, &ids, Some(i));
+            out.push_str("  }\n");
+        }
+        if self.nodes.iter().any(|node| node.module.is_none()) {
+            out.push_str("  subgraph cluster_global {\n    label=\"<global>\";\n");
+            self.push_cluster_nodes(&mut out, &ids, None);
+            out.push_str("  }\n");
+        }
+        for edge in &self.edges {
+            out.push_str(&format!("  {} -> {};\n", ids[edge.from], ids[edge.to]));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// The `dot` id of each node of a module tree: `m{module}_{n}` for the `n`th node of a
+    /// module, and `g_{n}` for the `n`th global provider.
+    fn dot_ids(&self) -> Vec<String> {
+        let mut counts = vec![0; self.modules.len()];
+        let mut globals = 0;
+        self.nodes
+            .iter()
+            .map(|node| match node.module {
+                Some(m) => {
+                    counts[m] += 1;
+                    format!("m{}_{}", m, counts[m] - 1)
+                }
+                None => {
+                    globals += 1;
+                    format!("g_{}", globals - 1)
+                }
+            })
+            .collect()
+    }
+
+    fn push_cluster_nodes(&self, out: &mut String, ids: &[String], module: Option<usize>) {
+        for (node, id) in self.nodes.iter().zip(ids) {
+            if node.module == module {
+                out.push_str(&format!(
+                    "    {} [label=\"{}\", shape=box];\n",
+                    id,
+                    node.dot_label()
+                ));
+            }
+        }
+    }
+
+    /// Serializes the description as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("graph descriptions always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_module::{Module, ModuleFactory};
+    use crate::Injectable;
+
+    #[derive(Clone, Injectable)]
+    struct Pool;
+
+    #[derive(Clone, Injectable)]
+    struct Service {
+        _pool: Arc<Pool>,
+    }
+
+    struct DatabaseModule;
+    impl ModuleFactory for DatabaseModule {
+        fn get_module() -> Module {
+            Module::new()
+                .version("1.2.0")
+                .provide::<Pool>()
+                .export::<Pool>()
+        }
+    }
+
+    #[test]
+    fn test_json_round_trips_node_counts() {
+        let mut ctx = ApplicationContext::default();
+        let root = Module::new()
+            .import::<DatabaseModule>()
+            .provide::<Service>()
+            .try_build(&mut ctx)
+            .unwrap();
+
+        let description = ctx.describe(&root);
+        assert_eq!(description.version, SCHEMA_VERSION);
+        assert_eq!(description.modules[1].version.as_deref(), Some("1.2.0"));
+        assert_eq!(description.nodes.len(), 2);
+        assert_eq!(description.nodes[0].module, Some(0));
+        assert_eq!(description.nodes[0].scope, Some(Scope::Singleton));
+        assert_eq!(description.edges, [EdgeDescription { from: 0, to: 1 }]);
+
+        let parsed: GraphDescription = serde_json::from_str(&ctx.to_json(&root)).unwrap();
+        assert_eq!(parsed, description);
+        let reparsed: GraphDescription = serde_json::from_str(&parsed.to_json()).unwrap();
+        assert_eq!(reparsed.nodes.len(), description.nodes.len());
+
+        let parsed: GraphDescription = serde_json::from_str(&root.graph.to_json()).unwrap();
+        assert_eq!(parsed.nodes.len(), 2);
+        assert!(parsed.nodes[1].external);
+    }
+}
@@ -1,6 +1,8 @@
+use crate::error::{BuildError, TypeInfo};
 use crate::graph::Graph;
 
 use super::{InstrumentationProvider, LogLevel};
+#[cfg(feature = "actix")]
 use futures_util::future::{ok, Ready};
 use std::sync::Arc;
 
@@ -54,13 +56,18 @@ impl Logger {
     }
 }
 
+#[diagnostic::do_not_recommend]
 impl crate::graph::Injected for Logger {
     type Output = Self;
-    fn resolve(_: &mut crate::graph::Graph, _: &[&Graph]) -> Self {
-        panic!("Logger not configured in application!")
+    fn resolve(_: &mut crate::graph::Graph, _: &[&Graph]) -> Result<Self, BuildError> {
+        Err(BuildError::MissingDependency {
+            needed_by: TypeInfo::of::<Self>(),
+            missing: TypeInfo::of::<dyn InstrumentationProvider>(),
+        })
     }
 }
 
+#[cfg(feature = "actix")]
 impl actix_web::FromRequest for Logger {
     type Error = actix_web::Error;
     type Future = Ready<Result<Self, Self::Error>>;
@@ -0,0 +1,252 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Wraps the handling of a request, with logic before and after it, like a NestJS interceptor.
+///
+/// Register one with `Module::interceptor::<I>()`; it's resolved like a provider, so it can
+/// depend on other providers, and applies to every route of the module's controllers.
+/// `intercept` hands the request on with [`Next::call`] and may transform the response it gets
+/// back, or respond without calling it at all.
+///
+/// Requests pass through middleware first, then the controller's guards, then interceptors in
+/// declaration order, the first declared outermost, and finally the handler.
+pub trait Interceptor: Send + Sync + 'static {
+    fn intercept(
+        &self,
+        req: ServiceRequest,
+        next: Next,
+    ) -> impl Future<Output = Result<ServiceResponse, Error>>;
+}
+
+trait ErasedInterceptor: Send + Sync {
+    fn intercept(
+        &self,
+        req: ServiceRequest,
+        next: Next,
+    ) -> LocalBoxFuture<'_, Result<ServiceResponse, Error>>;
+}
+
+impl<I: Interceptor> ErasedInterceptor for I {
+    fn intercept(
+        &self,
+        req: ServiceRequest,
+        next: Next,
+    ) -> LocalBoxFuture<'_, Result<ServiceResponse, Error>> {
+        Box::pin(Interceptor::intercept(self, req, next))
+    }
+}
+
+/// The interceptors of a module, applied to its controllers' routes as middleware.
+#[derive(Clone, Default)]
+pub struct Interceptors(Vec<Arc<dyn ErasedInterceptor>>);
+
+impl Interceptors {
+    pub(crate) fn push<I: Interceptor>(&mut self, interceptor: Arc<I>) {
+        self.0.push(interceptor);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+type Handle = dyn Fn(ServiceRequest) -> LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
+
+struct Chain {
+    interceptors: Interceptors,
+    handler: Rc<Handle>,
+}
+
+/// The rest of the chain after an interceptor: the interceptors declared after it, then the
+/// handler.
+pub struct Next {
+    chain: Rc<Chain>,
+    index: usize,
+}
+
+impl Next {
+    pub async fn call(self, req: ServiceRequest) -> Result<ServiceResponse, Error> {
+        match self.chain.interceptors.0.get(self.index).cloned() {
+            Some(interceptor) => {
+                let next = Self {
+                    chain: self.chain,
+                    index: self.index + 1,
+                };
+                interceptor.intercept(req, next).await
+            }
+            None => (self.chain.handler)(req).await,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Interceptors
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Transform = InterceptorService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, ()>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let service = Rc::new(service);
+        let handler = service.clone();
+        let handler: Rc<Handle> = Rc::new(move |req| {
+            let handler = handler.clone();
+            Box::pin(async move {
+                handler
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_boxed_body)
+            })
+        });
+        ready(Ok(InterceptorService {
+            service,
+            chain: Rc::new(Chain {
+                interceptors: self.clone(),
+                handler,
+            }),
+        }))
+    }
+}
+
+#[doc(hidden)]
+pub struct InterceptorService<S> {
+    service: Rc<S>,
+    chain: Rc<Chain>,
+}
+
+impl<S, B> Service<ServiceRequest> for InterceptorService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let next = Next {
+            chain: self.chain.clone(),
+            index: 0,
+        };
+        Box::pin(next.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::guard::Guard;
+    use crate::sept_application::SeptApplication;
+    use crate::sept_module::{ApplicationContext, Module};
+    use crate::{routes, Controller, Injectable};
+    use actix_web::error::ErrorInternalServerError;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+    use actix_web::{test, App, HttpResponse};
+
+    struct Prefix(&'static str);
+
+    /// Wraps the response body as `prefix(body)`.
+    #[derive(Injectable)]
+    struct Envelope {
+        prefix: Arc<Prefix>,
+    }
+
+    impl Interceptor for Envelope {
+        async fn intercept(
+            &self,
+            req: ServiceRequest,
+            next: Next,
+        ) -> Result<ServiceResponse, Error> {
+            let (req, res) = next.call(req).await?.into_parts();
+            let body = actix_web::body::to_bytes(res.into_body())
+                .await
+                .map_err(ErrorInternalServerError)?;
+            let body = format!("{}({})", self.prefix.0, String::from_utf8_lossy(&body));
+            Ok(ServiceResponse::new(req, HttpResponse::Ok().body(body)))
+        }
+    }
+
+    /// Marks the response, to show which interceptors ran.
+    #[derive(Injectable)]
+    struct Stamp;
+
+    impl Interceptor for Stamp {
+        async fn intercept(
+            &self,
+            req: ServiceRequest,
+            next: Next,
+        ) -> Result<ServiceResponse, Error> {
+            let mut res = next.call(req).await?;
+            res.headers_mut().insert(
+                HeaderName::from_static("x-stamp"),
+                HeaderValue::from_static("1"),
+            );
+            Ok(res)
+        }
+    }
+
+    #[derive(Injectable)]
+    struct DenyAdmin;
+
+    impl Guard for DenyAdmin {
+        async fn can_activate(&self, req: &ServiceRequest) -> bool {
+            req.path() != "/admin"
+        }
+    }
+
+    #[derive(Clone, Injectable, Controller)]
+    #[guard(DenyAdmin)]
+    struct PageController;
+
+    #[routes]
+    impl PageController {
+        #[get("/page")]
+        fn page(&self) -> &'static str {
+            "page"
+        }
+
+        #[get("/admin")]
+        fn admin(&self) -> &'static str {
+            "admin"
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_interceptors_wrap_handlers_inside_guards() {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(
+            Module::new()
+                .provide_val(Arc::new(Prefix("data")))
+                .interceptor::<Stamp>()
+                .interceptor::<Envelope>()
+                .client::<PageController>()
+                .build(&mut ctx),
+        );
+        let app = test::init_service(
+            App::new().configure(|cfg| SeptApplication::configure(module.clone(), cfg)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/page").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.headers().contains_key("x-stamp"));
+        assert_eq!(test::read_body(res).await, "data(page)");
+
+        let req = test::TestRequest::get().uri("/admin").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 403);
+        assert!(!res.headers().contains_key("x-stamp"));
+    }
+}
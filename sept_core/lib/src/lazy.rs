@@ -0,0 +1,255 @@
+use crate::error::{BuildError, TypeInfo};
+use crate::graph::{Graph, Injected};
+use crate::sept_module::{ApplicationContext, ContextSnapshot, Module, ResolvedModule};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+/// A provider registered with `Module::provide_lazy`, constructed the first time it's requested
+/// rather than when its module is built.
+///
+/// Inject it as an `Arc<Lazy<T>>` field and call [`Lazy::get`] where `T` is needed. `T` resolves
+/// its dependencies from everything visible to the declaring module once that module is built,
+/// so `get` can only succeed after the build.
+///
+/// The instance is shared across actix workers, and construction is safe under contention: while
+/// one thread constructs `T`, every other first caller blocks until it finishes and then shares
+/// its instance, so `T` is constructed exactly once. If construction fails, nothing is stored and
+/// the next caller tries again. Calls made once `T` exists only read the stored `Arc` and never
+/// block. `T`'s own construction must not request the same `Lazy<T>`, which would deadlock.
+pub struct Lazy<T> {
+    value: OnceLock<Arc<T>>,
+    /// Held while constructing, so concurrent first requests construct `T` only once.
+    init: Mutex<()>,
+    view: OnceLock<Arc<Graph>>,
+    module: &'static str,
+}
+
+impl<T: Injected<Output = T> + 'static> Lazy<T> {
+    pub(crate) fn new(module: &'static str) -> Self {
+        Self {
+            value: OnceLock::new(),
+            init: Mutex::new(()),
+            view: OnceLock::new(),
+            module,
+        }
+    }
+
+    pub(crate) fn bind(&self, view: Arc<Graph>) {
+        let _ = self.view.set(view);
+    }
+
+    /// The shared instance of `T`, constructing it on the first call. Blocks while another thread
+    /// is constructing it.
+    pub fn try_get(&self) -> Result<Arc<T>, BuildError> {
+        if let Some(value) = self.value.get() {
+            return Ok(value.clone());
+        }
+        let _init = self.init.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = self.value.get() {
+            return Ok(value.clone());
+        }
+        let view = self.view.get().ok_or(BuildError::UnresolvedProvider {
+            provider: TypeInfo::of::<T>(),
+            module: self.module,
+        })?;
+        let value = Arc::new(T::resolve(&mut (**view).clone(), &[])?);
+        Ok(self.value.get_or_init(|| value).clone())
+    }
+
+    /// Like [`Lazy::try_get`], but panics with the error's description on failure.
+    pub fn get(&self) -> Arc<T> {
+        self.try_get().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Whether `T` has been constructed yet.
+    pub fn is_initialized(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+/// A module imported with `Module::import_lazy`, whose whole subtree is built the first time one
+/// of its exports is requested through [`LazyModule::get`] or, with the `actix` feature, the first
+/// time a request reaches its mount prefix. Inject it as an `Arc<LazyModule<T>>` field.
+///
+/// The module builds against the global providers and modules that were built when the importing
+/// module was, so modules it shares with the rest of the tree aren't built twice, but a lazily
+/// built module is never cached for other imports of `T`. Building works like [`Lazy`]: one
+/// caller builds while the others block, and a failed build is retried by the next caller. Since
+/// the build is synchronous, async providers fail it with [`BuildError::AsyncProvider`], and
+/// `OnModuleInit` and `OnModuleDestroy` hooks the subtree declares never run.
+pub struct LazyModule<T> {
+    tree: Arc<LazyTree>,
+    module: PhantomData<fn() -> T>,
+}
+
+impl<T> LazyModule<T> {
+    pub(crate) fn new(tree: Arc<LazyTree>) -> Self {
+        Self {
+            tree,
+            module: PhantomData,
+        }
+    }
+
+    /// The exported `Arc<U>`, building the module first if this is the first request.
+    pub fn try_get<U: ?Sized + Send + Sync + 'static>(&self) -> Result<Arc<U>, BuildError> {
+        let module = self.tree.try_build()?;
+        let export = module.graphed_exports.get_node::<Arc<U>>();
+        export.cloned().ok_or(BuildError::UnresolvedProvider {
+            provider: TypeInfo::of::<U>(),
+            module: module.name,
+        })
+    }
+
+    /// Like [`LazyModule::try_get`], but panics with the error's description on failure.
+    pub fn get<U: ?Sized + Send + Sync + 'static>(&self) -> Arc<U> {
+        self.try_get().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Whether the module has been built yet.
+    pub fn is_built(&self) -> bool {
+        self.tree.built.get().is_some()
+    }
+}
+
+/// The state shared by a [`LazyModule`] and the routes registered for it.
+pub(crate) struct LazyTree {
+    get_module: fn() -> Module,
+    /// The mount prefix the module declares, read without building it.
+    #[cfg(feature = "actix")]
+    pub(crate) prefix: &'static str,
+    ctx: ContextSnapshot,
+    built: OnceLock<Arc<ResolvedModule>>,
+    /// Held while building, so concurrent first requests build the module only once.
+    init: Mutex<()>,
+}
+
+impl LazyTree {
+    pub(crate) fn new(get_module: fn() -> Module, ctx: ContextSnapshot) -> Self {
+        Self {
+            get_module,
+            #[cfg(feature = "actix")]
+            prefix: "",
+            ctx,
+            built: OnceLock::new(),
+            init: Mutex::new(()),
+        }
+    }
+
+    #[cfg(feature = "actix")]
+    pub(crate) fn mounted_at(self, prefix: &'static str) -> Self {
+        Self { prefix, ..self }
+    }
+
+    /// The built module, building it on the first call. Blocks while another thread is building
+    /// it.
+    pub(crate) fn try_build(&self) -> Result<Arc<ResolvedModule>, BuildError> {
+        if let Some(module) = self.built.get() {
+            return Ok(module.clone());
+        }
+        let _init = self.init.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(module) = self.built.get() {
+            return Ok(module.clone());
+        }
+        let mut ctx = ApplicationContext::from_snapshot(&self.ctx);
+        let module = Arc::new((self.get_module)().try_build(&mut ctx)?);
+        Ok(self.built.get_or_init(|| module).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_module::{ApplicationContext, Module};
+    use crate::Injectable;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static REPORTS_BUILT: AtomicUsize = AtomicUsize::new(0);
+    static INDEXES_BUILT: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Injectable)]
+    struct Config;
+
+    struct Reports {
+        _config: Arc<Config>,
+    }
+
+    impl Injected for Reports {
+        type Output = Self;
+
+        fn resolve(graph: &mut Graph, imported_graphs: &[&Graph]) -> Result<Self, BuildError> {
+            REPORTS_BUILT.fetch_add(1, Ordering::SeqCst);
+            Ok(Self {
+                _config: graph.resolve::<Arc<Config>>(imported_graphs)?.clone(),
+            })
+        }
+    }
+
+    #[derive(Injectable)]
+    struct Dashboard {
+        reports: Arc<Lazy<Reports>>,
+    }
+
+    #[test]
+    fn test_lazy_provider_constructs_once_on_first_get() {
+        let mut ctx = ApplicationContext::new();
+        let resolved = Module::new()
+            .provide_lazy::<Reports>()
+            .provide::<Dashboard>()
+            .provide::<Config>()
+            .build(&mut ctx);
+        let dashboard = resolved.graph.get_node::<Arc<Dashboard>>().unwrap().clone();
+        assert!(!dashboard.reports.is_initialized());
+        assert_eq!(REPORTS_BUILT.load(Ordering::SeqCst), 0);
+
+        let reports: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| dashboard.reports.get()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(REPORTS_BUILT.load(Ordering::SeqCst), 1);
+        assert!(reports.iter().all(|r| Arc::ptr_eq(r, &reports[0])));
+    }
+
+    struct SearchIndex;
+
+    impl Injected for SearchIndex {
+        type Output = Self;
+
+        fn resolve(_: &mut Graph, _: &[&Graph]) -> Result<Self, BuildError> {
+            INDEXES_BUILT.fetch_add(1, Ordering::SeqCst);
+            // Slow enough that the other threads arrive while construction is in flight.
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            Ok(Self)
+        }
+    }
+
+    #[test]
+    fn test_lazy_provider_constructs_once_under_contention() {
+        const THREADS: usize = 64;
+
+        let mut ctx = ApplicationContext::new();
+        let resolved = Module::new().provide_lazy::<SearchIndex>().build(&mut ctx);
+        let index = resolved
+            .graph
+            .get_node::<Arc<Lazy<SearchIndex>>>()
+            .unwrap()
+            .clone();
+        let start = std::sync::Barrier::new(THREADS);
+
+        let instances: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    s.spawn(|| {
+                        start.wait();
+                        index.get()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(INDEXES_BUILT.load(Ordering::SeqCst), 1);
+        assert!(instances.iter().all(|i| Arc::ptr_eq(i, &instances[0])));
+    }
+}
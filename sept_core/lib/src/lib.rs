@@ -1,11 +1,38 @@
 #![warn(clippy::use_self)]
 
+pub mod backend;
+pub mod config;
+pub mod error;
+pub mod factory;
+#[cfg(feature = "actix")]
+pub mod filter;
+#[cfg(feature = "actix")]
+pub mod guard;
+#[cfg(feature = "actix")]
+pub mod health;
+pub mod inspect;
 pub mod instrumentation;
+#[cfg(feature = "actix")]
+pub mod interceptor;
+pub mod lazy;
+pub mod lifecycle;
+#[cfg(feature = "actix")]
+pub mod middleware;
+pub mod module_ref;
+pub mod prelude;
+pub mod retry;
+#[cfg(feature = "actix")]
+pub mod scope;
+#[cfg(feature = "actix")]
 pub mod sept_application;
 pub mod sept_module;
+pub mod testing;
+mod trace;
+#[cfg(feature = "actix")]
+pub use sept_application::{bootstrap, bootstrap_app, run, SeptBuilder, SeptServer};
 pub use sept_codegen::*;
-#[doc(hidden)]
 pub mod graph;
 
+#[cfg(feature = "actix")]
 #[doc(hidden)]
 pub use actix_rt::System as Runtime;
@@ -0,0 +1,41 @@
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use std::error::Error;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Why an [`OnModuleInit`] hook failed. It's `Send + Sync` so the `BuildError` wrapping it can
+/// be converted to an `io::Error`.
+pub type InitError = Box<dyn Error + Send + Sync>;
+
+/// Runs once every provider in the application has been resolved.
+///
+/// Register the hook for a provider with `Module::on_init::<T>()`; hooks are awaited by
+/// `ApplicationContext::init_all` with each provider's dependencies initialized first. A hook
+/// returning an error, e.g. because a database is unreachable, aborts startup.
+pub trait OnModuleInit: Send + Sync + 'static {
+    fn on_init(&self) -> impl Future<Output = Result<(), InitError>>;
+}
+
+/// Runs when the application shuts down, in reverse dependency order.
+///
+/// Register the hook for a provider with `Module::on_destroy::<T>()`; hooks are awaited by
+/// `ApplicationContext::shutdown`, so a provider is destroyed before anything it depends on.
+pub trait OnModuleDestroy: Send + Sync + 'static {
+    fn on_destroy(&self) -> impl Future<Output = ()>;
+}
+
+pub(crate) type Hook = Box<dyn FnOnce() -> LocalBoxFuture<'static, Result<(), InitError>>>;
+
+pub(crate) fn init_hook<T: OnModuleInit>(provider: Arc<T>) -> Hook {
+    Box::new(move || async move { provider.on_init().await }.boxed_local())
+}
+
+pub(crate) fn destroy_hook<T: OnModuleDestroy>(provider: Arc<T>) -> Hook {
+    Box::new(move || {
+        async move {
+            provider.on_destroy().await;
+            Ok(())
+        }
+        .boxed_local()
+    })
+}
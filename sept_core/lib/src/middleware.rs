@@ -0,0 +1,195 @@
+use actix_service::boxed::{self, BoxService};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web::{self, ServiceConfig};
+use actix_web::Error;
+use std::sync::Arc;
+
+/// The service a middleware registered with `Module::middleware` wraps: the application's clients
+/// and any middleware declared after it.
+pub type MiddlewareService = BoxService<ServiceRequest, ServiceResponse, Error>;
+
+type Wrap = dyn Fn(&mut ServiceConfig, Box<dyn FnOnce(&mut ServiceConfig)>) + Send + Sync;
+
+/// A resolved middleware, which wraps whatever `inner` registers in a scope.
+#[derive(Clone)]
+pub(crate) struct Middleware(Arc<Wrap>);
+
+impl Middleware {
+    pub(crate) fn new<M>(middleware: M) -> Self
+    where
+        M: Transform<
+                MiddlewareService,
+                ServiceRequest,
+                Response = ServiceResponse,
+                Error = Error,
+                InitError = (),
+            > + Send
+            + Sync
+            + 'static,
+    {
+        let middleware = Arc::new(middleware);
+        Self(Arc::new(move |config, inner| {
+            config.service(
+                web::scope("")
+                    .wrap(Shared(middleware.clone()))
+                    .configure(inner),
+            );
+        }))
+    }
+
+    /// Registers everything `inner` registers behind each of `middleware`, the first outermost.
+    pub(crate) fn apply(
+        middleware: Vec<Self>,
+        config: &mut ServiceConfig,
+        inner: Box<dyn FnOnce(&mut ServiceConfig)>,
+    ) {
+        let register = middleware.into_iter().rev().fold(inner, |inner, wrap| {
+            Box::new(move |config: &mut ServiceConfig| (wrap.0)(config, inner))
+        });
+        register(config);
+    }
+}
+
+/// Lets one resolved middleware be shared by every worker, and erases the wrapped service's type.
+struct Shared<M>(Arc<M>);
+
+impl<S, M> Transform<S, ServiceRequest> for Shared<M>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = Error> + 'static,
+    M: Transform<
+        MiddlewareService,
+        ServiceRequest,
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+{
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Transform = M::Transform;
+    type InitError = ();
+    type Future = M::Future;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        self.0.new_transform(boxed::service(service))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_application::SeptApplication;
+    use crate::sept_module::{ApplicationContext, Module, ServiceFactory};
+    use crate::Injectable;
+    use actix_web::dev::forward_ready;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+    use actix_web::{test, App};
+    use futures_util::future::{ready, LocalBoxFuture, Ready};
+
+    struct Greeting(&'static str);
+
+    /// Appends `value` to the response's `x-trace` header.
+    struct Append<S> {
+        service: S,
+        value: &'static str,
+    }
+
+    impl<S> Service<ServiceRequest> for Append<S>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse, Error = Error>,
+        S::Future: 'static,
+    {
+        type Response = ServiceResponse;
+        type Error = Error;
+        type Future = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
+
+        forward_ready!(service);
+
+        fn call(&self, req: ServiceRequest) -> Self::Future {
+            let fut = self.service.call(req);
+            let value = self.value;
+            Box::pin(async move {
+                let mut res = fut.await?;
+                res.headers_mut().append(
+                    HeaderName::from_static("x-trace"),
+                    HeaderValue::from_static(value),
+                );
+                Ok(res)
+            })
+        }
+    }
+
+    #[derive(Injectable)]
+    struct Outer {
+        greeting: Arc<Greeting>,
+    }
+
+    impl Transform<MiddlewareService, ServiceRequest> for Outer {
+        type Response = ServiceResponse;
+        type Error = Error;
+        type Transform = Append<MiddlewareService>;
+        type InitError = ();
+        type Future = Ready<Result<Self::Transform, ()>>;
+
+        fn new_transform(&self, service: MiddlewareService) -> Self::Future {
+            ready(Ok(Append {
+                service,
+                value: self.greeting.0,
+            }))
+        }
+    }
+
+    #[derive(Injectable)]
+    struct Inner;
+
+    impl Transform<MiddlewareService, ServiceRequest> for Inner {
+        type Response = ServiceResponse;
+        type Error = Error;
+        type Transform = Append<MiddlewareService>;
+        type InitError = ();
+        type Future = Ready<Result<Self::Transform, ()>>;
+
+        fn new_transform(&self, service: MiddlewareService) -> Self::Future {
+            ready(Ok(Append {
+                service,
+                value: "inner",
+            }))
+        }
+    }
+
+    #[derive(Injectable)]
+    struct Routes;
+
+    impl ServiceFactory for Routes {
+        fn register(&self, app: &mut web::ServiceConfig) {
+            app.route("/", web::get().to(|| async { "ok" }));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_middleware_is_injected_and_applied_in_order() {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(
+            Module::new()
+                .provide_val(Arc::new(Greeting("hello")))
+                .middleware::<Outer>()
+                .middleware::<Inner>()
+                .client::<Routes>()
+                .build(&mut ctx),
+        );
+        let app = test::init_service(
+            App::new().configure(|cfg| SeptApplication::configure(module.clone(), cfg)),
+        )
+        .await;
+        let res = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert!(res.status().is_success());
+        let trace: Vec<_> = res
+            .headers()
+            .get_all("x-trace")
+            .map(|value| value.to_str().unwrap())
+            .collect();
+        // The outermost middleware sees the response last.
+        assert_eq!(trace, ["inner", "hello"]);
+    }
+}
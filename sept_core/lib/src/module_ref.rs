@@ -0,0 +1,93 @@
+use std::sync::OnceLock;
+
+/// The module a provider is constructed in, injectable as `Arc<ModuleRef>` without being
+/// registered, e.g. so a logger can tag its output with the module it serves.
+///
+/// Every module has its own, so a provider sees the module that declares it rather than the
+/// root, and an exported transient sees the module that exports it.
+pub struct ModuleRef {
+    name: &'static str,
+    providers: OnceLock<Vec<&'static str>>,
+}
+
+impl ModuleRef {
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            providers: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn set_providers(&self, providers: Vec<&'static str>) {
+        let _ = self.providers.set(providers);
+    }
+
+    /// The type name of the module.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Type names of the module's own providers, as `ResolvedModule::list_providers` lists them.
+    /// Empty until the module finishes building, so read it after construction, e.g. while
+    /// handling a request.
+    pub fn providers(&self) -> &[&'static str] {
+        self.providers.get().map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_module::{ApplicationContext, Module, ModuleFactory};
+    use crate::Injectable;
+    use std::sync::Arc;
+
+    #[derive(Injectable)]
+    struct Logger {
+        module: Arc<ModuleRef>,
+    }
+
+    #[derive(Injectable)]
+    struct Users {
+        _logger: Arc<Logger>,
+    }
+
+    struct UserModule;
+    impl ModuleFactory for UserModule {
+        fn get_module() -> Module {
+            Module::new()
+                .provide::<Logger>()
+                .provide::<Users>()
+                .export::<Logger>()
+        }
+    }
+
+    #[derive(Injectable)]
+    struct App {
+        module: Arc<ModuleRef>,
+        logger: Arc<Logger>,
+    }
+
+    #[test]
+    fn test_module_ref_describes_the_constructing_module() {
+        let mut ctx = ApplicationContext::new();
+        let resolved = Module::new()
+            .import::<UserModule>()
+            .provide::<App>()
+            .build(&mut ctx);
+        let app = resolved.graph.get_node::<Arc<App>>().unwrap();
+        assert_eq!(app.module.name(), "<root>");
+        assert_eq!(app.module.providers(), [std::any::type_name::<Arc<App>>()]);
+
+        let users = &app.logger.module;
+        assert!(users.name().ends_with("UserModule"));
+        assert_eq!(
+            users.providers(),
+            [
+                std::any::type_name::<Arc<Logger>>(),
+                std::any::type_name::<Arc<Users>>(),
+            ]
+        );
+    }
+}
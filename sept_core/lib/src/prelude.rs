@@ -0,0 +1,50 @@
+//! The items most applications use, imported together with `use sept::prelude::*;`: the derives
+//! and attributes, the module builder and the traits providers implement or consumers inject.
+//!
+//! Items for wiring by hand, such as [`Graph`](crate::graph::Graph) and the route layers, stay in
+//! their modules.
+
+pub use crate::config::ConfigModule;
+pub use crate::error::BuildError;
+pub use crate::factory::Factory;
+pub use crate::graph::{Exports, Injected, Multi, MultiToken, Token, Tokened, Upcast};
+pub use crate::lazy::{Lazy, LazyModule};
+pub use crate::lifecycle::{OnModuleDestroy, OnModuleInit};
+pub use crate::retry::RetryPolicy;
+pub use crate::sept_module::{ApplicationContext, Module, ModuleFactory, ResolvedModule, Scope};
+pub use crate::{export_all, module, upcast, Injectable};
+
+#[cfg(feature = "actix")]
+pub use crate::scope::{OnRequestEnd, Scoped};
+#[cfg(feature = "actix")]
+pub use crate::sept_application::{bootstrap, run, SeptApplication, SeptServer};
+#[cfg(feature = "actix")]
+pub use crate::sept_module::ServiceFactory;
+#[cfg(feature = "actix")]
+pub use crate::{client, routes, Controller};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use std::sync::Arc;
+
+    #[derive(Injectable)]
+    struct Greeting;
+
+    #[derive(Injectable)]
+    struct Greeter {
+        _greeting: Arc<Greeting>,
+    }
+
+    #[module(providers = [Greeting, Greeter], exports = [Greeter])]
+    struct GreeterModule;
+
+    #[test]
+    fn test_prelude_covers_a_module_declaration() {
+        let module = Module::from_factory::<GreeterModule>()
+            .try_build(&mut ApplicationContext::new())
+            .unwrap();
+        assert!(module.exports::<Greeter>());
+    }
+}
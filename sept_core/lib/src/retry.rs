@@ -0,0 +1,171 @@
+//! Retrying async provider factories at startup, for `Module::provide_async_retry`.
+
+use crate::error::{BuildError, TypeInfo};
+use crate::lifecycle::InitError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// How many times `Module::provide_async_retry` tries an async factory, and how long it waits
+/// between tries:
+///
+/// ```ignore
+/// RetryPolicy { attempts: 5, backoff: Duration::from_millis(200) }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Tries in total, including the first. Zero counts as one.
+    pub attempts: u32,
+    /// The wait after the first failure, doubled after each failure that follows.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// The wait after the `failures`th failure.
+    fn wait(&self, failures: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+    }
+}
+
+/// Awaits `attempt` until it succeeds or `policy` runs out of attempts, emitting a `tracing`
+/// warning for each failure.
+pub(crate) async fn run<T: 'static, E, Fut>(
+    policy: RetryPolicy,
+    mut attempt: impl FnMut() -> Fut,
+) -> Result<T, BuildError>
+where
+    E: Into<InitError>,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let attempts = policy.attempts.max(1);
+    let mut failures = 0;
+    loop {
+        let err = match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err.into(),
+        };
+        failures += 1;
+        if failures == attempts {
+            return Err(BuildError::ProviderFailed {
+                provider: TypeInfo::of::<T>(),
+                attempts,
+                source: err,
+            });
+        }
+        let wait = policy.wait(failures);
+        crate::trace::warn(
+            std::any::type_name::<T>(),
+            format_args!("attempt {failures} of {attempts} failed, retrying in {wait:?}: {err}"),
+        );
+        Delay::new(wait).await;
+    }
+}
+
+/// Resolves once its duration has passed, timed on a thread of its own so that it works on any
+/// executor. Startup retries are rare enough that the thread doesn't matter.
+struct Delay {
+    duration: Duration,
+    /// `None` until first polled.
+    state: Option<Arc<Mutex<Timer>>>,
+}
+
+/// Whether the time is up, and the task to wake when it is.
+type Timer = (bool, Option<Waker>);
+
+impl Delay {
+    fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            state: None,
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        if this.duration.is_zero() {
+            return Poll::Ready(());
+        }
+        let duration = this.duration;
+        let state = this.state.get_or_insert_with(|| {
+            let state = Arc::new(Mutex::new((false, None::<Waker>)));
+            let timer = state.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(duration);
+                let mut timer = timer.lock().unwrap();
+                timer.0 = true;
+                if let Some(waker) = timer.1.take() {
+                    waker.wake();
+                }
+            });
+            state
+        });
+        let mut state = state.lock().unwrap();
+        if state.0 {
+            Poll::Ready(())
+        } else {
+            state.1 = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sept_module::Module;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct Connection {
+        attempt: u32,
+    }
+
+    fn flaky(tries: Arc<AtomicU32>, failures: u32) -> Module {
+        let policy = RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(1),
+        };
+        Module::new().provide_async_retry(
+            move |_| {
+                let attempt = tries.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    if attempt <= failures {
+                        Err("connection refused")
+                    } else {
+                        Ok(Connection { attempt })
+                    }
+                }
+            },
+            policy,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_async_factories_are_retried_until_they_succeed() {
+        let tries = Arc::new(AtomicU32::new(0));
+        let module = flaky(tries.clone(), 2)
+            .try_build_async(&mut Default::default())
+            .await
+            .unwrap();
+        let connection = module.graph.get_node::<Arc<Connection>>().unwrap();
+        assert_eq!(connection.attempt, 3);
+
+        let tries = Arc::new(AtomicU32::new(0));
+        let err = flaky(tries.clone(), 3)
+            .try_build_async(&mut Default::default())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            BuildError::ProviderFailed { attempts: 3, .. }
+        ));
+        assert_eq!(tries.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,515 @@
+use crate::error::{BuildError, TypeInfo};
+use crate::graph::{Graph, Injected, NodeKey};
+use crate::middleware::MiddlewareService;
+use crate::sept_module::ResolvedModule;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::{header::HeaderMap, Method, StatusCode, Uri, Version};
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::{ready, FutureExt, LocalBoxFuture, Ready};
+use std::any::Any;
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::sync::Arc;
+
+type ScopedStep = Arc<dyn Fn(&mut Graph, &[&Graph]) -> Result<(), BuildError> + Send + Sync>;
+
+/// Why an [`OnRequestEnd`] hook failed.
+pub type CleanupError = Box<dyn Error + Send + Sync>;
+
+pub(crate) type Cleanup = Arc<
+    dyn Fn(
+            &Arc<dyn Send + Sync + Any>,
+            StatusCode,
+        ) -> LocalBoxFuture<'static, Result<(), CleanupError>>
+        + Send
+        + Sync,
+>;
+
+/// Runs when a request that constructed this request-scoped provider completes, e.g. to commit a
+/// request's transaction, or roll it back if the response is an error.
+///
+/// Register the hook with `Module::on_request_end::<T>()`. Once the handler has returned, the hooks
+/// of the scoped providers the request constructed are awaited before the response is sent,
+/// dependents first, the reverse of construction order, and the instances are then dropped. A
+/// failing hook doesn't change the response and doesn't stop the remaining hooks; with the
+/// `tracing` feature, its error is emitted as a warning. A handler error still produces a
+/// response, so the hooks see its status, but if a middleware fails the request without one, the
+/// instances are dropped without running their hooks.
+pub trait OnRequestEnd: Send + Sync + 'static {
+    /// `status` is the status of the response the handler produced.
+    fn on_request_end(&self, status: StatusCode) -> impl Future<Output = Result<(), CleanupError>>;
+}
+
+pub(crate) fn cleanup<T: OnRequestEnd>() -> Cleanup {
+    Arc::new(|node, status| {
+        let provider = node.downcast_ref::<Arc<T>>().unwrap().clone();
+        async move { provider.on_request_end(status).await }.boxed_local()
+    })
+}
+
+/// A provider registered with `Module::provide_scoped`, constructed at most once per request.
+#[derive(Clone)]
+pub(crate) struct ScopedProvider {
+    key: NodeKey,
+    info: TypeInfo,
+    dependencies: Vec<NodeKey>,
+    step: ScopedStep,
+    cleanup: Option<Cleanup>,
+    /// Everything visible from the declaring module, which non-scoped dependencies resolve from.
+    singletons: Arc<Graph>,
+}
+
+impl ScopedProvider {
+    pub(crate) fn of<T: Injected<Output = T> + 'static>() -> Self {
+        Self {
+            key: Graph::key::<Arc<T>>(None),
+            info: TypeInfo::of::<T>(),
+            dependencies: T::dependencies().into_iter().map(|(key, _)| key).collect(),
+            step: Arc::new(|graph, singletons| graph.resolve::<Arc<T>>(singletons).map(|_| ())),
+            cleanup: None,
+            singletons: Arc::new(Graph::new()),
+        }
+    }
+
+    pub(crate) fn info(&self) -> TypeInfo {
+        self.info
+    }
+
+    pub(crate) fn set_cleanup(&mut self, cleanup: Cleanup) {
+        self.cleanup = Some(cleanup);
+    }
+
+    pub(crate) fn has_cleanup(&self) -> bool {
+        self.cleanup.is_some()
+    }
+
+    pub(crate) fn with_singletons(mut self, singletons: Arc<Graph>) -> Self {
+        self.singletons = singletons;
+        self
+    }
+}
+
+/// Every request-scoped provider in the application, registered as app data so [`Scoped`] can
+/// construct them.
+pub struct ScopeRegistry {
+    providers: HashMap<NodeKey, ScopedProvider>,
+}
+
+impl ScopeRegistry {
+    /// Collects the scoped providers of `root` and everything it transitively imports. If several
+    /// modules declare the same scoped type, the first one reached wins.
+    pub fn collect(root: &ResolvedModule) -> Self {
+        let mut providers = HashMap::new();
+        for module in root.tree() {
+            for provider in &module.scoped {
+                providers
+                    .entry(provider.key)
+                    .or_insert_with(|| provider.clone());
+            }
+        }
+        Self { providers }
+    }
+
+    /// Runs the [`OnRequestEnd`] hooks of the providers in a finished request's `scope`, latest
+    /// constructed first.
+    async fn end(&self, scope: RequestScope, status: StatusCode) {
+        for key in scope.graph.node_keys().iter().rev() {
+            let Some(provider) = self.providers.get(key) else {
+                continue;
+            };
+            let (Some(cleanup), Some(node)) = (&provider.cleanup, scope.graph.node(key)) else {
+                continue;
+            };
+            if let Err(err) = cleanup(node, status).await {
+                let name = provider.info.name;
+                crate::trace::warn(name, format_args!("request cleanup failed: {err}"));
+            }
+        }
+    }
+
+    /// Resolves the scoped `T` into `graph`, constructing its scoped dependencies first.
+    fn resolve<T: Send + Sync + 'static>(&self, graph: &mut Graph) -> Result<Arc<T>, BuildError> {
+        let key = Graph::key::<Arc<T>>(None);
+        if !self.providers.contains_key(&key) {
+            return Err(BuildError::MissingDependency {
+                needed_by: TypeInfo::of::<Scoped<T>>(),
+                missing: TypeInfo::of::<T>(),
+            });
+        }
+        self.build(key, graph, &mut Vec::new())?;
+        Ok(graph.get_node::<Arc<T>>().unwrap().clone())
+    }
+
+    fn build(
+        &self,
+        key: NodeKey,
+        graph: &mut Graph,
+        resolving: &mut Vec<TypeInfo>,
+    ) -> Result<(), BuildError> {
+        let Some(provider) = self.providers.get(&key) else {
+            // Not scoped, so it resolves from the singletons.
+            return Ok(());
+        };
+        if graph.node(&key).is_some() {
+            return Ok(());
+        }
+        if let Some(needed_by) = resolving
+            .last()
+            .filter(|_| resolving.contains(&provider.info))
+        {
+            return Err(BuildError::MissingDependency {
+                needed_by: *needed_by,
+                missing: provider.info,
+            });
+        }
+        resolving.push(provider.info);
+        for dependency in &provider.dependencies {
+            self.build(*dependency, graph, resolving)?;
+        }
+        resolving.pop();
+        (provider.step)(graph, &[&provider.singletons])
+    }
+}
+
+/// The request-scoped instances constructed so far, stored in the request's extensions.
+///
+/// Its graph is seeded with the request's [`ScopedRequest`] when the first [`Scoped`] extraction
+/// creates it, and it's dropped along with the extensions when the request completes.
+struct RequestScope {
+    graph: Graph,
+}
+
+impl RequestScope {
+    fn new(req: &HttpRequest) -> Self {
+        let mut graph = Graph::new();
+        graph.provide_hidden(Arc::new(Arc::new(ScopedRequest::of(req))));
+        Self { graph }
+    }
+}
+
+/// The request a scope was created for, injectable into request-scoped providers as
+/// `Arc<ScopedRequest>`.
+///
+/// `HttpRequest` itself can't be a graph node, as it's neither `Send` nor `Sync`, so the scope
+/// holds a copy of its request line and headers instead. Providers that need the request's
+/// extensions should have the handler extract them and pass them along.
+pub struct ScopedRequest {
+    method: Method,
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap,
+}
+
+impl ScopedRequest {
+    fn of(req: &HttpRequest) -> Self {
+        Self {
+            method: req.method().clone(),
+            uri: req.uri().clone(),
+            version: req.version(),
+            headers: req.headers().clone(),
+        }
+    }
+
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The value of the `name` header, if it's present and visible ASCII.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+}
+
+/// Runs the [`OnRequestEnd`] hooks of each request once its handler returns. Wraps the whole
+/// application when any scoped provider registers one.
+pub(crate) struct EndRequests;
+
+impl Transform<MiddlewareService, ServiceRequest> for EndRequests {
+    type Response = ServiceResponse;
+    type Error = actix_web::Error;
+    type Transform = EndRequestsService;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, ()>>;
+
+    fn new_transform(&self, service: MiddlewareService) -> Self::Future {
+        ready(Ok(EndRequestsService { service }))
+    }
+}
+
+pub(crate) struct EndRequestsService {
+    service: MiddlewareService,
+}
+
+impl Service<ServiceRequest> for EndRequestsService {
+    type Response = ServiceResponse;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<ServiceResponse, actix_web::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let response = self.service.call(req);
+        async move {
+            let response = response.await?;
+            let request = response.request();
+            let scope = request.extensions_mut().remove::<RequestScope>();
+            if let (Some(scope), Some(registry)) =
+                (scope, request.app_data::<web::Data<ScopeRegistry>>())
+            {
+                registry.end(scope, response.status()).await;
+            }
+            Ok(response)
+        }
+        .boxed_local()
+    }
+}
+
+/// Extracts a provider registered with `Module::provide_scoped`.
+///
+/// The first extraction in a request constructs `T` along with any scoped dependencies it has;
+/// later extractions in the same request share that instance. Non-scoped dependencies are the
+/// declaring module's singletons, and a provider can inject `Arc<ScopedRequest>` to read the
+/// request it's constructed for. The instances are dropped when the request completes, after any
+/// [`OnRequestEnd`] hooks they registered have run.
+pub struct Scoped<T: ?Sized>(pub Arc<T>);
+
+impl<T: ?Sized> std::ops::Deref for Scoped<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Send + Sync + 'static> Scoped<T> {
+    fn extract(req: &HttpRequest) -> Result<Self, actix_web::Error> {
+        let registry = req
+            .app_data::<web::Data<ScopeRegistry>>()
+            .ok_or_else(|| ErrorInternalServerError("no ScopeRegistry registered as app data"))?;
+        let mut extensions = req.extensions_mut();
+        if !extensions.contains::<RequestScope>() {
+            extensions.insert(RequestScope::new(req));
+        }
+        let scope = extensions.get_mut::<RequestScope>().unwrap();
+        registry
+            .resolve::<T>(&mut scope.graph)
+            .map(Self)
+            .map_err(ErrorInternalServerError)
+    }
+}
+
+impl<T: Send + Sync + 'static> FromRequest for Scoped<T> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::extract(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_application::SeptApplication;
+    use crate::sept_module::{ApplicationContext, Module, ServiceFactory};
+    use crate::Injectable;
+    use actix_web::{test, App};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static SESSIONS_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Injectable)]
+    struct Session;
+
+    impl Drop for Session {
+        fn drop(&mut self) {
+            SESSIONS_DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[derive(Clone, Injectable)]
+    struct Config;
+
+    #[derive(Injectable)]
+    struct Auth {
+        session: Arc<Session>,
+        _config: Arc<Config>,
+    }
+
+    async fn handler(session: Scoped<Session>, auth: Scoped<Auth>) -> &'static str {
+        assert!(Arc::ptr_eq(&session.0, &auth.session));
+        "ok"
+    }
+
+    #[derive(Injectable)]
+    struct Routes;
+
+    impl ServiceFactory for Routes {
+        fn register(&self, app: &mut web::ServiceConfig) {
+            app.route("/", web::get().to(handler));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_scoped_providers_live_for_one_request() {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(
+            Module::new()
+                .provide::<Config>()
+                .provide_scoped::<Auth>()
+                .provide_scoped::<Session>()
+                .client::<Routes>()
+                .build(&mut ctx),
+        );
+        assert!(!module.graph.contains::<Arc<Session>>());
+
+        let registry = web::Data::new(ScopeRegistry::collect(&module));
+        let app = test::init_service(
+            App::new()
+                .app_data(registry)
+                .configure(|cfg| SeptApplication::configure(module.clone(), cfg)),
+        )
+        .await;
+        for expected in 1..=2 {
+            let res =
+                test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+            assert!(res.status().is_success());
+            drop(res);
+            assert_eq!(SESSIONS_DROPPED.load(Ordering::SeqCst), expected);
+        }
+    }
+
+    #[derive(Injectable)]
+    struct Tenant {
+        request: Arc<ScopedRequest>,
+    }
+
+    impl Tenant {
+        fn id(&self) -> &str {
+            self.request.header("x-tenant").unwrap_or("none")
+        }
+    }
+
+    #[derive(Injectable)]
+    struct TenantRoutes;
+
+    impl ServiceFactory for TenantRoutes {
+        fn register(&self, app: &mut web::ServiceConfig) {
+            app.route(
+                "/tenant",
+                web::get().to(|tenant: Scoped<Tenant>| async move { tenant.id().to_owned() }),
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_scoped_providers_can_read_the_request() {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(
+            Module::new()
+                .provide_scoped::<Tenant>()
+                .client::<TenantRoutes>()
+                .build(&mut ctx),
+        );
+        let registry = web::Data::new(ScopeRegistry::collect(&module));
+        let app = test::init_service(
+            App::new()
+                .app_data(registry)
+                .configure(|cfg| SeptApplication::configure(module.clone(), cfg)),
+        )
+        .await;
+        for (tenant, expected) in [(Some("acme"), "acme"), (None, "none")] {
+            let mut req = test::TestRequest::get().uri("/tenant");
+            if let Some(tenant) = tenant {
+                req = req.insert_header(("x-tenant", tenant));
+            }
+            let body = test::call_and_read_body(&app, req.to_request()).await;
+            assert_eq!(body, expected);
+        }
+    }
+
+    static ENDED: std::sync::Mutex<Vec<(&'static str, StatusCode)>> =
+        std::sync::Mutex::new(Vec::new());
+
+    #[derive(Injectable)]
+    struct Connection;
+
+    impl OnRequestEnd for Connection {
+        async fn on_request_end(&self, status: StatusCode) -> Result<(), CleanupError> {
+            ENDED.lock().unwrap().push(("connection", status));
+            Ok(())
+        }
+    }
+
+    #[derive(Injectable)]
+    struct Transaction {
+        _connection: Arc<Connection>,
+    }
+
+    impl OnRequestEnd for Transaction {
+        async fn on_request_end(&self, status: StatusCode) -> Result<(), CleanupError> {
+            ENDED.lock().unwrap().push(("transaction", status));
+            Err("commit failed".into())
+        }
+    }
+
+    #[derive(Injectable)]
+    struct TransactionRoutes;
+
+    impl ServiceFactory for TransactionRoutes {
+        fn register(&self, app: &mut web::ServiceConfig) {
+            app.route(
+                "/tx",
+                web::get().to(|_: Scoped<Transaction>| async { "ok" }),
+            );
+            app.route("/none", web::get().to(|| async { "ok" }));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_request_end_hooks_run_once_per_request_in_reverse() {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(
+            Module::new()
+                .provide_scoped::<Connection>()
+                .provide_scoped::<Transaction>()
+                .on_request_end::<Connection>()
+                .on_request_end::<Transaction>()
+                .client::<TransactionRoutes>()
+                .build(&mut ctx),
+        );
+        let registry = web::Data::new(ScopeRegistry::collect(&module));
+        let app = test::init_service(
+            App::new()
+                .app_data(registry)
+                .configure(|cfg| SeptApplication::configure(module.clone(), cfg)),
+        )
+        .await;
+
+        for uri in ["/tx", "/none", "/tx"] {
+            let res =
+                test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert!(res.status().is_success());
+        }
+        let ended = [
+            ("transaction", StatusCode::OK),
+            ("connection", StatusCode::OK),
+        ];
+        assert_eq!(*ENDED.lock().unwrap(), [ended, ended].concat());
+    }
+}
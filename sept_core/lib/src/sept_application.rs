@@ -1,14 +1,330 @@
+use crate::error::BuildError;
 use crate::graph::Graph;
+use crate::health::HealthRegistry;
+use crate::inspect::GraphDescription;
 use crate::instrumentation::InstrumentationOpts;
-use crate::sept_module::{ApplicationContext, ModuleFactory, ResolvedModule};
+use crate::lazy::LazyTree;
+use crate::middleware::Middleware;
+use crate::scope::{EndRequests, ScopeRegistry};
+use crate::sept_module::{
+    ApplicationContext, BuildReport, Module, ModuleFactory, ModuleInfo, ResolvedModule, RouteMeta,
+};
 use actix_cors::Cors;
+use actix_http::{KeepAlive, Request};
+use actix_service::boxed::{self, BoxService};
+use actix_service::{IntoServiceFactory, Service, ServiceFactory as _};
 use actix_tls::accept::rustls::reexports::ServerConfig;
-use actix_web::web::ServiceConfig;
+use actix_web::dev::{fn_service, AppConfig, ServiceRequest, ServiceResponse};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::web::{self, ServiceConfig};
 use actix_web::{App as ActixApp, HttpServer};
 use listenfd::ListenFd;
-use std::collections::HashMap;
+use std::cell::OnceCell;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::rc::Rc;
 use std::{io, sync::Arc};
 
+/// Builds the module tree rooted at `T` and returns a function for `App::configure` that
+/// registers every client in the tree, along with the app data request-scoped providers and
+/// health checks need, and a [`BuildReport`] summarizing the tree:
+///
+/// ```ignore
+/// let (configure, report) = sept::bootstrap::<AppModule>()?;
+/// println!("built {} modules", report.modules);
+/// HttpServer::new(move || App::new().configure(configure.clone()))
+/// ```
+///
+/// Unlike [`SeptApplication::init`], this builds synchronously and runs no lifecycle hooks.
+pub fn bootstrap<T: ModuleFactory + 'static>() -> Result<
+    (
+        impl Fn(&mut ServiceConfig) + Clone + Send + 'static,
+        BuildReport,
+    ),
+    BuildError,
+> {
+    SeptBuilder::new().bootstrap::<T>()
+}
+
+/// Like [`bootstrap`], but returns the built tree as a [`BuiltApp`], so tests can assert how the
+/// application is wired without serving any requests.
+pub fn bootstrap_app<T: ModuleFactory + 'static>() -> Result<BuiltApp, BuildError> {
+    SeptBuilder::new().bootstrap_app::<T>()
+}
+
+/// Bootstraps an application like [`bootstrap`], running callbacks once around the build of the
+/// whole module tree, e.g. to install a tracing subscriber or seed a process-global registry
+/// that isn't a provider:
+///
+/// ```ignore
+/// let (configure, _) = SeptBuilder::new()
+///     .on_before_build(|| tracing_subscriber::fmt().init())
+///     .on_after_build(|report| println!("built {} modules", report.modules))
+///     .bootstrap::<AppModule>()?;
+/// ```
+#[derive(Default)]
+pub struct SeptBuilder {
+    before_build: Vec<Box<dyn FnOnce()>>,
+    after_build: Vec<AfterBuild>,
+}
+
+type AfterBuild = Box<dyn FnOnce(&BuildReport)>;
+
+impl SeptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f` before any module builds. Callbacks run in registration order.
+    pub fn on_before_build(mut self, f: impl FnOnce() + 'static) -> Self {
+        self.before_build.push(Box::new(f));
+        self
+    }
+
+    /// Runs `f` with the tree's [`BuildReport`] once every module has built, including
+    /// [`BuildReport::provider_timings`]. It doesn't run if the build fails.
+    pub fn on_after_build(mut self, f: impl FnOnce(&BuildReport) + 'static) -> Self {
+        self.after_build.push(Box::new(f));
+        self
+    }
+
+    /// [`bootstrap`], with this builder's callbacks.
+    pub fn bootstrap<T: ModuleFactory + 'static>(
+        self,
+    ) -> Result<
+        (
+            impl Fn(&mut ServiceConfig) + Clone + Send + 'static,
+            BuildReport,
+        ),
+        BuildError,
+    > {
+        let app = self.build::<T>(true)?;
+        let report = app.report();
+        Ok((app.into_configure(), report))
+    }
+
+    /// [`bootstrap_app`], with this builder's callbacks. Providers are only timed when there are
+    /// [`SeptBuilder::on_after_build`] callbacks to report to, so [`BuiltApp::report`] has empty
+    /// [`BuildReport::provider_timings`] otherwise.
+    pub fn bootstrap_app<T: ModuleFactory + 'static>(self) -> Result<BuiltApp, BuildError> {
+        let timed = !self.after_build.is_empty();
+        self.build::<T>(timed)
+    }
+
+    fn build<T: ModuleFactory + 'static>(self, timed: bool) -> Result<BuiltApp, BuildError> {
+        for before_build in self.before_build {
+            before_build();
+        }
+        let mut ctx = ApplicationContext::new();
+        if timed {
+            ctx.record_timings();
+        }
+        let module = Arc::new(Module::from_factory::<T>().try_build(&mut ctx)?);
+        let app = BuiltApp { ctx, module };
+        if !self.after_build.is_empty() {
+            let report = app.report();
+            for after_build in self.after_build {
+                after_build(&report);
+            }
+        }
+        Ok(app)
+    }
+}
+
+/// A read-only view of a built module tree and its [`ApplicationContext`].
+pub struct BuiltApp {
+    ctx: ApplicationContext,
+    module: Arc<ResolvedModule>,
+}
+
+impl BuiltApp {
+    /// The type names of the root module and every module it transitively imports, root first.
+    pub fn module_names(&self) -> Vec<&'static str> {
+        self.module
+            .tree()
+            .into_iter()
+            .map(|module| module.name)
+            .collect()
+    }
+
+    /// The `Arc<T>` provider of the first module in [`BuiltApp::module_names`] order that
+    /// registers one, falling back to the global providers.
+    pub fn get<T: ?Sized + Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.find::<T>().map(|(_, node)| node.clone())
+    }
+
+    /// The module that provides the `Arc<T>` [`BuiltApp::get`] returns, for tracking down where
+    /// an instance comes from when several modules could provide it. `None` if nothing provides
+    /// `T`, or if it's a global provider registered outside any module.
+    pub fn provider_source<T: ?Sized + Send + Sync + 'static>(&self) -> Option<ModuleInfo> {
+        self.find::<T>().and_then(|(owner, _)| owner)
+    }
+
+    /// The `Arc<T>` [`BuiltApp::get`] returns, with the module it comes from.
+    fn find<T: ?Sized + Send + Sync + 'static>(&self) -> Option<(Option<ModuleInfo>, &Arc<T>)> {
+        let own = self.module.tree().into_iter().find_map(|module| {
+            let node = module.graph.get_node::<Arc<T>>()?;
+            Some((Some(module.info()), node))
+        });
+        own.or_else(|| {
+            let node = self.ctx.global_providers.get_node::<Arc<T>>()?;
+            let owner = self.ctx.global_owners.get(&Graph::key::<Arc<T>>(None));
+            Some((owner.copied(), node))
+        })
+    }
+
+    pub fn report(&self) -> BuildReport {
+        self.ctx.report(&self.module)
+    }
+
+    /// The module tree's nodes and dependency edges; see [`ApplicationContext::describe`].
+    pub fn describe(&self) -> GraphDescription {
+        self.ctx.describe(&self.module)
+    }
+
+    /// Renders the module tree in Graphviz `dot` syntax; see [`ApplicationContext::to_dot`].
+    pub fn to_dot(&self) -> String {
+        self.ctx.to_dot(&self.module)
+    }
+
+    /// Serializes [`BuiltApp::describe`] as JSON, for external tooling and dashboards.
+    pub fn to_json(&self) -> String {
+        self.ctx.to_json(&self.module)
+    }
+
+    /// Every route the clients of the module tree report through [`ServiceFactory::routes`], with
+    /// their full paths, including mount prefixes.
+    pub fn routes(&self) -> Vec<RouteMeta> {
+        let mut routes = Vec::new();
+        for (prefix, clients) in self.module.mounted_clients() {
+            for client in clients {
+                routes.extend(
+                    client
+                        .routes()
+                        .into_iter()
+                        .map(|route| route.under(&prefix)),
+                );
+            }
+        }
+        routes
+    }
+
+    /// The function for `App::configure` that [`bootstrap`] returns.
+    pub fn into_configure(self) -> impl Fn(&mut ServiceConfig) + Clone + Send + 'static {
+        let module = self.module;
+        let scopes = web::Data::new(ScopeRegistry::collect(&module));
+        let health = web::Data::new(HealthRegistry::collect(&module));
+        move |config: &mut ServiceConfig| {
+            config.app_data(scopes.clone());
+            config.app_data(health.clone());
+            SeptApplication::configure(module.clone(), config);
+        }
+    }
+}
+
+/// Builds the module tree rooted at `T` and serves it on `addr` until the server stops; see
+/// [`SeptServer::run`]. Use [`SeptServer`] directly to configure the server:
+///
+/// ```ignore
+/// #[actix_web::main]
+/// async fn main() -> std::io::Result<()> {
+///     sept::run::<AppModule>("0.0.0.0:8080").await
+/// }
+/// ```
+pub async fn run<T: ModuleFactory + 'static>(addr: impl ToSocketAddrs) -> io::Result<()> {
+    SeptServer::bind(addr)?.run::<T>().await
+}
+
+/// Serves a module tree on an actix-web `HttpServer`, configured through the builder:
+///
+/// ```ignore
+/// SeptServer::bind("0.0.0.0:8080")?
+///     .workers(4)
+///     .keep_alive(Duration::from_secs(30))
+///     .run::<AppModule>()
+///     .await
+/// ```
+pub struct SeptServer {
+    listen: Listen,
+    workers: Option<usize>,
+    keep_alive: Option<KeepAlive>,
+}
+
+enum Listen {
+    Addrs(Vec<SocketAddr>),
+    Listener(TcpListener),
+}
+
+impl SeptServer {
+    /// A server listening on every address `addr` resolves to.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self::new(Listen::Addrs(addr.to_socket_addrs()?.collect())))
+    }
+
+    /// A server accepting connections on an already bound `listener`, e.g. one bound to port 0.
+    pub fn listen(listener: TcpListener) -> Self {
+        Self::new(Listen::Listener(listener))
+    }
+
+    fn new(listen: Listen) -> Self {
+        Self {
+            listen,
+            workers: None,
+            keep_alive: None,
+        }
+    }
+
+    /// The number of worker threads, by default one per physical CPU core.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers);
+        self
+    }
+
+    /// How long idle connections are kept open, as `HttpServer::keep_alive` takes it.
+    pub fn keep_alive(mut self, keep_alive: impl Into<KeepAlive>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    /// Builds the module tree rooted at `T`, runs its `OnModuleInit` hooks and serves every
+    /// client in it. actix-web stops the server gracefully on SIGINT or SIGTERM, after which the
+    /// `OnModuleDestroy` hooks run and this returns. They also run if binding fails, e.g. because
+    /// the address is in use.
+    pub async fn run<T: ModuleFactory + 'static>(self) -> io::Result<()> {
+        let mut ctx = ApplicationContext::new();
+        let module = Arc::new(ctx.build_async::<T>().await.map_err(io::Error::other)?);
+        ctx.init_all().await.map_err(io::Error::other)?;
+        let scopes = web::Data::new(ScopeRegistry::collect(&module));
+        let health = web::Data::new(HealthRegistry::collect(&module));
+        let mut server = HttpServer::new(move || {
+            ActixApp::new()
+                .app_data(scopes.clone())
+                .app_data(health.clone())
+                .configure(|cfg| SeptApplication::configure(module.clone(), cfg))
+        });
+        if let Some(workers) = self.workers {
+            server = server.workers(workers);
+        }
+        if let Some(keep_alive) = self.keep_alive {
+            server = server.keep_alive(keep_alive);
+        }
+        let bound = match self.listen {
+            Listen::Addrs(addrs) => server.bind(&addrs[..]),
+            Listen::Listener(listener) => server.listen(listener),
+        };
+        let server = match bound {
+            Ok(server) => server,
+            Err(err) => {
+                ctx.shutdown().await;
+                return Err(err);
+            }
+        };
+
+        let result = server.run().await;
+        ctx.shutdown().await;
+        result
+    }
+}
+
 pub struct SeptConfig {
     pub port: u16,
     pub tls_config: Option<ServerConfig>,
@@ -16,17 +332,14 @@ pub struct SeptConfig {
 
 impl SeptConfig {
     fn new() -> Self {
-        SeptConfig {
+        Self {
             port: 3000,
             tls_config: None,
         }
     }
 
     fn register_globals(&mut self) -> ApplicationContext {
-        ApplicationContext {
-            global_providers: Graph::new(),
-            modules: HashMap::new(),
-        }
+        ApplicationContext::new()
     }
 }
 
@@ -56,13 +369,50 @@ impl SeptApplication {
         }
     }
 
-    fn configure(module: Arc<ResolvedModule>, config: &mut ServiceConfig) {
-        for client in &module.clients {
-            client.register(config);
-        }
-        for imports in &module.imports {
-            Self::configure(imports.clone(), config);
+    pub(crate) fn configure(module: Arc<ResolvedModule>, config: &mut ServiceConfig) {
+        let mounted = module.mounted_clients();
+        let mut middleware = module.all_middleware();
+        let mut lazy = module.mounted_lazy();
+        let tree = module.tree();
+        if tree
+            .iter()
+            .any(|m| m.scoped.iter().any(|provider| provider.has_cleanup()))
+        {
+            // Outermost, so the hooks see the response every other middleware produced.
+            middleware.insert(0, Middleware::new(EndRequests));
         }
+        Middleware::apply(
+            middleware,
+            config,
+            Box::new(move |config| {
+                for (prefix, clients) in mounted {
+                    // Catch-alls at longer or equal prefixes first, so this scope doesn't answer
+                    // their paths.
+                    while lazy
+                        .first()
+                        .is_some_and(|(_, lazy, _)| lazy.len() >= prefix.len())
+                    {
+                        let (parent, prefix, tree) = lazy.remove(0);
+                        config.service(lazy_scope(parent, prefix, tree));
+                    }
+                    if prefix.is_empty() {
+                        for client in clients {
+                            client.register(config);
+                        }
+                        continue;
+                    }
+                    // One scope per prefix, since a scope answers every path under it.
+                    config.service(web::scope(&prefix).configure(|config| {
+                        for client in clients {
+                            client.register(config);
+                        }
+                    }));
+                }
+                for (parent, prefix, tree) in lazy {
+                    config.service(lazy_scope(parent, prefix, tree));
+                }
+            }),
+        );
     }
 
     /// Method to enable default instrumentation for the application
@@ -82,11 +432,14 @@ impl SeptApplication {
         self
     }
 
-    pub async fn init<T: ModuleFactory>(mut self) -> io::Result<()> {
+    pub async fn init<T: ModuleFactory + 'static>(mut self) -> io::Result<()> {
         let mut fd = ListenFd::from_env();
         let mut ctx: ApplicationContext = self.app_config.register_globals();
-        let module = Arc::new(T::get_module().build(&mut ctx));
-        let mut server = HttpServer::new(move || {
+        let module = Arc::new(ctx.build_async::<T>().await.map_err(io::Error::other)?);
+        ctx.init_all().await.map_err(io::Error::other)?;
+        let scopes = web::Data::new(ScopeRegistry::collect(&module));
+        let health = web::Data::new(HealthRegistry::collect(&module));
+        let server = HttpServer::new(move || {
             let cors_config = self.cors.clone();
             let cors = Cors::default()
                 .allowed_origin(&cors_config.allowed_origin)
@@ -95,27 +448,39 @@ impl SeptApplication {
                 .expose_headers(cors_config.expose_headers)
                 .max_age(cors_config.max_age);
 
-            ActixApp::new().wrap(cors).configure(|cfg| Self::configure(module.clone(), cfg))
+            ActixApp::new()
+                .wrap(cors)
+                .app_data(scopes.clone())
+                .app_data(health.clone())
+                .configure(|cfg| Self::configure(module.clone(), cfg))
         });
 
-        if cfg!(feature = "rustls") && self.app_config.tls_config.is_some() {
-            server = match fd.take_tcp_listener(0).unwrap() {
-                Some(listener) => {
-                    server.listen_rustls(listener, self.app_config.tls_config.unwrap())?
-                }
-                None => server.bind_rustls(
-                    format!("0.0.0.0:{}", self.app_config.port),
-                    self.app_config.tls_config.unwrap(),
-                )?,
+        let bound = if let Some(tls_config) = self
+            .app_config
+            .tls_config
+            .filter(|_| cfg!(feature = "rustls"))
+        {
+            match fd.take_tcp_listener(0).unwrap() {
+                Some(listener) => server.listen_rustls(listener, tls_config),
+                None => server.bind_rustls(format!("0.0.0.0:{}", self.app_config.port), tls_config),
             }
         } else {
-            server = match fd.take_tcp_listener(0).unwrap() {
-                Some(listener) => server.listen(listener)?,
-                None => server.bind(format!("0.0.0.0:{}", self.app_config.port))?,
-            };
-        }
+            match fd.take_tcp_listener(0).unwrap() {
+                Some(listener) => server.listen(listener),
+                None => server.bind(format!("0.0.0.0:{}", self.app_config.port)),
+            }
+        };
+        let server = match bound {
+            Ok(server) => server,
+            Err(err) => {
+                ctx.shutdown().await;
+                return Err(err);
+            }
+        };
 
-        server.run().await
+        let result = server.run().await;
+        ctx.shutdown().await;
+        result
     }
 }
 
@@ -126,10 +491,15 @@ impl Default for SeptApplication {
 }
 
 impl Default for CorsConfig {
-    fn default() -> CorsConfig {
-        CorsConfig {
+    fn default() -> Self {
+        Self {
             allowed_origin: "http://localhost:3000".to_string(),
-            allowed_methods: vec![actix_http::Method::GET, actix_http::Method::POST, actix_http::Method::PUT, actix_http::Method::DELETE],
+            allowed_methods: vec![
+                actix_http::Method::GET,
+                actix_http::Method::POST,
+                actix_http::Method::PUT,
+                actix_http::Method::DELETE,
+            ],
             allowed_headers: vec![
                 actix_web::http::header::AUTHORIZATION,
                 actix_web::http::header::ACCEPT,
@@ -140,4 +510,378 @@ impl Default for CorsConfig {
             expose_headers: vec![],
         }
     }
-}
\ No newline at end of file
+}
+
+/// The app a lazily built module serves its requests from.
+type LazyService = BoxService<Request, ServiceResponse, actix_web::Error>;
+
+/// A catch-all scope at `prefix` for the module of `tree`, imported with `Module::import_lazy` by
+/// a module whose clients mount under `parent`. The first request builds the module on a blocking
+/// thread, and each worker then starts an app of the module's clients that every request under
+/// `prefix` is forwarded to.
+fn lazy_scope(parent: String, prefix: String, tree: Arc<LazyTree>) -> actix_web::Scope {
+    let service: Rc<OnceCell<Rc<LazyService>>> = Rc::default();
+    web::scope(&prefix).default_service(fn_service(move |req: ServiceRequest| {
+        let (parent, tree, service) = (parent.clone(), tree.clone(), service.clone());
+        async move {
+            let inner = match service.get() {
+                Some(inner) => inner.clone(),
+                None => {
+                    let module = web::block(move || tree.try_build())
+                        .await?
+                        .map_err(ErrorInternalServerError)?;
+                    let inner = Rc::new(serve_lazy(parent, module).await?);
+                    service.get_or_init(|| inner).clone()
+                }
+            };
+            let (http, payload) = req.into_parts();
+            let mut request = Request::with_payload(payload);
+            *request.head_mut() = http.head().clone();
+            let (_, response) = inner.call(request).await?.into_parts();
+            Ok::<_, actix_web::Error>(ServiceResponse::new(http, response))
+        }
+    }))
+}
+
+/// Starts an app of `module`, with its clients mounted under `parent` as they would have been
+/// had it been built eagerly.
+async fn serve_lazy(
+    parent: String,
+    module: Arc<ResolvedModule>,
+) -> Result<LazyService, actix_web::Error> {
+    let app = ActixApp::new()
+        .app_data(web::Data::new(ScopeRegistry::collect(&module)))
+        .app_data(web::Data::new(HealthRegistry::collect(&module)));
+    let configure = move |config: &mut ServiceConfig| SeptApplication::configure(module, config);
+    let app = if parent.is_empty() {
+        app.configure(configure)
+    } else {
+        app.service(web::scope(&parent).configure(configure))
+    };
+    let service = app
+        .into_factory()
+        .new_service(AppConfig::default())
+        .await
+        .map_err(|()| ErrorInternalServerError("failed to start a lazily built module"))?;
+    Ok(boxed::service(service))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::graph::{Graph, Injected};
+    use crate::lazy::LazyModule;
+    use crate::sept_module::ServiceFactory;
+    use crate::Injectable;
+    use actix_web::{test, App};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Injectable)]
+    struct Greeting;
+
+    #[derive(Injectable)]
+    struct GreetingController {
+        _greeting: Arc<Greeting>,
+    }
+
+    impl ServiceFactory for GreetingController {
+        fn register(&self, app: &mut ServiceConfig) {
+            app.route("/hello", web::get().to(|| async { "hello" }));
+        }
+    }
+
+    struct GreetingModule;
+    impl ModuleFactory for GreetingModule {
+        fn get_module() -> Module {
+            Module::new()
+                .provide::<Greeting>()
+                .client::<GreetingController>()
+        }
+    }
+
+    struct AppModule;
+    impl ModuleFactory for AppModule {
+        fn get_module() -> Module {
+            Module::new().import::<GreetingModule>()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_bootstrap_registers_imported_clients() {
+        let (configure, report) = bootstrap::<AppModule>().unwrap();
+        assert_eq!(report.clients, 1);
+        assert!(report
+            .provider_timings
+            .contains_key(std::any::type_name::<Arc<Greeting>>()));
+        let app = bootstrap_app::<AppModule>().unwrap();
+        assert!(app.report().provider_timings.is_empty());
+        let app = test::init_service(App::new().configure(configure)).await;
+        let req = test::TestRequest::get().uri("/hello").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(body, "hello");
+    }
+
+    #[actix_rt::test]
+    async fn test_server_serves_registered_routes() {
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        actix_rt::spawn(SeptServer::listen(listener).workers(1).run::<AppModule>());
+
+        let response = actix_rt::task::spawn_blocking(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(b"GET /hello HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        })
+        .await
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("hello"));
+    }
+
+    #[actix_rt::test]
+    async fn test_failed_bind_runs_destroy_hooks() {
+        use crate::lifecycle::{InitError, OnModuleDestroy, OnModuleInit};
+        use std::sync::Mutex;
+
+        static LOG: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+        #[derive(Injectable)]
+        struct Pool;
+        impl OnModuleInit for Pool {
+            async fn on_init(&self) -> Result<(), InitError> {
+                LOG.lock().unwrap().push("init");
+                Ok(())
+            }
+        }
+        impl OnModuleDestroy for Pool {
+            async fn on_destroy(&self) {
+                LOG.lock().unwrap().push("destroy");
+            }
+        }
+
+        struct PoolModule;
+        impl ModuleFactory for PoolModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide::<Pool>()
+                    .on_init::<Pool>()
+                    .on_destroy::<Pool>()
+            }
+        }
+
+        let taken = TcpListener::bind("0.0.0.0:0").unwrap();
+        let addr = taken.local_addr().unwrap();
+        assert!(SeptServer::bind(addr)
+            .unwrap()
+            .run::<PoolModule>()
+            .await
+            .is_err());
+        assert_eq!(*LOG.lock().unwrap(), ["init", "destroy"]);
+
+        LOG.lock().unwrap().clear();
+        let config = SeptConfig {
+            port: addr.port(),
+            tls_config: None,
+        };
+        assert!(SeptApplication::new(config)
+            .init::<PoolModule>()
+            .await
+            .is_err());
+        assert_eq!(*LOG.lock().unwrap(), ["init", "destroy"]);
+    }
+
+    #[actix_rt::test]
+    async fn test_built_app_exposes_modules_and_providers() {
+        let app = bootstrap_app::<AppModule>().unwrap();
+        let names = app.module_names();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].ends_with("AppModule"));
+        assert!(names[1].ends_with("GreetingModule"));
+        assert!(app.get::<Greeting>().is_some());
+        assert!(app.get::<GreetingController>().is_some());
+        assert!(app.get::<String>().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_provider_source_names_the_providing_module() {
+        let app = bootstrap_app::<AppModule>().unwrap();
+        let source = app.provider_source::<Greeting>().unwrap();
+        assert_eq!(source.id, Some(std::any::TypeId::of::<GreetingModule>()));
+        assert_eq!(source.name, std::any::type_name::<GreetingModule>());
+        assert!(app.provider_source::<String>().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_provider_source_names_the_module_get_resolves_from() {
+        struct ShadowingModule;
+        impl ModuleFactory for ShadowingModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .import::<GreetingModule>()
+                    .provide::<Greeting>()
+            }
+        }
+
+        let app = bootstrap_app::<ShadowingModule>().unwrap();
+        let source = app.provider_source::<Greeting>().unwrap();
+        assert_eq!(source.id, Some(std::any::TypeId::of::<ShadowingModule>()));
+        let own = app.module.graph.get_node::<Arc<Greeting>>().unwrap();
+        assert!(Arc::ptr_eq(&app.get::<Greeting>().unwrap(), own));
+    }
+
+    #[actix_rt::test]
+    async fn test_provider_source_names_the_global_module_that_exported_it() {
+        struct SharedModule;
+        impl ModuleFactory for SharedModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide::<Greeting>()
+                    .export::<Greeting>()
+                    .global()
+            }
+        }
+
+        let mut ctx = ApplicationContext::new();
+        Module::from_factory::<SharedModule>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let module = Arc::new(Module::new().try_build(&mut ctx).unwrap());
+        let app = BuiltApp { ctx, module };
+        let source = app.provider_source::<Greeting>().unwrap();
+        assert_eq!(source.id, Some(std::any::TypeId::of::<SharedModule>()));
+        assert_eq!(source.name, std::any::type_name::<SharedModule>());
+    }
+
+    #[actix_rt::test]
+    async fn test_builder_runs_callbacks_around_the_build() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (before, after) = (log.clone(), log.clone());
+        SeptBuilder::new()
+            .on_before_build(move || before.borrow_mut().push("before".to_string()))
+            .on_after_build(move |report| {
+                after
+                    .borrow_mut()
+                    .push(format!("after {} modules", report.modules))
+            })
+            .bootstrap_app::<AppModule>()
+            .unwrap();
+        assert_eq!(*log.borrow(), ["before", "after 2 modules"]);
+    }
+
+    #[derive(Clone, Injectable, crate::Controller)]
+    #[controller("/users")]
+    struct UsersController;
+
+    #[crate::routes]
+    impl UsersController {
+        #[get("/{id}")]
+        async fn find(&self, id: web::Path<u32>) -> String {
+            id.to_string()
+        }
+
+        #[post("")]
+        async fn create(&self) -> &'static str {
+            "created"
+        }
+    }
+
+    struct UsersModule;
+    impl ModuleFactory for UsersModule {
+        fn get_module() -> Module {
+            Module::new()
+                .client::<UsersController>()
+                .mount_prefix("/v1")
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_built_app_lists_controller_routes() {
+        let app = bootstrap_app::<UsersModule>().unwrap();
+        assert_eq!(
+            app.routes(),
+            [
+                RouteMeta::new("GET", "/v1/users/{id}"),
+                RouteMeta::new("POST", "/v1/users"),
+            ]
+        );
+    }
+
+    static AUDIT_LOGS_BUILT: AtomicUsize = AtomicUsize::new(0);
+
+    struct AuditLog;
+
+    impl Injected for AuditLog {
+        type Output = Self;
+
+        fn resolve(_: &mut Graph, _: &[&Graph]) -> Result<Self, BuildError> {
+            AUDIT_LOGS_BUILT.fetch_add(1, Ordering::SeqCst);
+            Ok(Self)
+        }
+    }
+
+    #[derive(Injectable)]
+    struct AdminController {
+        _log: Arc<AuditLog>,
+    }
+
+    impl ServiceFactory for AdminController {
+        fn register(&self, app: &mut ServiceConfig) {
+            app.route("/stats", web::get().to(|| async { "stats" }));
+        }
+    }
+
+    struct AdminModule;
+    impl ModuleFactory for AdminModule {
+        fn get_module() -> Module {
+            Module::new()
+                .mount_prefix("/admin")
+                .provide::<AuditLog>()
+                .export::<AuditLog>()
+                .client::<AdminController>()
+        }
+    }
+
+    struct ShopModule;
+    impl ModuleFactory for ShopModule {
+        fn get_module() -> Module {
+            Module::new()
+                .import::<GreetingModule>()
+                .import_lazy::<AdminModule>()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_lazy_imports_build_on_first_request() {
+        let app = bootstrap_app::<ShopModule>().unwrap();
+        assert!(app.get::<GreetingController>().is_some());
+        let admin = app.get::<LazyModule<AdminModule>>().unwrap();
+        assert!(!admin.is_built());
+
+        let service = test::init_service(App::new().configure(app.into_configure())).await;
+        let req = test::TestRequest::get().uri("/hello").to_request();
+        assert_eq!(test::call_and_read_body(&service, req).await, "hello");
+        assert_eq!(AUDIT_LOGS_BUILT.load(Ordering::SeqCst), 0);
+
+        for _ in 0..2 {
+            let req = test::TestRequest::get().uri("/admin/stats").to_request();
+            assert_eq!(test::call_and_read_body(&service, req).await, "stats");
+        }
+        let req = test::TestRequest::get().uri("/admin/missing").to_request();
+        let res = test::call_service(&service, req).await;
+        assert_eq!(res.status(), actix_web::http::StatusCode::NOT_FOUND);
+        assert!(admin.is_built());
+        admin.get::<AuditLog>();
+        assert_eq!(AUDIT_LOGS_BUILT.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,208 +1,5405 @@
-use crate::graph::{Graph, Injected};
+#[cfg(feature = "axum")]
+use crate::backend::AxumServiceFactory;
+use crate::config::ConfigParts;
+use crate::error::{BuildError, TypeInfo};
+use crate::factory::Factory;
+#[cfg(feature = "actix")]
+use crate::filter::{ExceptionFilter, ExceptionFilters};
+use crate::graph::{Exports, Graph, Injected, Multi, MultiToken, NodeKey, Token, Tokened, Upcast};
+#[cfg(feature = "actix")]
+use crate::guard::Guards;
+#[cfg(feature = "actix")]
+use crate::health::{HealthCheck, RegisteredCheck};
+use crate::inspect::GraphDescription;
+#[cfg(feature = "actix")]
+use crate::interceptor::{Interceptor, Interceptors};
+use crate::lazy::{Lazy, LazyModule, LazyTree};
+use crate::lifecycle::{self, Hook, InitError, OnModuleDestroy, OnModuleInit};
+#[cfg(feature = "actix")]
+use crate::middleware::{Middleware, MiddlewareService};
+use crate::module_ref::ModuleRef;
+use crate::retry::{self, RetryPolicy};
+#[cfg(feature = "actix")]
+use crate::scope::{Cleanup, OnRequestEnd, ScopedProvider};
+#[cfg(feature = "actix")]
+use actix_web::dev::{ServiceRequest, ServiceResponse, Transform};
+#[cfg(feature = "actix")]
 use actix_web::web::ServiceConfig;
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use semver::{Version, VersionReq};
+use std::future::Future;
+use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{
     any::TypeId,
-    collections::{HashMap, HashSet},
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
 };
 
+/// A client whose routes register on an actix-web `ServiceConfig`, with the `actix` feature.
+#[cfg(feature = "actix")]
 pub trait ServiceFactory: Send + Sync {
     fn register(&self, app: &mut ServiceConfig);
+
+    /// Resolves the [`Guard`](crate::guard::Guard)s every route of this client must pass, along
+    /// with the client. `#[guard(...)]` on `#[derive(Controller)]` declares them.
+    fn guards(_graph: &mut Graph, _imports: &[&Graph]) -> Result<Guards, BuildError>
+    where
+        Self: Sized,
+    {
+        Ok(Guards::default())
+    }
+
+    /// Registers the routes behind `layers`. `#[derive(Controller)]` implements this; the default
+    /// ignores the layers.
+    fn register_layered(&self, app: &mut ServiceConfig, layers: &RouteLayers) {
+        let _ = layers;
+        self.register(app);
+    }
+
+    /// The routes `register` adds, relative to the client's mount prefix, for listing them with
+    /// `BuiltApp::routes`. `#[client]` and `#[derive(Controller)]` implement this from the route
+    /// attributes; a hand-written client reports none unless it implements it too.
+    fn routes(&self) -> Vec<RouteMeta> {
+        Vec::new()
+    }
 }
 
-pub(crate) struct ApplicationContext {
-    pub(crate) global_providers: Graph,
-    pub(crate) modules: HashMap<TypeId, Arc<ResolvedModule>>,
+/// The method and path of a registered route, e.g. for logging the routes an application serves
+/// or seeding an OpenAPI document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteMeta {
+    /// The HTTP method in upper case, e.g. `GET`.
+    pub method: &'static str,
+    /// The path pattern as given to actix-web, e.g. `/users/{id}`.
+    pub path: String,
+}
+
+impl RouteMeta {
+    pub fn new(method: &'static str, path: impl Into<String>) -> Self {
+        Self {
+            method,
+            path: path.into(),
+        }
+    }
+
+    /// This route as seen from outside a scope mounted at `prefix`.
+    pub fn under(mut self, prefix: &str) -> Self {
+        self.path = format!("{}{}", prefix.trim_end_matches('/'), self.path);
+        self
+    }
+}
+
+/// What wraps each route of a client, outermost first: its module's exception filters, its own
+/// guards, then its module's interceptors.
+#[cfg(feature = "actix")]
+#[derive(Clone, Default)]
+pub struct RouteLayers {
+    pub filters: ExceptionFilters,
+    pub guards: Guards,
+    pub interceptors: Interceptors,
+}
+
+#[cfg(feature = "actix")]
+impl RouteLayers {
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.guards.is_empty() && self.interceptors.is_empty()
+    }
+}
+
+/// A client registered behind its route layers.
+#[cfg(feature = "actix")]
+struct LayeredClient<T> {
+    client: Arc<T>,
+    layers: RouteLayers,
+}
+
+#[cfg(feature = "actix")]
+impl<T: ServiceFactory> ServiceFactory for LayeredClient<T> {
+    fn register(&self, app: &mut ServiceConfig) {
+        self.client.register_layered(app, &self.layers);
+    }
+
+    fn routes(&self) -> Vec<RouteMeta> {
+        self.client.routes()
+    }
+}
+
+/// The routes of a controller, generated by `#[routes]` and registered by `#[derive(Controller)]`.
+#[cfg(feature = "actix")]
+pub trait Routes: Sized + 'static {
+    /// Registers each route, wrapped in `layers` unless there are none.
+    fn routes(
+        config: &mut ServiceConfig,
+        controller: actix_web::web::Data<Self>,
+        layers: &RouteLayers,
+    );
+
+    /// The method and path of each route, in declaration order.
+    fn route_meta() -> Vec<RouteMeta> {
+        Vec::new()
+    }
+}
+
+type BuildStep =
+    Box<dyn FnOnce(&mut ResolvedModule, &mut ApplicationContext) -> Result<(), BuildError>>;
+/// Provider steps may run more than once: one that fails on a missing dependency is retried after
+/// the rest of the module's providers have resolved.
+type ProviderStep =
+    Box<dyn FnMut(&mut ResolvedModule, &mut ApplicationContext) -> Result<(), BuildError>>;
+type FallbackStep = Box<dyn FnOnce(&mut ResolvedModule, &BuildError)>;
+#[cfg(feature = "actix")]
+type HealthStep = Box<dyn FnOnce(&ResolvedModule) -> Result<RegisteredCheck, BuildError>>;
+type LazyStep = Box<dyn FnOnce(&ResolvedModule, &Arc<Graph>)>;
+type HookStep = Box<dyn FnOnce(&ResolvedModule) -> Result<(usize, TypeInfo, Hook), BuildError>>;
+/// Inserts an awaited async provider into the module's graph.
+type InsertAsync = Box<dyn FnOnce(&mut Graph)>;
+type AsyncBuildStep =
+    Box<dyn FnOnce(Graph) -> LocalBoxFuture<'static, Result<InsertAsync, BuildError>>>;
+
+/// A provider step, with the token it registers, so [`Module::replace`] can remove it, and the
+/// node it inserts, so a stalled build can tell which providers wait on each other.
+struct Provider {
+    token: Option<NodeKey>,
+    node: Option<NodeKey>,
+    /// The nodes the step is known to read, so providers of these in the same module run first.
+    deps: Vec<NodeKey>,
+    step: ProviderStep,
+    /// Inserts a substitute for the provider's node if the step fails, from `Module::provide_or`.
+    fallback: Option<FallbackStep>,
+}
+
+/// An async provider step, with the node it inserts and the nodes it declared it reads, so it's
+/// awaited after the async providers of those.
+struct AsyncProvider {
+    info: TypeInfo,
+    node: NodeKey,
+    deps: Vec<(NodeKey, &'static str)>,
+    step: AsyncBuildStep,
+}
+
+/// Exports each of the listed types from a module, like chaining [`Module::export`] once per
+/// type:
+///
+/// ```ignore
+/// let module = sept::export_all!(
+///     Module::new().provide::<UserService>().provide::<UserRepo>(),
+///     UserService,
+///     UserRepo,
+/// );
+/// ```
+#[macro_export]
+macro_rules! export_all {
+    ($module:expr $(, $ty:ty)* $(,)?) => {
+        $module$(.export::<$ty>())*
+    };
+}
+
+/// Provides one instance of a concrete type as each of the listed trait objects, like chaining
+/// [`Module::provide_as`] once per trait. Every `Arc<dyn Trait>` shares the `Arc<Concrete>` node,
+/// so the concrete type is constructed once:
+///
+/// ```ignore
+/// sept::upcast!(PostgresRepo => dyn UserRepository, dyn AuditLog);
+///
+/// let module = sept::provide_as_multi!(
+///     Module::new(),
+///     PostgresRepo => [dyn UserRepository, dyn AuditLog],
+/// );
+/// ```
+#[macro_export]
+macro_rules! provide_as_multi {
+    ($module:expr, $concrete:ty => [$($target:ty),+ $(,)?] $(,)?) => {
+        $module$(.provide_as::<$concrete, $target>())+
+    };
+}
+
+/// How long the instances of a provider live, chosen with [`Module::provide_with_scope`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// One instance, constructed while its module builds, once its dependencies have resolved,
+    /// and shared by every consumer. Injected as `Arc<T>`.
+    Singleton,
+    /// One instance per HTTP request, constructed the first time a handler extracts it with
+    /// [`Scoped<T>`](crate::scope::Scoped) and dropped when the request completes. It may depend
+    /// on singletons and other request-scoped providers, but singletons can't depend on it.
+    #[cfg(feature = "actix")]
+    Request,
+    /// A new instance for every consumer, constructed when a field injecting `Arc<T>` resolves.
+    /// It may depend on singletons and other transients, and isn't visible through
+    /// `Graph::get_node`.
+    Transient,
+}
+
+/// What one module contributed to a [`BuildReport`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ModuleReport {
+    pub providers: usize,
+    pub clients: usize,
+}
+
+/// Counts summarizing a built module tree, from [`ApplicationContext::report`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BuildReport {
+    /// Distinct modules in the tree, counting a module imported in several places once.
+    pub modules: usize,
+    pub providers: usize,
+    pub clients: usize,
+    /// The longest chain of imports below the root; a root without imports has depth 0.
+    pub max_depth: usize,
+    /// Counts per module, keyed by module name. Dynamic instances of one module share an entry.
+    pub per_module: BTreeMap<&'static str, ModuleReport>,
+    /// How long each provider built with the context took to construct, keyed by the names
+    /// `ResolvedModule::list_providers` uses, summed over providers sharing a name, so the
+    /// contributions to a `Multi` collection add up under its name. An async provider's time is
+    /// how long it was awaited. Empty unless [`ApplicationContext::record_timings`] was called
+    /// before building.
+    pub provider_timings: BTreeMap<&'static str, Duration>,
+    /// Providers that no provider or client in the tree depends on and whose module doesn't
+    /// export them, a sign of dead wiring. Consumers through `provide_as` trait objects and
+    /// `export_as` names count, as do the dependencies declared with
+    /// `provide_factory_with_deps`, but other reads inside a `provide_factory` closure can't be
+    /// seen, so providers only such a factory uses are listed too.
+    pub unused_providers: Vec<&'static str>,
+}
+
+impl BuildReport {
+    /// The `n` providers that took longest to construct, slowest first, e.g. to fail CI when
+    /// startup exceeds a budget.
+    pub fn slowest(&self, n: usize) -> Vec<(&'static str, Duration)> {
+        let mut timings: Vec<_> = self
+            .provider_timings
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        timings.sort_by_key(|(_, took)| std::cmp::Reverse(*took));
+        timings.truncate(n);
+        timings
+    }
+}
+
+/// Identifies a built module, e.g. the one `BuiltApp::provider_source` reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModuleInfo {
+    /// The type that declared the module, `None` for a module built from `Module::new()`.
+    pub id: Option<TypeId>,
+    /// The declaring type's name, or `<root>` for a module built from `Module::new()`.
+    pub name: &'static str,
+}
+
+/// A built module and the modules it imports, from [`ResolvedModule::import_tree`].
+///
+/// A module imported in several places, such as the shared base of a diamond, lists its own
+/// imports where it first appears, in depth-first order; later appearances are marked `shared`
+/// and list no imports.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportTree {
+    /// The module's type name, or `<root>` for a module built from `Module::new()`.
+    pub name: &'static str,
+    /// Whether this module already appeared earlier in the tree.
+    pub shared: bool,
+    pub imports: Vec<Self>,
+}
+
+impl ImportTree {
+    fn of<'a>(module: &'a ResolvedModule, seen: &mut Vec<&'a ResolvedModule>) -> Self {
+        if seen.iter().any(|m| std::ptr::eq(*m, module)) {
+            return Self {
+                name: module.name,
+                shared: true,
+                imports: Vec::new(),
+            };
+        }
+        seen.push(module);
+        Self {
+            name: module.name,
+            shared: false,
+            imports: module
+                .imports
+                .iter()
+                .map(|import| Self::of(import, seen))
+                .collect(),
+        }
+    }
+
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{:indent$}{}", "", self.name, indent = depth * 2)?;
+        if self.shared {
+            write!(f, " (shared)")?;
+        }
+        writeln!(f)?;
+        self.imports
+            .iter()
+            .try_for_each(|import| import.write(f, depth + 1))
+    }
 }
+
+/// One module per line, indented two spaces per level of imports.
+impl std::fmt::Display for ImportTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 0)
+    }
+}
+
 #[derive(Default)]
-pub struct Module {
-    exports: HashSet<TypeId>,
-    tokens: HashSet<TypeId>,
-    imports: Vec<Box<dyn FnOnce(&mut ResolvedModule, &mut ApplicationContext)>>,
-    providers: Vec<Box<dyn FnOnce(&mut ResolvedModule, &mut ApplicationContext)>>,
-    provider_vals: Vec<Box<dyn FnOnce(&mut ResolvedModule, &mut ApplicationContext)>>,
-    clients: Vec<Box<dyn FnOnce(&mut ResolvedModule, &mut ApplicationContext)>>,
+pub struct ApplicationContext {
+    pub(crate) global_providers: Graph,
+    /// The global module that exported each of the global providers, for those a module exported.
+    pub(crate) global_owners: HashMap<NodeKey, ModuleInfo>,
+    pub(crate) modules: HashMap<ModuleKey, Arc<ResolvedModule>>,
+    /// Modules currently being built, outermost first, with their names.
+    pub(crate) resolving: Vec<(ModuleKey, &'static str)>,
+    /// `OnModuleInit` hooks in dependency order, drained by `init_all`.
+    pub(crate) init_hooks: Vec<(TypeInfo, Hook)>,
+    /// `OnModuleDestroy` hooks in dependency order, drained in reverse by `shutdown`.
+    pub(crate) destroy_hooks: Vec<(TypeInfo, Hook)>,
+    /// Construction times by provider name, once [`ApplicationContext::record_timings`] is called.
+    timings: Option<BTreeMap<&'static str, Duration>>,
+    /// Whether local providers shadowing a global export log a warning.
+    warn_on_shadowing: bool,
+    /// Overrides registered with `ModuleBuilder::override_provider_in` for modules not yet built.
+    nested_overrides: Vec<(TypeId, BuildStep)>,
+    /// The partial configs contributed with `ConfigModule::for_feature` so far, merged, by the
+    /// type of the config they're part of.
+    pub(crate) config_parts: HashMap<TypeId, ConfigParts>,
 }
 
-impl Module {
+/// The state of an [`ApplicationContext`] captured by [`ApplicationContext::snapshot`].
+#[derive(Clone)]
+pub struct ContextSnapshot {
+    global_providers: Graph,
+    global_owners: HashMap<NodeKey, ModuleInfo>,
+    modules: HashMap<ModuleKey, Arc<ResolvedModule>>,
+    timings: Option<BTreeMap<&'static str, Duration>>,
+    config_parts: HashMap<TypeId, ConfigParts>,
+}
+
+impl ApplicationContext {
     pub fn new() -> Self {
         Self {
-            exports: HashSet::new(),
-            tokens: HashSet::new(),
-            imports: Vec::new(),
-            providers: Vec::new(),
-            provider_vals: Vec::new(),
-            clients: Vec::new(),
+            global_providers: Graph::new(),
+            global_owners: HashMap::new(),
+            modules: HashMap::new(),
+            resolving: Vec::new(),
+            init_hooks: Vec::new(),
+            destroy_hooks: Vec::new(),
+            timings: None,
+            warn_on_shadowing: false,
+            nested_overrides: Vec::new(),
+            config_parts: HashMap::new(),
         }
     }
 
-    pub fn import<T: ModuleFactory + 'static>(mut self) -> Self {
-        self.imports.push(Box::new(|module, ctx| {
-            if let Some(resolved) = ctx.modules.get(&TypeId::of::<T>()) {
-                module.imports.push(resolved.clone());
-            } else {
-                let new_module = Arc::new(T::get_module().build(ctx));
-                ctx.modules.insert(TypeId::of::<T>(), new_module.clone());
-                module.imports.push(new_module);
-            }
-        }));
-        self
+    /// Captures the global providers and built modules of this context, so that tests can build
+    /// the modules they share once and start each from a copy with
+    /// [`ApplicationContext::from_snapshot`]. Provider instances are shared with the copies, not
+    /// cloned.
+    ///
+    /// Lifecycle hooks aren't captured: a restored context doesn't run the hooks of modules built
+    /// before the snapshot, so call [`ApplicationContext::init_all`] first if they need to run.
+    pub fn snapshot(&self) -> ContextSnapshot {
+        ContextSnapshot {
+            global_providers: self.global_providers.clone(),
+            global_owners: self.global_owners.clone(),
+            modules: self.modules.clone(),
+            timings: self.timings.clone(),
+            config_parts: self.config_parts.clone(),
+        }
     }
 
-    pub fn export<T>(mut self) -> Self
-    where
-        T: Injected + Send + Sync + 'static,
-    {
-        self.exports.insert(TypeId::of::<Arc<T>>());
-        self
+    /// A context holding the global providers and built modules captured in `snapshot`. Modules
+    /// built or globals provided afterwards affect neither the snapshot nor other contexts
+    /// restored from it.
+    pub fn from_snapshot(snapshot: &ContextSnapshot) -> Self {
+        Self {
+            global_providers: snapshot.global_providers.clone(),
+            global_owners: snapshot.global_owners.clone(),
+            modules: snapshot.modules.clone(),
+            timings: snapshot.timings.clone(),
+            config_parts: snapshot.config_parts.clone(),
+            ..Self::new()
+        }
     }
 
-    pub fn export_val<T>(mut self, _: &T) -> Self
-    where
-        T: Injected + Send + Sync + 'static,
-    {
-        self.exports.insert(TypeId::of::<T>());
-        self
+    /// Times the construction of each provider in later builds with this context, for
+    /// [`BuildReport::provider_timings`]. Off by default, so builds that don't need a report
+    /// don't read the clock.
+    pub fn record_timings(&mut self) {
+        self.timings.get_or_insert_with(BTreeMap::new);
     }
 
-    pub fn provide<T>(mut self) -> Self
-    where
-        T: Injected<Output = T> + 'static,
-    {
-        self.providers.push(Box::new(|module, ctx| {
-            let mut graphs = vec![&ctx.global_providers];
-            for module in &module.imports {
-                graphs.push(&module.graphed_exports);
+    /// Emits a `tracing` warning, with the `tracing` feature, whenever a module later built with
+    /// this context provides a type that a global module also exports. The local provider wins
+    /// either way; off by default, since overriding a global export on purpose is common.
+    pub fn warn_on_shadowing(&mut self) {
+        self.warn_on_shadowing = true;
+    }
+
+    /// Awaits the `OnModuleInit` hook of every registered provider, dependencies first.
+    ///
+    /// Imported modules finish building before their importers, and within a module a provider's
+    /// dependencies are resolved before it, so the recorded order already respects dependencies.
+    /// Each hook runs at most once.
+    ///
+    /// The first hook to fail stops the rest from running, and the `OnModuleDestroy` hooks of
+    /// the providers already initialized run, dependents first, before its error is returned.
+    pub async fn init_all(&mut self) -> Result<(), BuildError> {
+        let mut initialized = HashSet::new();
+        for (provider, hook) in std::mem::take(&mut self.init_hooks) {
+            if let Err(source) = hook().await {
+                let (cleanup, rest) = std::mem::take(&mut self.destroy_hooks)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|(info, _)| initialized.contains(&info.id));
+                self.destroy_hooks = rest;
+                for (_, hook) in cleanup.into_iter().rev() {
+                    let _ = hook().await;
+                }
+                return Err(BuildError::InitFailed { provider, source });
             }
-            module.graph.resolve::<Arc<T>>(&graphs);
-        }));
-        self.tokens.insert(TypeId::of::<T>());
-        self
+            initialized.insert(provider.id);
+        }
+        Ok(())
     }
 
-    pub fn provide_val<T: Sync + Send + Clone>(mut self, t: T) -> Self
-    where
-        T: 'static,
-    {
-        self.provider_vals.push(Box::new(|module, _| {
-            module.graph.provide(Arc::new(t));
-        }));
-        self.tokens.insert(TypeId::of::<T>());
-        self
+    /// Awaits the `OnModuleDestroy` hook of every registered provider, dependents first.
+    ///
+    /// Modules are built once however many modules import them, so each hook runs exactly once;
+    /// calling `shutdown` again is a no-op.
+    pub async fn shutdown(&mut self) {
+        for (_, hook) in std::mem::take(&mut self.destroy_hooks).into_iter().rev() {
+            let _ = hook().await;
+        }
     }
 
-    pub fn client<T>(mut self) -> Self
-    where
-        T: Injected<Output = T> + ServiceFactory + 'static,
-    {
-        self.clients.push(Box::new(|module, ctx| {
-            let mut graphs = vec![&ctx.global_providers];
-            for module in &module.imports {
-                graphs.push(&module.graphed_exports);
-            }
-            let resolved = T::resolve(&mut module.graph, &graphs);
-            module.clients.push(Arc::new(resolved));
-        }));
-        self.tokens.insert(TypeId::of::<T>());
-        self
+    /// Marks the module `key` as being built, failing if it's already being built further out,
+    /// so that differently configured instances of one dynamic module can nest.
+    fn enter(&mut self, key: ModuleKey, name: &'static str) -> Result<(), BuildError> {
+        if let Some(start) = self.resolving.iter().position(|(k, _)| *k == key) {
+            let mut cycle: Vec<&'static str> = self.resolving[start..]
+                .iter()
+                .map(|(_, name)| *name)
+                .collect();
+            cycle.push(name);
+            return Err(BuildError::CircularImport { cycle });
+        }
+        self.resolving.push((key, name));
+        Ok(())
     }
 
-    pub(crate) fn build(self, ctx: &mut ApplicationContext) -> ResolvedModule {
-        let mut module = ResolvedModule::new();
+    /// Summarizes `root` and every module it transitively imports, e.g. to catch a change that
+    /// accidentally doubles the providers an application registers.
+    pub fn report(&self, root: &ResolvedModule) -> BuildReport {
+        let mut report = BuildReport {
+            max_depth: root.depth(),
+            provider_timings: self.timings.clone().unwrap_or_default(),
+            ..BuildReport::default()
+        };
+        for module in root.tree() {
+            #[cfg(feature = "actix")]
+            let (clients, scoped) = (module.clients.len(), module.scoped.len());
+            #[cfg(not(feature = "actix"))]
+            let (clients, scoped) = (0, 0);
+            #[cfg(feature = "axum")]
+            let clients = clients + module.axum_clients.len();
+            let providers = module.list_providers().len() + scoped - clients;
+            report.modules += 1;
+            report.providers += providers;
+            report.clients += clients;
+            let entry = report.per_module.entry(module.name).or_default();
+            entry.providers += providers;
+            entry.clients += clients;
+        }
+        report.unused_providers = unused_providers(root);
+        report
+    }
+
+    /// Describes `root` and every module it transitively imports: each module's nodes, the
+    /// global providers they inject and the edges between them, pointing at the provider
+    /// actually injected across module boundaries.
+    pub fn describe(&self, root: &ResolvedModule) -> GraphDescription {
+        GraphDescription::of_tree(self, root)
+    }
+
+    /// Renders [`ApplicationContext::describe`] in Graphviz `dot` syntax, each module as a
+    /// cluster. Global providers registered outside any module get a cluster of their own.
+    pub fn to_dot(&self, root: &ResolvedModule) -> String {
+        self.describe(root).to_dot()
+    }
+
+    /// Serializes [`ApplicationContext::describe`] as JSON.
+    pub fn to_json(&self, root: &ResolvedModule) -> String {
+        self.describe(root).to_json()
+    }
+
+    /// Builds the module declared by `T`, awaiting any async providers it or its imports declare.
+    pub async fn build_async<T: ModuleFactory + 'static>(
+        &mut self,
+    ) -> Result<ResolvedModule, BuildError> {
+        Module::from_factory::<T>().try_build_async(self).await
+    }
+}
+
+/// Identifies a built module: the declaring type, plus a config hash for dynamic modules.
+pub(crate) type ModuleKey = (TypeId, Option<u64>);
+
+struct Import {
+    target: ImportTarget,
+    /// `None` for an optional import, which only uses a module that's already built.
+    get_module: Option<Box<dyn FnOnce() -> Module>>,
+    /// Makes the built module's exports injectable as an [`Exports`] bundle, for imports by type.
+    bundle: Option<fn(&ResolvedModule, &mut Graph)>,
+    /// The version the imported module must declare, from [`Module::import_versioned`].
+    required: Option<VersionReq>,
+}
+
+#[derive(Clone, Copy)]
+struct ImportTarget {
+    name: &'static str,
+    key: Option<ModuleKey>,
+}
+
+impl ImportTarget {
+    fn failed(self, err: BuildError) -> BuildError {
+        BuildError::ImportFailed {
+            module: self.name,
+            source: Box::new(err),
+        }
+    }
+
+    fn cached(self, ctx: &ApplicationContext) -> Option<Arc<ResolvedModule>> {
+        self.key.and_then(|key| ctx.modules.get(&key).cloned())
+    }
+
+    fn store(self, ctx: &mut ApplicationContext, resolved: Arc<ResolvedModule>) {
+        if let Some(key) = self.key {
+            ctx.modules.insert(key, resolved);
+        }
+    }
+}
 
-        for import in self.imports {
-            import(&mut module, ctx);
+impl Import {
+    fn of<T: ModuleFactory + 'static>() -> Self {
+        Self {
+            target: ImportTarget {
+                name: std::any::type_name::<T>(),
+                key: Some((TypeId::of::<T>(), None)),
+            },
+            get_module: Some(Box::new(Module::from_factory::<T>)),
+            bundle: Some(|import, graph| {
+                graph.provide_hidden(Arc::new(Exports::<T>::new(import.graphed_exports.clone())));
+            }),
+            required: None,
         }
+    }
 
-        for provided_val in self.provider_vals {
-            provided_val(&mut module, ctx);
+    fn optional<T: ModuleFactory + 'static>() -> Self {
+        Self {
+            get_module: None,
+            ..Self::of::<T>()
         }
+    }
 
-        for provider in self.providers {
-            provider(&mut module, ctx);
+    fn from_module(module: Module) -> Self {
+        Self {
+            target: ImportTarget {
+                name: module.name(),
+                key: module.key(),
+            },
+            get_module: Some(Box::new(move || module)),
+            bundle: None,
+            required: None,
         }
+    }
 
-        for client in self.clients {
-            client(&mut module, ctx);
+    /// Declares the imported module, turning a panic in its `get_module` into an error naming it.
+    /// Panics can't be caught when built with `panic = "abort"`.
+    fn declare(
+        get_module: Box<dyn FnOnce() -> Module>,
+        target: ImportTarget,
+    ) -> Result<Module, BuildError> {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(get_module)).map_err(|panic| {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "<non-string panic>".to_string());
+            target.failed(BuildError::ModulePanicked {
+                module: target.name,
+                message,
+            })
+        })
+    }
+
+    /// The built module, or `None` for an optional import of a module nothing built yet.
+    fn resolve(
+        self,
+        ctx: &mut ApplicationContext,
+    ) -> Result<Option<Arc<ResolvedModule>>, BuildError> {
+        let target = self.target;
+        if let Some(resolved) = target.cached(ctx) {
+            return Ok(Some(resolved));
         }
+        let Some(get_module) = self.get_module else {
+            return Ok(None);
+        };
+        let resolved = Self::declare(get_module, target)?
+            .try_build(ctx)
+            .map(Arc::new)
+            .map_err(|err| target.failed(err))?;
+        target.store(ctx, resolved.clone());
+        Ok(Some(resolved))
+    }
 
-        module.graphed_exports = module.graph.filter_by(self.exports);
-        module
+    async fn resolve_async(
+        self,
+        ctx: &mut ApplicationContext,
+    ) -> Result<Option<Arc<ResolvedModule>>, BuildError> {
+        let target = self.target;
+        if let Some(resolved) = target.cached(ctx) {
+            return Ok(Some(resolved));
+        }
+        let Some(get_module) = self.get_module else {
+            return Ok(None);
+        };
+        let resolved = Self::declare(get_module, target)?
+            .try_build_async(ctx)
+            .await
+            .map(Arc::new)
+            .map_err(|err| target.failed(err))?;
+        target.store(ctx, resolved.clone());
+        Ok(Some(resolved))
     }
 }
 
-pub trait ModuleFactory: Sized {
-    fn get_module() -> Module;
+/// The candidate implementations of `Arc<U>` for [`Module::provide_one_of`] to pick from.
+pub struct OneOf<U: ?Sized> {
+    chosen: Option<fn(Module) -> Module>,
+    target: PhantomData<fn() -> Arc<U>>,
 }
 
-#[derive(Clone)]
-pub(crate) struct ResolvedModule {
-    pub(crate) graph: Graph,
-    pub(crate) imports: Vec<Arc<Self>>,
-    graphed_exports: Graph,
-    pub(crate) clients: Vec<Arc<dyn ServiceFactory>>,
+impl<U: ?Sized + Send + Sync + 'static> OneOf<U> {
+    /// Picks `T` if `condition` holds and no earlier candidate was picked.
+    pub fn when<T>(mut self, condition: bool) -> Self
+    where
+        T: Injected<Output = T> + Upcast<U> + Send + Sync + 'static,
+    {
+        if condition && self.chosen.is_none() {
+            self.chosen = Some(Module::provide_as::<T, U>);
+        }
+        self
+    }
+
+    /// Picks `T` if no earlier candidate was picked.
+    pub fn otherwise<T>(self) -> Self
+    where
+        T: Injected<Output = T> + Upcast<U> + Send + Sync + 'static,
+    {
+        self.when::<T>(true)
+    }
 }
 
-impl ResolvedModule {
+#[derive(Default)]
+pub struct Module {
+    info: Option<TypeInfo>,
+    instance: Option<u64>,
+    /// Set with [`Module::version`].
+    version: Option<Version>,
+    /// Versions and requirements that didn't parse, with the parse error, reported when the module
+    /// is built.
+    invalid_versions: Vec<(String, String)>,
+    is_global: bool,
+    prefix: &'static str,
+    /// Ordered by key rather than hashed, so which missing export a build reports is the same
+    /// from run to run.
+    exports: BTreeMap<NodeKey, TypeInfo>,
+    reexported_modules: HashSet<TypeId>,
+    tokens: HashSet<NodeKey>,
+    /// Tokens registered more than once, reported when the module is built.
+    duplicates: Vec<(NodeKey, TypeInfo)>,
+    imports: Vec<Import>,
+    providers: Vec<Provider>,
+    provider_vals: Vec<(Option<NodeKey>, BuildStep)>,
+    async_providers: Vec<AsyncProvider>,
+    #[cfg(feature = "actix")]
+    scoped: Vec<ScopedProvider>,
+    /// `OnRequestEnd` hooks, attached to the scoped providers they name when the module is built.
+    #[cfg(feature = "actix")]
+    request_cleanups: Vec<(TypeInfo, Cleanup)>,
+    lazy: Vec<LazyStep>,
+    #[cfg(feature = "actix")]
+    clients: Vec<BuildStep>,
+    #[cfg(feature = "axum")]
+    axum_clients: Vec<BuildStep>,
+    #[cfg(feature = "actix")]
+    interceptors: Vec<BuildStep>,
+    #[cfg(feature = "actix")]
+    filters: Vec<BuildStep>,
+    #[cfg(feature = "actix")]
+    middleware: Vec<BuildStep>,
+    init_hooks: Vec<HookStep>,
+    destroy_hooks: Vec<HookStep>,
+    #[cfg(feature = "actix")]
+    health_checks: Vec<HealthStep>,
+    /// Providers the framework itself consumes, such as clients and hook providers.
+    entry_points: Vec<NodeKey>,
+    /// Overrides for the module declared by the `TypeId`, wherever it's built in this tree.
+    nested_overrides: Vec<(TypeId, BuildStep)>,
+}
+
+impl Module {
     pub fn new() -> Self {
         Self {
-            graph: Graph::new(),
+            info: None,
+            instance: None,
+            version: None,
+            invalid_versions: Vec::new(),
+            is_global: false,
+            prefix: "",
+            exports: BTreeMap::new(),
+            reexported_modules: HashSet::new(),
+            tokens: HashSet::new(),
+            duplicates: Vec::new(),
             imports: Vec::new(),
-            graphed_exports: Graph::new(),
+            providers: Vec::new(),
+            provider_vals: Vec::new(),
+            async_providers: Vec::new(),
+            #[cfg(feature = "actix")]
+            scoped: Vec::new(),
+            #[cfg(feature = "actix")]
+            request_cleanups: Vec::new(),
+            lazy: Vec::new(),
+            #[cfg(feature = "actix")]
             clients: Vec::new(),
+            #[cfg(feature = "axum")]
+            axum_clients: Vec::new(),
+            #[cfg(feature = "actix")]
+            interceptors: Vec::new(),
+            #[cfg(feature = "actix")]
+            filters: Vec::new(),
+            #[cfg(feature = "actix")]
+            middleware: Vec::new(),
+            init_hooks: Vec::new(),
+            destroy_hooks: Vec::new(),
+            #[cfg(feature = "actix")]
+            health_checks: Vec::new(),
+            entry_points: Vec::new(),
+            nested_overrides: Vec::new(),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate as sept;
-    use crate::sept_module::{Module, ServiceConfig, ServiceFactory};
-    use crate::Injectable;
+    /// Gets the module declared by `T`, tagged with `T`'s name for error reporting.
+    pub fn from_factory<T: ModuleFactory + 'static>() -> Self {
+        let mut module = T::get_module();
+        module.info = Some(TypeInfo::of::<T>());
+        module
+    }
 
-    fn get_empty_ctx() -> ApplicationContext {
-        ApplicationContext {
-            global_providers: Graph::new(),
-            modules: HashMap::new(),
+    /// Starts a configured instance of `T`, for `for_root`-style constructors:
+    ///
+    /// ```ignore
+    /// impl DatabaseModule {
+    ///     pub fn for_root(config: DbConfig) -> Module {
+    ///         Module::dynamic::<Self, _>(&config)
+    ///             .provide_val(config)
+    ///             .provide::<Pool>()
+    ///             .export::<Pool>()
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// Import the result with [`Module::import_module`]. Dynamic modules are deduplicated by `T`
+    /// together with the hash of `config`: importing an equal config again reuses the module built
+    /// the first time, while a different config builds a separate instance. They never share an
+    /// identity with `T`'s own `ModuleFactory` module, if it has one.
+    pub fn dynamic<T: 'static, C: Hash>(config: &C) -> Self {
+        let mut hasher = DefaultHasher::new();
+        config.hash(&mut hasher);
+        let mut module = Self::new();
+        module.info = Some(TypeInfo::of::<T>());
+        module.instance = Some(hasher.finish());
+        module
+    }
+
+    /// Declares the version of the API this module exports, e.g. `"2.1.0"`, which modules
+    /// importing it with [`Module::import_versioned`] check their requirement against. A
+    /// `version` that isn't a semantic version fails the build with
+    /// [`BuildError::InvalidVersion`].
+    pub fn version(mut self, version: &str) -> Self {
+        match Version::parse(version) {
+            Ok(parsed) => self.version = Some(parsed),
+            Err(err) => self
+                .invalid_versions
+                .push((version.to_string(), err.to_string())),
         }
+        self
     }
 
-    #[test]
-    fn test_client_is_reachable() {
-        #[derive(Clone, Injectable)]
-        struct TestInjectable;
+    fn name(&self) -> &'static str {
+        self.info.map_or("<root>", |info| info.name)
+    }
 
-        impl ServiceFactory for TestInjectable {
-            fn register(&self, _: &mut ServiceConfig) {}
-        }
+    fn key(&self) -> Option<ModuleKey> {
+        self.info.map(|info| (info.id, self.instance))
+    }
 
-        let mut ctx = get_empty_ctx();
-        let resolved = Module::new().client::<TestInjectable>().build(&mut ctx);
-        assert_eq!(resolved.clients.len(), 1);
+    /// Makes this module's exports visible to every module without an explicit import, like
+    /// NestJS `@Global()`. A global module still has to be imported once, before the modules that
+    /// rely on it.
+    ///
+    /// A module resolves each dependency from its own providers first, then from its imports'
+    /// exports in import order, then from global modules. So a module that provides or imports
+    /// a `T` of its own gets that one instead of the global export; providing one locally also
+    /// logs a warning once [`ApplicationContext::warn_on_shadowing`] is called.
+    pub fn global(mut self) -> Self {
+        self.is_global = true;
+        self
     }
 
-    #[test]
-    fn test_imported_provider_is_reachable() {
-        #[derive(Clone, Injectable)]
-        struct TestInjectable;
+    /// Mounts this module's clients under the path `prefix`, e.g. `/v2`, without changing their
+    /// routes. Prefixes of nested modules concatenate, so a module mounted at `/admin` imported by
+    /// one mounted at `/v2` serves under `/v2/admin`. A module imported in several places mounts
+    /// under the prefix of each import path, so a module shared by `/v1` and `/v2` serves under
+    /// both; import paths with equal prefixes mount it once.
+    pub fn mount_prefix(mut self, prefix: &'static str) -> Self {
+        self.prefix = prefix;
+        self
+    }
 
-        struct ExportingModule;
-        impl ModuleFactory for ExportingModule {
-            fn get_module() -> Module {
-                Module::new()
-                    .export::<TestInjectable>()
-                    .provide::<TestInjectable>()
+    pub fn import<T: ModuleFactory + 'static>(mut self) -> Self {
+        self.imports.push(Import::of::<T>());
+        self
+    }
+
+    /// Imports `T` like [`Module::import`], requiring the version `T` declares with
+    /// [`Module::version`] to match `requirement`, e.g. `"^2.1"`, so that a breaking change to
+    /// its exports fails the build with [`BuildError::VersionMismatch`] rather than going
+    /// unnoticed. A module that declares no version matches no requirement, and a `requirement`
+    /// that isn't a semantic version requirement fails the build with
+    /// [`BuildError::InvalidVersion`].
+    pub fn import_versioned<T: ModuleFactory + 'static>(mut self, requirement: &str) -> Self {
+        match VersionReq::parse(requirement) {
+            Ok(required) => self.imports.push(Import {
+                required: Some(required),
+                ..Import::of::<T>()
+            }),
+            Err(err) => {
+                let invalid = (requirement.to_string(), err.to_string());
+                self.invalid_versions.push(invalid);
             }
         }
+        self
+    }
 
-        let mut ctx = get_empty_ctx();
-        let resolved = Module::new().import::<ExportingModule>().build(&mut ctx);
-        assert_eq!(resolved.imports.len(), 1);
+    /// Imports `T` only if it has already been built elsewhere in the tree, by a module that
+    /// imports it and builds before this one, and skips it silently otherwise; `T` is never built
+    /// just for this import. Pair it with `Option<Arc<_>>` fields so consumers of `T`'s exports
+    /// degrade gracefully when it's absent, e.g. for optional plugin modules.
+    pub fn import_optional<T: ModuleFactory + 'static>(mut self) -> Self {
+        self.imports.push(Import::optional::<T>());
+        self
+    }
 
-        assert!(resolved.imports[0]
-            .graph
-            .get_node::<Arc<TestInjectable>>()
-            .is_some());
+    /// Imports `T` without building it, so that startup doesn't pay for a rarely used subtree
+    /// such as an admin module. Providers inject an `Arc<LazyModule<T>>` rather than `T`'s
+    /// exports, and the first [`LazyModule::get`] builds `T`.
+    ///
+    /// With the `actix` feature, routes have to exist when the application is configured, so a
+    /// catch-all scope is registered at `T`'s mount prefix instead of `T`'s clients. The first
+    /// request under the prefix builds `T` on a blocking thread and every request is then served
+    /// by `T`'s clients and middleware, under the middleware of the importing tree. Give `T` a
+    /// mount prefix of its own: the catch-all answers every path under it, shadowing eager
+    /// clients mounted at the same prefix, and with no prefix it answers every path nothing else
+    /// does. The first request pays for the build, and a build that fails answers with a server
+    /// error until one succeeds, rather than failing startup.
+    pub fn import_lazy<T: ModuleFactory + 'static>(mut self) -> Self {
+        self.push_val::<LazyModule<T>>(Box::new(|module, ctx| {
+            let tree = LazyTree::new(Self::from_factory::<T>, ctx.snapshot());
+            #[cfg(feature = "actix")]
+            let tree = tree.mounted_at(Self::from_factory::<T>().prefix);
+            let tree = Arc::new(tree);
+            #[cfg(feature = "actix")]
+            module.lazy_modules.push(tree.clone());
+            module
+                .graph
+                .provide(Arc::new(Arc::new(LazyModule::<T>::new(tree))));
+            Ok(())
+        }));
+        self.entry_points
+            .push(Graph::key::<Arc<LazyModule<T>>>(None));
+        self
+    }
+
+    /// Imports a module value, typically one returned by a `for_root`-style constructor built on
+    /// [`Module::dynamic`]. Modules without an identity are built afresh on every import.
+    pub fn import_module(mut self, module: Self) -> Self {
+        self.imports.push(Import::from_module(module));
+        self
+    }
+
+    /// Exports `Arc<T>` to importing modules. `T` may be provided by this module or re-exported
+    /// from an import, so a façade module can pass on what it imports; a provider of its own
+    /// takes precedence.
+    pub fn export<T>(mut self) -> Self
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.exports
+            .insert(Graph::key::<Arc<T>>(None), TypeInfo::of::<T>());
+        self
+    }
+
+    /// Exports the provider registered with [`Module::provide_with_token`] under `K`.
+    pub fn export_with_token<K: Token, T: Send + Sync + 'static>(mut self) -> Self {
+        self.exports.insert(
+            Graph::key::<Tokened<K, T>>(None),
+            TypeInfo::of::<Tokened<K, T>>(),
+        );
+        self
+    }
+
+    /// Exports the provider registered with [`Module::provide_named`] under `name`.
+    pub fn export_named<T>(mut self, name: &'static str) -> Self
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.exports
+            .insert(Graph::key::<Arc<T>>(Some(name)), TypeInfo::of::<T>());
+        self
+    }
+
+    /// Exports the unnamed `Arc<T>` this module provides or imports under `name` instead, so two
+    /// configured instances of one [`Module::dynamic`] module can be imported side by side, each
+    /// exporting its own `T`. Consumers pick one with `#[named("...")]`, as for
+    /// [`Module::provide_named`]; inside the module, `T` stays injectable unnamed.
+    pub fn export_as<T>(mut self, name: &'static str) -> Self
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.register::<Arc<T>>(Some(name));
+        self.providers.push(Provider {
+            token: Some(Graph::key::<Arc<T>>(Some(name))),
+            // Not a node of its own for cycle detection: it only waits on the unnamed `T`.
+            node: None,
+            deps: Vec::new(),
+            fallback: None,
+            step: Box::new(move |module, ctx| {
+                let graphs = visible(&module.imports, ctx);
+                let node = module
+                    .graph
+                    .get_node::<Arc<T>>()
+                    .or_else(|| Graph::search_all::<Arc<T>>(&graphs))
+                    .cloned()
+                    .ok_or(BuildError::MissingDependency {
+                        needed_by: TypeInfo {
+                            id: TypeId::of::<Arc<T>>(),
+                            name,
+                        },
+                        missing: TypeInfo::of::<Arc<T>>(),
+                    })?;
+                module.graph.provide_named(Some(name), Arc::new(node));
+                module
+                    .graph
+                    .record_edge(Graph::key::<Arc<T>>(Some(name)), Graph::key::<Arc<T>>(None));
+                Ok(())
+            }),
+        });
+        self.export_named::<T>(name)
+    }
+
+    /// Re-exports everything the imported module `T` exports, like listing a module in NestJS
+    /// `exports`. `T` must also be imported.
+    pub fn export_module<T: ModuleFactory + 'static>(mut self) -> Self {
+        self.reexported_modules.insert(TypeId::of::<T>());
+        self
+    }
+
+    /// Exports this module's view of the `Tag` collection, including imported contributions.
+    pub fn export_multi<Tag: MultiToken>(mut self) -> Self {
+        self.exports
+            .insert(Graph::key::<Multi<Tag>>(None), TypeInfo::of::<Tag>());
+        self
+    }
+
+    pub fn export_val<T>(mut self, _: &T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.exports
+            .insert(Graph::key::<T>(None), TypeInfo::of::<T>());
+        self
+    }
+
+    /// Provides `T` as a [`Scope::Singleton`]. The module's own `T` takes precedence over one
+    /// exported by an import or a global module; see [`Module::global`].
+    pub fn provide<T>(self) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        self.provide_with_scope::<T>(Scope::Singleton)
+    }
+
+    /// Provides `T` with the lifetime `scope`; see [`Scope`] for how each resolves.
+    /// [`Module::provide`], [`Module::provide_transient`] and `Module::provide_scoped` are
+    /// shorthands for the singleton, transient and request scopes.
+    pub fn provide_with_scope<T>(mut self, scope: Scope) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        match scope {
+            Scope::Singleton => self.push_provider::<T>(
+                None,
+                Graph::key::<Arc<T>>(None),
+                declared::<T>(),
+                Box::new(|module, ctx| resolve_own::<Arc<T>>(module, ctx, None)),
+            ),
+            #[cfg(feature = "actix")]
+            Scope::Request => {
+                self.scoped.push(ScopedProvider::of::<T>());
+                self.register::<T>(None);
+                self.provider_vals.push((
+                    None,
+                    Box::new(|module, _| {
+                        record_deferred::<T, T>(module);
+                        Ok(())
+                    }),
+                ));
+            }
+            Scope::Transient => self.push_val::<T>(Box::new(|module, _| {
+                module.graph.provide_transient::<T>();
+                record_deferred::<T, T>(module);
+                Ok(())
+            })),
+        }
+        self
+    }
+
+    /// Provides a separate instance of `T` under `name`, so several instances of the same type can
+    /// coexist. Consumers request it with `#[named("...")]` on an injected field or with
+    /// [`Graph::get_node_named`]. Unnamed `provide::<T>` is unaffected.
+    pub fn provide_named<T>(mut self, name: &'static str) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        self.push_provider::<T>(
+            Some(name),
+            Graph::key::<Arc<T>>(Some(name)),
+            declared::<T>(),
+            Box::new(move |module, ctx| resolve_own::<Arc<T>>(module, ctx, Some(name))),
+        );
+        self
+    }
+
+    /// Provides a separate instance of `T` under the [`Token`] `K`, like [`Module::provide_named`]
+    /// with a marker type in place of the name, so a typo or rename is a compile error.
+    /// Consumers inject it as a [`Tokened<K, T>`] field.
+    pub fn provide_with_token<K, T>(mut self) -> Self
+    where
+        K: Token,
+        T: Injected<Output = T> + 'static,
+    {
+        self.push_provider::<Tokened<K, T>>(
+            None,
+            Graph::key::<Tokened<K, T>>(None),
+            declared::<T>(),
+            Box::new(|module, ctx| resolve_own::<Tokened<K, T>>(module, ctx, None)),
+        );
+        self
+    }
+
+    /// Provides `T` as `Arc<U>`, usually a trait object, so consumers can depend on
+    /// `Arc<dyn Trait>` without naming the implementation; see [`Upcast`] for declaring the
+    /// coercion. The trait object shares its instance with the `Arc<T>` node, which stays
+    /// injectable in this module. Export it with `export::<dyn Trait>()`.
+    pub fn provide_as<T, U>(mut self) -> Self
+    where
+        T: Injected<Output = T> + Upcast<U> + Send + Sync + 'static,
+        U: ?Sized + Send + Sync + 'static,
+    {
+        self.push_provider::<Arc<U>>(
+            None,
+            Graph::key::<Arc<U>>(None),
+            vec![Graph::key::<Arc<T>>(None)],
+            Box::new(|module, ctx| {
+                let graphs = visible(&module.imports, ctx);
+                let concrete = module.graph.resolve::<Arc<T>>(&graphs)?.clone();
+                module.graph.provide(Arc::new(concrete.upcast()));
+                module
+                    .graph
+                    .record_edge(Graph::key::<Arc<U>>(None), Graph::key::<Arc<T>>(None));
+                Ok(())
+            }),
+        );
+        self
+    }
+
+    /// Makes `Arc<From>` resolve to the `Arc<To>` already in the graph, provided by this module
+    /// or visible from an import, so both inject the same instance. Unlike
+    /// [`Module::provide_as`], nothing is constructed or provided here: the alias only redirects
+    /// to wherever `To` comes from, which suits keeping a legacy name injectable after the
+    /// provider moves. Two distinct types can only share an instance through a coercion such as
+    /// `upcast!(NewClient => dyn LegacyClient)`, so `From` is usually a trait object.
+    pub fn alias<From, To>(mut self) -> Self
+    where
+        From: ?Sized + Send + Sync + 'static,
+        To: Upcast<From> + Send + Sync + 'static,
+    {
+        self.register::<Arc<From>>(None);
+        self.providers.push(Provider {
+            token: Some(Graph::key::<Arc<From>>(None)),
+            // Not a node of its own for cycle detection: it only waits on `To`.
+            node: None,
+            deps: Vec::new(),
+            fallback: None,
+            step: Box::new(|module, ctx| {
+                let graphs = visible(&module.imports, ctx);
+                let target = module
+                    .graph
+                    .get_node::<Arc<To>>()
+                    .or_else(|| Graph::search_all::<Arc<To>>(&graphs))
+                    .cloned()
+                    .ok_or(BuildError::MissingDependency {
+                        needed_by: TypeInfo::of::<From>(),
+                        missing: TypeInfo::of::<To>(),
+                    })?;
+                module.graph.provide(Arc::new(target.upcast()));
+                module
+                    .graph
+                    .record_edge(Graph::key::<Arc<From>>(None), Graph::key::<Arc<To>>(None));
+                Ok(())
+            }),
+        });
+        self
+    }
+
+    /// Contributes a new instance of `T` to the collection named by `Tag`, which consumers inject
+    /// as a [`Multi<Tag>`] field. Contributions append rather than overwrite. The collection is
+    /// sorted by `order`, lowest first, so a chain of plugins can run in a fixed order whichever
+    /// modules contribute them; contributions of the same order keep their declaration order.
+    pub fn provide_multi<Tag, T>(mut self, order: i32) -> Self
+    where
+        Tag: MultiToken,
+        T: Injected<Output = T> + Upcast<Tag::Item> + 'static,
+    {
+        self.providers.push(Provider {
+            token: None,
+            // The collection, so each contribution is timed under its name.
+            node: Some(Graph::key::<Multi<Tag>>(None)),
+            deps: Vec::new(),
+            fallback: None,
+            step: Box::new(move |module, ctx| {
+                let graphs = visible(&module.imports, ctx);
+                let item = T::resolve(&mut module.graph, &graphs)?;
+                module
+                    .graph
+                    .provide_multi::<Tag>(order, Arc::new(item).upcast(), &graphs);
+                module.graph.record_dependencies::<T>(
+                    Graph::key::<Multi<Tag>>(None),
+                    std::any::type_name::<Multi<Tag>>(),
+                );
+                Ok(())
+            }),
+        });
+        self
+    }
+
+    /// Provides `T` from a factory, the equivalent of NestJS `useFactory`.
+    ///
+    /// The factory runs once imports are resolved and receives a graph holding this module's
+    /// providers along with everything visible from its imports, so dependencies can be looked
+    /// up with `graph.get_node::<Arc<Dep>>()`. The result is injectable and exportable as `Arc<T>`.
+    pub fn provide_factory<T, F>(self, f: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&Graph) -> T + 'static,
+    {
+        self.provide_factory_with_deps(&[], f)
+    }
+
+    /// Like [`Module::provide_factory`], declaring the nodes the factory reads, in the form
+    /// `Injected::dependencies` uses, e.g. `&[Graph::dependency::<Config>()]`.
+    ///
+    /// The factory only runs once each of `deps` is in the graph: this module's providers of them
+    /// are resolved first, whatever order they were declared in, and the build fails with
+    /// `BuildError::MissingDependency` if one is never provided. The declared edges also count
+    /// towards [`BuildReport::unused_providers`] and `ApplicationContext::to_dot`.
+    pub fn provide_factory_with_deps<T, F>(mut self, deps: &[(NodeKey, &'static str)], f: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&Graph) -> T + 'static,
+    {
+        let mut f = Some(f);
+        let declared = deps.to_vec();
+        self.push_provider::<T>(
+            None,
+            Graph::key::<Arc<T>>(None),
+            declared.iter().map(|(key, _)| *key).collect(),
+            Box::new(move |module, ctx| {
+                let view = module.view(ctx);
+                if let Some((key, name)) = declared.iter().find(|(key, _)| view.node(key).is_none())
+                {
+                    return Err(BuildError::MissingDependency {
+                        needed_by: TypeInfo::of::<T>(),
+                        missing: TypeInfo { id: key.0, name },
+                    });
+                }
+                if let Some(f) = f.take() {
+                    let value = f(&view);
+                    module.graph.provide(Arc::new(Arc::new(value)));
+                    for (key, _) in &declared {
+                        module.graph.record_edge(Graph::key::<Arc<T>>(None), *key);
+                    }
+                }
+                Ok(())
+            }),
+        );
+        self
+    }
+
+    /// Provides `T` built by `f` from the configuration `C`, e.g. one loaded by
+    /// [`ConfigModule`](crate::config::ConfigModule), a shorthand for
+    /// [`Module::provide_factory_with_deps`] with `C` as the only dependency. The build fails
+    /// with `BuildError::MissingDependency` if nothing visible provides `Arc<C>`.
+    pub fn provide_from_config<C, T>(self, f: impl FnOnce(&C) -> T + 'static) -> Self
+    where
+        C: Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        self.provide_factory_with_deps(&[Graph::dependency::<C>()], |graph| {
+            f(graph.get_node::<Arc<C>>().unwrap())
+        })
+    }
+
+    /// Provides `T` from an async factory, such as a connection pool that must be awaited.
+    ///
+    /// Modules declaring async providers must be built with [`Module::try_build_async`] or
+    /// [`ApplicationContext::build_async`]. Async providers are awaited after imports and values
+    /// are resolved, in declaration order apart from those waiting on others through
+    /// [`Module::provide_async_with_deps`], and before any synchronous providers, so those may
+    /// depend on them. The factory sees the same graph as [`Module::provide_factory`].
+    pub fn provide_async<T, F, Fut>(self, f: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&Graph) -> Fut + 'static,
+        Fut: Future<Output = T> + 'static,
+    {
+        self.provide_async_with_deps(&[], f)
+    }
+
+    /// Like [`Module::provide_async`], declaring the nodes the factory reads, as
+    /// [`Module::provide_factory_with_deps`] does. The factory is awaited after this module's
+    /// async providers of `deps`, whatever order they were declared in, and the build fails with
+    /// `BuildError::MissingDependency` if one isn't in the graph by then. Since synchronous
+    /// providers resolve later, `deps` can only name imports, values and other async providers.
+    pub fn provide_async_with_deps<T, F, Fut>(
+        mut self,
+        deps: &[(NodeKey, &'static str)],
+        f: F,
+    ) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&Graph) -> Fut + 'static,
+        Fut: Future<Output = T> + 'static,
+    {
+        self.async_providers.push(AsyncProvider {
+            info: TypeInfo::of::<T>(),
+            node: Graph::key::<Arc<T>>(None),
+            deps: deps.to_vec(),
+            step: Box::new(|graph| {
+                f(&graph)
+                    .map(|value| -> Result<InsertAsync, BuildError> {
+                        Ok(Box::new(|graph| {
+                            graph.provide(Arc::new(Arc::new(value)));
+                        }))
+                    })
+                    .boxed_local()
+            }),
+        });
+        self.register::<T>(None);
+        self
+    }
+
+    /// Like [`Module::provide_async`], but for a factory that can fail, such as one connecting to
+    /// a database that may still be starting. A failed attempt is logged and retried after the
+    /// wait `policy` sets, and the build fails with [`BuildError::ProviderFailed`] once every
+    /// attempt has failed.
+    pub fn provide_async_retry<T, E, F, Fut>(mut self, f: F, policy: RetryPolicy) -> Self
+    where
+        T: Send + Sync + 'static,
+        E: Into<InitError>,
+        F: Fn(&Graph) -> Fut + 'static,
+        Fut: Future<Output = Result<T, E>> + 'static,
+    {
+        self.async_providers.push(AsyncProvider {
+            info: TypeInfo::of::<T>(),
+            node: Graph::key::<Arc<T>>(None),
+            deps: Vec::new(),
+            step: Box::new(move |graph| {
+                async move {
+                    let value = retry::run(policy, || f(&graph)).await?;
+                    Ok(Box::new(|graph: &mut Graph| {
+                        graph.provide(Arc::new(Arc::new(value)));
+                    }) as InsertAsync)
+                }
+                .boxed_local()
+            }),
+        });
+        self.register::<T>(None);
+        self
+    }
+
+    /// Provides `t` as is, moved into the graph when the module is built, so it needn't be
+    /// `Clone`.
+    pub fn provide_val<T>(mut self, t: T) -> Self
+    where
+        T: Sync + Send + 'static,
+    {
+        self.push_val::<T>(Box::new(|module, _| {
+            module.graph.provide(Arc::new(t));
+            Ok(())
+        }));
+        self
+    }
+
+    /// Provides the value returned by `f`, which runs when the module is built so that loading
+    /// it can fail the build.
+    pub(crate) fn provide_val_with<T, F>(mut self, f: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&ApplicationContext) -> Result<T, BuildError> + 'static,
+    {
+        self.push_val::<T>(Box::new(|module, ctx| {
+            module.graph.provide(Arc::new(f(ctx)?));
+            Ok(())
+        }));
+        self
+    }
+
+    /// Runs `f` when the module is built, in order with the values it provides.
+    pub(crate) fn on_build<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut ApplicationContext) -> Result<(), BuildError> + 'static,
+    {
+        self.provider_vals.push((None, Box::new(|_, ctx| f(ctx))));
+        self
+    }
+
+    /// Provides `T` as transient: every consumer that injects `Arc<T>` gets a new instance
+    /// rather than a shared one. `T` may depend on singletons and on other transients. Exported
+    /// transients keep resolving their dependencies from this module.
+    ///
+    /// Transients are constructed only for injected fields, so they aren't visible through
+    /// `Graph::get_node`, for instance from a factory.
+    pub fn provide_transient<T>(self) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        self.provide_with_scope::<T>(Scope::Transient)
+    }
+
+    /// Provides `T` per HTTP request rather than as a singleton. Handlers extract it with
+    /// [`Scoped<T>`](crate::scope::Scoped), which constructs it on first use in a request and
+    /// drops it when the request completes.
+    ///
+    /// `T` may depend on other scoped providers and on anything visible from this module. Scoped
+    /// providers are registered as app data for the whole application rather than per
+    /// `ServiceConfig`, so any client's handlers can extract them, but singletons can't depend on
+    /// them.
+    #[cfg(feature = "actix")]
+    pub fn provide_scoped<T>(self) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        self.provide_with_scope::<T>(Scope::Request)
+    }
+
+    /// Provides `T` as a [`Lazy<T>`], which constructs `T` the first time it's requested rather
+    /// than while this module builds. Consumers inject `Arc<Lazy<T>>`, and every request shares
+    /// the one instance.
+    pub fn provide_lazy<T>(mut self) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        self.push_val::<Lazy<T>>(Box::new(|module, _| {
+            module
+                .graph
+                .provide(Arc::new(Arc::new(Lazy::<T>::new(module.name))));
+            record_deferred::<T, Lazy<T>>(module);
+            Ok(())
+        }));
+        self.lazy.push(Box::new(|module, view| {
+            if let Some(lazy) = module.graph.get_node::<Arc<Lazy<T>>>() {
+                lazy.bind(view.clone());
+            }
+        }));
+        self
+    }
+
+    /// Like [`Module::provide`], but if `T` can't be resolved, e.g. because an optional
+    /// integration's configuration is missing, `fallback` is provided in its place, with a
+    /// `tracing` warning, instead of failing the build. Consumers of `T` get the fallback like any other
+    /// instance.
+    ///
+    /// A missing dependency only counts as a failure once nothing else in the module can resolve,
+    /// since it may be provided later in the same module.
+    pub fn provide_or<T>(self, fallback: T) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        let mut module = self.provide::<T>();
+        module.providers.last_mut().unwrap().fallback = Some(Box::new(|module, err| {
+            let name = std::any::type_name::<T>();
+            crate::trace::warn(
+                name,
+                format_args!("failed to resolve, using fallback: {err}"),
+            );
+            module.graph.provide(Arc::new(Arc::new(fallback)));
+        }));
+        module
+    }
+
+    /// Provides a [`Factory<T>`], which constructs a new `T` each time it's called. Consumers
+    /// inject `Arc<Factory<T>>`; `T` itself isn't injectable.
+    pub fn provide_on_demand<T>(mut self) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        self.push_val::<Factory<T>>(Box::new(|module, _| {
+            module
+                .graph
+                .provide(Arc::new(Arc::new(Factory::<T>::new(module.name))));
+            record_deferred::<T, Factory<T>>(module);
+            Ok(())
+        }));
+        self.lazy.push(Box::new(|module, view| {
+            if let Some(factory) = module.graph.get_node::<Arc<Factory<T>>>() {
+                factory.bind(view.clone());
+            }
+        }));
+        self
+    }
+
+    /// Like [`Module::provide`], but only when `condition` holds. Otherwise nothing is
+    /// registered, so consumers injecting `Option<Arc<T>>` get `None`.
+    pub fn provide_if<T>(self, condition: bool) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        if condition {
+            self.provide::<T>()
+        } else {
+            self
+        }
+    }
+
+    /// Provides `Arc<U>` from one of several implementations, picked while the module is declared,
+    /// so consumers inject the same trait object whichever is chosen. Candidates are tried in
+    /// order and the first whose condition holds is provided as with [`Module::provide_as`]:
+    ///
+    /// ```ignore
+    /// Module::new()
+    ///     .provide_one_of::<dyn Mailer>(|mailers| {
+    ///         mailers
+    ///             .when::<MockMailer>(cfg!(feature = "mock-mail"))
+    ///             .otherwise::<SmtpMailer>()
+    ///     })
+    ///     .export::<dyn Mailer>()
+    /// ```
+    ///
+    /// If no condition holds, nothing is provided.
+    pub fn provide_one_of<U>(self, select: impl FnOnce(OneOf<U>) -> OneOf<U>) -> Self
+    where
+        U: ?Sized + Send + Sync + 'static,
+    {
+        let candidates = select(OneOf {
+            chosen: None,
+            target: PhantomData,
+        });
+        match candidates.chosen {
+            Some(provide) => provide(self),
+            None => self,
+        }
+    }
+
+    /// Like [`Module::provide_val`], but only when `condition` holds.
+    pub fn provide_val_if<T>(self, t: T, condition: bool) -> Self
+    where
+        T: Sync + Send + 'static,
+    {
+        if condition {
+            self.provide_val(t)
+        } else {
+            self
+        }
+    }
+
+    /// Registers `T`'s routes when the application is configured. `T` is also provided as
+    /// `Arc<T>` like any provider, so other providers can depend on it and it can be exported.
+    ///
+    /// A dependency of `T` or of one of its guards that no visible module provides fails the
+    /// build with [`BuildError::MissingDependency`] naming `T` and what it's missing.
+    #[cfg(feature = "actix")]
+    pub fn client<T>(mut self) -> Self
+    where
+        T: Injected<Output = T> + ServiceFactory + 'static,
+    {
+        self = self.provide::<T>();
+        self.entry_points.push(Graph::key::<Arc<T>>(None));
+        self.clients.push(Box::new(|module, ctx| {
+            let client = module.graph.get_node::<Arc<T>>().cloned().ok_or(
+                BuildError::UnresolvedProvider {
+                    provider: TypeInfo::of::<T>(),
+                    module: module.name,
+                },
+            )?;
+            let graphs = visible(&module.imports, ctx);
+            let resolved = module.graph.node_keys().len();
+            // A guard is constructed for the client alone, so the client is what's missing it.
+            let guards = T::guards(&mut module.graph, &graphs).map_err(|err| match err {
+                BuildError::MissingDependency { missing, .. } => BuildError::MissingDependency {
+                    needed_by: TypeInfo::of::<T>(),
+                    missing,
+                },
+                err => err,
+            })?;
+            // Guards are resolved into the graph with the client, which consumes them.
+            let guards_resolved = module.graph.node_keys()[resolved..].to_vec();
+            module.entry_points.extend(guards_resolved);
+            let layers = RouteLayers {
+                filters: module.filters.clone(),
+                guards,
+                interceptors: module.interceptors.clone(),
+            };
+            if layers.is_empty() {
+                module.clients.push(client);
+            } else {
+                module
+                    .clients
+                    .push(Arc::new(LayeredClient { client, layers }));
+            }
+            Ok(())
+        }));
+        self
+    }
+
+    /// Registers `T`'s routes on the axum router [`Axum`](crate::backend::Axum) builds. Like
+    /// [`Module::client`], `T` is also provided as `Arc<T>`; guards, interceptors and exception
+    /// filters only wrap actix clients.
+    #[cfg(feature = "axum")]
+    pub fn axum_client<T>(mut self) -> Self
+    where
+        T: Injected<Output = T> + AxumServiceFactory + 'static,
+    {
+        self = self.provide::<T>();
+        self.entry_points.push(Graph::key::<Arc<T>>(None));
+        self.axum_clients.push(Box::new(|module, _| {
+            let client = module.graph.get_node::<Arc<T>>().cloned().ok_or(
+                BuildError::UnresolvedProvider {
+                    provider: TypeInfo::of::<T>(),
+                    module: module.name,
+                },
+            )?;
+            module.axum_clients.push(client);
+            Ok(())
+        }));
+        self
+    }
+
+    /// Runs the [`Interceptor`] `I` around every route of this module's controllers, resolved from
+    /// this module so it can depend on providers. Interceptors run inside the controllers' guards,
+    /// in declaration order with the first declared outermost.
+    #[cfg(feature = "actix")]
+    pub fn interceptor<I>(mut self) -> Self
+    where
+        I: Injected<Output = I> + Interceptor,
+    {
+        self.entry_points.push(Graph::key::<Arc<I>>(None));
+        self.interceptors.push(Box::new(|module, ctx| {
+            let graphs = visible(&module.imports, ctx);
+            let interceptor = module.graph.resolve::<Arc<I>>(&graphs)?.clone();
+            module.interceptors.push(interceptor);
+            Ok(())
+        }));
+        self
+    }
+
+    /// Maps errors from every route of this module's controllers with the [`ExceptionFilter`] `F`,
+    /// resolved from this module so it can depend on providers. Filters run outside the
+    /// controllers' guards and interceptors, and are tried in declaration order until one returns a
+    /// response.
+    #[cfg(feature = "actix")]
+    pub fn exception_filter<F>(mut self) -> Self
+    where
+        F: Injected<Output = F> + ExceptionFilter,
+    {
+        self.entry_points.push(Graph::key::<Arc<F>>(None));
+        self.filters.push(Box::new(|module, ctx| {
+            let graphs = visible(&module.imports, ctx);
+            let filter = module.graph.resolve::<Arc<F>>(&graphs)?.clone();
+            module.filters.push(filter);
+            Ok(())
+        }));
+        self
+    }
+
+    /// Wraps the application's clients in the middleware `M`, resolved from this module so it can
+    /// depend on providers. Middleware applies to every client in the application and runs in
+    /// declaration order, the first declared outermost; a module's middleware comes before that
+    /// of its imports.
+    #[cfg(feature = "actix")]
+    pub fn middleware<M>(mut self) -> Self
+    where
+        M: Injected<Output = M>
+            + Transform<
+                MiddlewareService,
+                ServiceRequest,
+                Response = ServiceResponse,
+                Error = actix_web::Error,
+                InitError = (),
+            > + Send
+            + Sync
+            + 'static,
+    {
+        self.middleware.push(Box::new(|module, ctx| {
+            let graphs = visible(&module.imports, ctx);
+            let resolved = M::resolve(&mut module.graph, &graphs)?;
+            module
+                .graph
+                .record_dependencies::<M>(Graph::key::<M>(None), std::any::type_name::<M>());
+            module.middleware.push(Middleware::new(resolved));
+            Ok(())
+        }));
+        self.register::<M>(None);
+        self
+    }
+
+    /// Provides `T` in place of every earlier registration of `T` in this module. Registering a
+    /// type twice is otherwise a [`BuildError::DuplicateProvider`]; `replace` makes the intent
+    /// explicit, e.g. when extending a module returned by a shared constructor.
+    pub fn replace<T>(mut self) -> Self
+    where
+        T: Injected<Output = T> + 'static,
+    {
+        let key = Graph::key::<T>(None);
+        self.providers
+            .retain(|provider| provider.token != Some(key));
+        self.provider_vals.retain(|(token, _)| *token != Some(key));
+        self.async_providers
+            .retain(|provider| provider.info.id != key.0);
+        #[cfg(feature = "actix")]
+        self.scoped.retain(|provider| provider.info().id != key.0);
+        self.duplicates.retain(|(token, _)| *token != key);
+        self.tokens.remove(&key);
+        self.provide::<T>()
+    }
+
+    /// Records that this module registers `T`, noting a duplicate if it already does.
+    fn register<T: 'static>(&mut self, name: Option<&'static str>) {
+        let key = Graph::key::<T>(name);
+        if !self.tokens.insert(key) {
+            self.duplicates.push((key, TypeInfo::of::<T>()));
+        }
+    }
+
+    fn push_provider<T: 'static>(
+        &mut self,
+        name: Option<&'static str>,
+        node: NodeKey,
+        deps: Vec<NodeKey>,
+        step: ProviderStep,
+    ) {
+        self.register::<T>(name);
+        self.providers.push(Provider {
+            token: Some(Graph::key::<T>(name)),
+            node: Some(node),
+            deps,
+            step,
+            fallback: None,
+        });
+    }
+
+    fn push_val<T: 'static>(&mut self, step: BuildStep) {
+        self.register::<T>(None);
+        self.provider_vals.push((Some(Graph::key::<T>(None)), step));
+    }
+
+    /// Seeds `value` as the `Arc<T>` node before anything else in this module resolves, so it wins
+    /// over this module's own providers and over imported exports. Used by
+    /// `testing::ModuleBuilder`.
+    pub(crate) fn override_provider<T>(mut self, value: Arc<T>) -> Self
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.provider_vals.insert(0, (None, seed(value)));
+        self
+    }
+
+    /// Like [`Module::override_provider`], but for the module declared by `M` wherever it's built
+    /// while building this module's tree, including as this module itself. Used by
+    /// `testing::ModuleBuilder`.
+    pub(crate) fn override_provider_in<M, T>(mut self, value: Arc<T>) -> Self
+    where
+        M: ModuleFactory + 'static,
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.nested_overrides.push((TypeId::of::<M>(), seed(value)));
+        self
+    }
+
+    /// Hands this module's nested overrides to `ctx` for the modules below it, and takes the ones
+    /// `ctx` holds for this module.
+    fn apply_nested_overrides(&mut self, ctx: &mut ApplicationContext) {
+        ctx.nested_overrides.append(&mut self.nested_overrides);
+        let Some(info) = self.info else {
+            return;
+        };
+        let (own, rest) = std::mem::take(&mut ctx.nested_overrides)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(id, _)| *id == info.id);
+        ctx.nested_overrides = rest;
+        for (_, step) in own {
+            self.provider_vals.insert(0, (None, step));
+        }
+    }
+
+    /// Runs `T`'s [`OnModuleInit`] hook from [`ApplicationContext::init_all`]. `T` must be provided
+    /// by this module.
+    pub fn on_init<T: OnModuleInit>(mut self) -> Self {
+        self.entry_points.push(Graph::key::<Arc<T>>(None));
+        self.init_hooks.push(hook_step(lifecycle::init_hook::<T>));
+        self
+    }
+
+    /// Runs `T`'s [`OnModuleDestroy`] hook from [`ApplicationContext::shutdown`]. `T` must be
+    /// provided by this module.
+    pub fn on_destroy<T: OnModuleDestroy>(mut self) -> Self {
+        self.entry_points.push(Graph::key::<Arc<T>>(None));
+        self.destroy_hooks
+            .push(hook_step(lifecycle::destroy_hook::<T>));
+        self
+    }
+
+    /// Runs `T`'s [`OnRequestEnd`] hook when a request that constructed `T` completes. `T` must be
+    /// provided by this module with `Module::provide_scoped`.
+    #[cfg(feature = "actix")]
+    pub fn on_request_end<T: OnRequestEnd>(mut self) -> Self {
+        self.request_cleanups
+            .push((TypeInfo::of::<T>(), crate::scope::cleanup::<T>()));
+        self
+    }
+
+    /// Reports `T`'s [`HealthCheck`] from `HealthModule`'s `/health` endpoint. `T` must be
+    /// provided by this module.
+    #[cfg(feature = "actix")]
+    pub fn health_check<T: HealthCheck>(mut self) -> Self {
+        self.entry_points.push(Graph::key::<Arc<T>>(None));
+        self.health_checks.push(Box::new(|module| {
+            module
+                .graph
+                .get_node::<Arc<T>>()
+                .map(|provider| RegisteredCheck::new(provider.clone()))
+                .ok_or(BuildError::UnresolvedProvider {
+                    provider: TypeInfo::of::<T>(),
+                    module: module.name,
+                })
+        }));
+        self
+    }
+
+    /// Resolves the module's imports, providers and clients, returning the first failure.
+    pub fn try_build(self, ctx: &mut ApplicationContext) -> Result<ResolvedModule, BuildError> {
+        let _span = crate::trace::module(self.name());
+        match self.info {
+            Some(info) => {
+                ctx.enter((info.id, self.instance), info.name)?;
+                let result = self.resolve(ctx);
+                ctx.resolving.pop();
+                result
+            }
+            None => self.resolve(ctx),
+        }
+    }
+
+    /// Like [`Module::try_build`], but also awaits async providers throughout the import tree.
+    pub fn try_build_async(
+        self,
+        ctx: &mut ApplicationContext,
+    ) -> LocalBoxFuture<'_, Result<ResolvedModule, BuildError>> {
+        let name = self.name();
+        crate::trace::module_async(name, async move {
+            match self.info {
+                Some(info) => {
+                    ctx.enter((info.id, self.instance), info.name)?;
+                    let result = self.resolve_async(ctx).await;
+                    ctx.resolving.pop();
+                    result
+                }
+                None => self.resolve_async(ctx).await,
+            }
+        })
+        .boxed_local()
+    }
+
+    fn resolve(mut self, ctx: &mut ApplicationContext) -> Result<ResolvedModule, BuildError> {
+        self.apply_nested_overrides(ctx);
+        let mut module = ResolvedModule::new();
+        module.name = self.name();
+        module.id = self.info.map(|info| info.id);
+        module.version = self.version.take();
+        module.prefix = self.prefix;
+        module
+            .graph
+            .provide_hidden(Arc::new(Arc::new(ModuleRef::new(module.name))));
+        self.check_versions()?;
+        self.check_duplicates()?;
+
+        for mut import in std::mem::take(&mut self.imports) {
+            let (key, bundle) = (import.target.key, import.bundle);
+            let required = import.required.take();
+            let Some(resolved) = import.resolve(ctx)? else {
+                continue;
+            };
+            if let Some(required) = required {
+                check_version(&required, module.name, &resolved)?;
+            }
+            if let Some(bundle) = bundle {
+                bundle(&resolved, &mut module.graph);
+            }
+            self.reexport(key, &resolved);
+            module.imports.push(resolved);
+        }
+
+        if let Some(provider) = self.async_providers.first() {
+            return Err(BuildError::AsyncProvider {
+                provider: provider.info,
+                module: module.name,
+            });
+        }
+
+        self.finish(module, ctx)
+    }
+
+    async fn resolve_async(
+        mut self,
+        ctx: &mut ApplicationContext,
+    ) -> Result<ResolvedModule, BuildError> {
+        self.apply_nested_overrides(ctx);
+        let mut module = ResolvedModule::new();
+        module.name = self.name();
+        module.id = self.info.map(|info| info.id);
+        module.version = self.version.take();
+        module.prefix = self.prefix;
+        module
+            .graph
+            .provide_hidden(Arc::new(Arc::new(ModuleRef::new(module.name))));
+        self.check_versions()?;
+        self.check_duplicates()?;
+
+        for mut import in std::mem::take(&mut self.imports) {
+            let (key, bundle) = (import.target.key, import.bundle);
+            let required = import.required.take();
+            let Some(resolved) = import.resolve_async(ctx).await? else {
+                continue;
+            };
+            if let Some(required) = required {
+                check_version(&required, module.name, &resolved)?;
+            }
+            if let Some(bundle) = bundle {
+                bundle(&resolved, &mut module.graph);
+            }
+            self.reexport(key, &resolved);
+            module.imports.push(resolved);
+        }
+
+        for (_, provided_val) in std::mem::take(&mut self.provider_vals) {
+            provided_val(&mut module, ctx)?;
+        }
+
+        let async_providers = in_dependency_order(std::mem::take(&mut self.async_providers), |p| {
+            (Some(p.node), p.deps.iter().map(|(key, _)| *key).collect())
+        });
+        for provider in async_providers {
+            let view = module.view(ctx);
+            if let Some((key, name)) = provider
+                .deps
+                .iter()
+                .find(|(key, _)| view.node(key).is_none())
+            {
+                return Err(BuildError::MissingDependency {
+                    needed_by: provider.info,
+                    missing: TypeInfo { id: key.0, name },
+                });
+            }
+            let started = ctx.timings.is_some().then(Instant::now);
+            let insert = (provider.step)(view).await?;
+            insert(&mut module.graph);
+            if let (Some(started), Some(timings)) = (started, ctx.timings.as_mut()) {
+                let name = module.graph.label(&provider.node);
+                *timings.entry(name).or_default() += started.elapsed();
+            }
+            for (key, _) in &provider.deps {
+                module.graph.record_edge(provider.node, *key);
+            }
+        }
+
+        self.finish(module, ctx)
+    }
+
+    fn check_versions(&self) -> Result<(), BuildError> {
+        match self.invalid_versions.first() {
+            Some((version, message)) => Err(BuildError::InvalidVersion {
+                module: self.name(),
+                version: version.clone(),
+                message: message.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn check_duplicates(&self) -> Result<(), BuildError> {
+        match self.duplicates.first() {
+            Some((_, provider)) => Err(BuildError::DuplicateProvider {
+                provider: *provider,
+                module: self.name(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds everything `import` exports to this module's exports if it was passed to
+    /// [`Module::export_module`].
+    fn reexport(&mut self, key: Option<ModuleKey>, import: &ResolvedModule) {
+        if key.is_some_and(|(id, _)| self.reexported_modules.contains(&id)) {
+            let exports = &import.graphed_exports;
+            self.exports.extend(exports.keys().map(|key| {
+                let name = exports.label(&key);
+                (key, TypeInfo { id: key.0, name })
+            }));
+        }
+    }
+
+    fn finish(
+        self,
+        mut module: ResolvedModule,
+        ctx: &mut ApplicationContext,
+    ) -> Result<ResolvedModule, BuildError> {
+        module.entry_points.extend(self.entry_points);
+        for (_, provided_val) in self.provider_vals {
+            provided_val(&mut module, ctx)?;
+        }
+
+        resolve_providers(self.providers, &mut module, ctx)?;
+
+        #[cfg(feature = "actix")]
+        for interceptor in self.interceptors {
+            interceptor(&mut module, ctx)?;
+        }
+
+        #[cfg(feature = "actix")]
+        for filter in self.filters {
+            filter(&mut module, ctx)?;
+        }
+
+        #[cfg(feature = "actix")]
+        for client in self.clients {
+            client(&mut module, ctx)?;
+        }
+
+        #[cfg(feature = "axum")]
+        for client in self.axum_clients {
+            client(&mut module, ctx)?;
+        }
+
+        #[cfg(feature = "actix")]
+        for middleware in self.middleware {
+            middleware(&mut module, ctx)?;
+        }
+
+        ctx.init_hooks
+            .extend(collect_hooks(self.init_hooks, &module)?);
+        ctx.destroy_hooks
+            .extend(collect_hooks(self.destroy_hooks, &module)?);
+        #[cfg(feature = "actix")]
+        for health_check in self.health_checks {
+            let check = health_check(&module)?;
+            module.health_checks.push(check);
+        }
+
+        #[cfg(feature = "actix")]
+        let mut scoped = self.scoped;
+        #[cfg(feature = "actix")]
+        for (info, cleanup) in self.request_cleanups {
+            scoped
+                .iter_mut()
+                .find(|provider| provider.info().id == info.id)
+                .ok_or(BuildError::UnresolvedProvider {
+                    provider: info,
+                    module: module.name,
+                })?
+                .set_cleanup(cleanup);
+        }
+
+        #[cfg(feature = "actix")]
+        if !scoped.is_empty() {
+            let singletons = Arc::new(module.view(ctx));
+            module.scoped = scoped
+                .into_iter()
+                .map(|provider| provider.with_singletons(singletons.clone()))
+                .collect();
+        }
+
+        if !self.lazy.is_empty() {
+            let view = Arc::new(module.view(ctx));
+            for bind in self.lazy {
+                bind(&module, &view);
+            }
+        }
+
+        let keys: HashSet<NodeKey> = self.exports.keys().copied().collect();
+        let mut exports = module.graph.filter_by(keys.clone());
+        if exports.has_transients() {
+            // Exported transients are constructed downstream, so they take the internal
+            // providers they depend on along, without exporting them.
+            let view = module.view(ctx);
+            let closure = view.dependency_closure(keys.iter().copied());
+            exports.bind_transients(Arc::new(view.filter_by(closure)));
+        }
+        // Exports this module doesn't provide itself re-export whichever import exports them.
+        let imported: Vec<&Graph> = module.imports.iter().map(|m| &m.graphed_exports).collect();
+        let exports = exports.layered(&imported).filter_by(keys);
+        let exported: HashSet<NodeKey> = exports.keys().collect();
+        if let Some((_, export)) = self.exports.iter().find(|(key, _)| !exported.contains(key)) {
+            return Err(BuildError::ExportNotProvided {
+                export: *export,
+                module: module.name,
+            });
+        }
+        module.graphed_exports = exports;
+        if self.is_global {
+            for key in module.graphed_exports.keys() {
+                if ctx.global_providers.node(&key).is_none() {
+                    ctx.global_owners.insert(key, module.info());
+                }
+            }
+            ctx.global_providers = ctx.global_providers.layered(&[&module.graphed_exports]);
+        }
+        if let Some(module_ref) = module.graph.get_node::<Arc<ModuleRef>>() {
+            module_ref.set_providers(module.list_providers());
+        }
+        crate::trace::module_built(&module);
+        Ok(module)
+    }
+
+    /// Like [`Module::try_build`], but panics with the error's description on failure.
+    pub fn build(self, ctx: &mut ApplicationContext) -> ResolvedModule {
+        self.try_build(ctx).unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+/// Fails with [`BuildError::VersionMismatch`] unless `import`, imported by `importer`, declares a
+/// version matching `required`.
+fn check_version(
+    required: &VersionReq,
+    importer: &'static str,
+    import: &ResolvedModule,
+) -> Result<(), BuildError> {
+    match &import.version {
+        Some(version) if required.matches(version) => Ok(()),
+        version => Err(BuildError::VersionMismatch {
+            importer,
+            module: import.name,
+            required: required.to_string(),
+            version: version.as_ref().map(Version::to_string),
+        }),
+    }
+}
+
+/// A build step seeding `value` as the `Arc<T>` node.
+fn seed<T: ?Sized + Send + Sync + 'static>(value: Arc<T>) -> BuildStep {
+    Box::new(|module, _| {
+        module.graph.provide(Arc::new(value));
+        Ok(())
+    })
+}
+
+/// Records what `T` declares it depends on as edges from the `Arc<N>` provider that constructs
+/// `T` after the build: a transient, request-scoped, lazy or on-demand provider. Resolving them
+/// later records nothing, so without these edges their dependencies would look unused in
+/// [`BuildReport::unused_providers`].
+fn record_deferred<T: Injected, N: ?Sized + 'static>(module: &mut ResolvedModule) {
+    let from = Graph::key::<Arc<N>>(None);
+    module
+        .graph
+        .record_dependencies::<T>(from, std::any::type_name::<Arc<N>>());
+}
+
+/// The graph keys of what `T`'s derived or hand-written `Injected` impl reads.
+fn declared<T: Injected>() -> Vec<NodeKey> {
+    T::dependencies().into_iter().map(|(key, _)| key).collect()
+}
+
+/// Reorders `providers` so each comes after the providers of its declared dependencies, keeping
+/// declaration order otherwise. `links` gives the key a provider registers and the keys it
+/// depends on. Providers in a cycle stay in place, for the build to report.
+fn in_dependency_order<P>(
+    providers: Vec<P>,
+    links: impl Fn(&P) -> (Option<NodeKey>, Vec<NodeKey>),
+) -> Vec<P> {
+    type Links = [(Option<NodeKey>, Vec<NodeKey>)];
+
+    fn visit(i: usize, links: &Links, state: &mut [u8], order: &mut Vec<usize>) {
+        // 0: unvisited, 1: on the current path, 2: placed.
+        if state[i] != 0 {
+            return;
+        }
+        state[i] = 1;
+        for dep in &links[i].1 {
+            if let Some(j) = links
+                .iter()
+                .position(|(token, _)| token.as_ref() == Some(dep))
+            {
+                visit(j, links, state, order);
+            }
+        }
+        state[i] = 2;
+        order.push(i);
+    }
+
+    let links: Vec<_> = providers.iter().map(links).collect();
+    let mut state = vec![0; providers.len()];
+    let mut order = Vec::with_capacity(providers.len());
+    for i in 0..providers.len() {
+        visit(i, &links, &mut state, &mut order);
+    }
+    let mut slots: Vec<Option<P>> = providers.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .map(|i| slots[i].take().unwrap())
+        .collect()
+}
+
+/// Runs `providers` to a fixpoint, so they resolve in dependency order whatever order they were
+/// declared in. Each pass defers the providers still missing a dependency; the build fails once a
+/// pass makes no progress, reporting the last missing dependency.
+fn resolve_providers(
+    pending: Vec<Provider>,
+    module: &mut ResolvedModule,
+    ctx: &mut ApplicationContext,
+) -> Result<(), BuildError> {
+    let mut pending = in_dependency_order(pending, |p| (p.token, p.deps.clone()));
+    while !pending.is_empty() {
+        let attempted = pending.len();
+        // Each deferred provider, with the dependency it's missing.
+        let mut deferred: Vec<(Provider, BuildError)> = Vec::new();
+        // What each deferred provider is waiting on.
+        let mut waits = Vec::new();
+        for mut provider in pending {
+            let started = ctx.timings.is_some().then(Instant::now);
+            match (provider.step)(module, ctx) {
+                Ok(()) => {
+                    if let (Some(started), Some(node)) = (started, provider.node) {
+                        let name = module.graph.label(&node);
+                        let timings = ctx.timings.as_mut().unwrap();
+                        *timings.entry(name).or_default() += started.elapsed();
+                    }
+                }
+                Err(err @ BuildError::MissingDependency { .. }) => {
+                    if let (Some(node), BuildError::MissingDependency { missing, .. }) =
+                        (provider.node, &err)
+                    {
+                        waits.push((node.0, *missing));
+                    }
+                    deferred.push((provider, err));
+                }
+                Err(err) => match provider.fallback {
+                    Some(fallback) => fallback(module, &err),
+                    None => return Err(err),
+                },
+            }
+        }
+        if deferred.len() == attempted {
+            // Nothing resolved this round, so substituting a fallback is the only way forward:
+            // the first stalled provider that has one gives up, and the rest try again.
+            match deferred
+                .iter()
+                .position(|(provider, _)| provider.fallback.is_some())
+            {
+                Some(i) => {
+                    let (provider, err) = deferred.remove(i);
+                    (provider.fallback.unwrap())(module, &err);
+                }
+                None => {
+                    let (_, err) = deferred.pop().unwrap();
+                    return Err(match dependency_cycle(&waits) {
+                        Some(cycle) => BuildError::CircularDependency { cycle },
+                        None => err,
+                    });
+                }
+            }
+        }
+        pending = deferred.into_iter().map(|(provider, _)| provider).collect();
+    }
+    Ok(())
+}
+
+/// The providers of the tree rooted at `root` nothing consumes; see
+/// [`BuildReport::unused_providers`].
+fn unused_providers(root: &ResolvedModule) -> Vec<&'static str> {
+    let modules = root.tree();
+    let consumed: HashSet<NodeKey> = modules
+        .iter()
+        .flat_map(|module| {
+            let edges = module.graph.edges().iter().map(|(_, to)| *to);
+            edges.chain(module.entry_points.iter().copied())
+        })
+        .collect();
+    let mut unused = Vec::new();
+    for module in &modules {
+        let exported: HashSet<NodeKey> = module.graphed_exports.keys().collect();
+        for key in module.graph.node_keys() {
+            if !consumed.contains(key) && !exported.contains(key) {
+                unused.push(module.graph.label(key));
+            }
+        }
+    }
+    unused
+}
+
+/// The type names around a loop in `waits`, which pairs each stalled provider with the
+/// dependency it's missing, if one of those dependencies is itself stalled on the first.
+fn dependency_cycle(waits: &[(TypeId, TypeInfo)]) -> Option<Vec<&'static str>> {
+    let missing = |id: TypeId| waits.iter().find(|(key, _)| *key == id).map(|(_, m)| *m);
+    for (start, _) in waits {
+        let mut path = vec![*start];
+        let mut names = Vec::new();
+        while let Some(next) = missing(*path.last().unwrap()) {
+            names.push(next.name);
+            if let Some(i) = path.iter().position(|id| *id == next.id) {
+                // `names[j]` is the name of `path[j + 1]`, so the loop runs from `path[i]`, named
+                // by `next`, through `names[i..]` back to itself.
+                let mut cycle = vec![next.name];
+                cycle.extend(&names[i..]);
+                return Some(cycle);
+            }
+            path.push(next.id);
+        }
+    }
+    None
+}
+
+fn hook_step<T: Send + Sync + 'static>(hook: fn(Arc<T>) -> Hook) -> HookStep {
+    Box::new(move |module| {
+        let key = Graph::key::<Arc<T>>(None);
+        match (
+            module.graph.position(&key),
+            module.graph.get_node::<Arc<T>>(),
+        ) {
+            (Some(position), Some(provider)) => {
+                Ok((position, TypeInfo::of::<T>(), hook(provider.clone())))
+            }
+            _ => Err(BuildError::UnresolvedProvider {
+                provider: TypeInfo::of::<T>(),
+                module: module.name,
+            }),
+        }
+    })
+}
+
+/// Runs `steps` against `module`, ordering the hooks by when their provider was resolved.
+fn collect_hooks(
+    steps: Vec<HookStep>,
+    module: &ResolvedModule,
+) -> Result<Vec<(TypeInfo, Hook)>, BuildError> {
+    let mut hooks = Vec::new();
+    for step in steps {
+        hooks.push(step(module)?);
+    }
+    hooks.sort_by_key(|(position, _, _)| *position);
+    Ok(hooks
+        .into_iter()
+        .map(|(_, provider, hook)| (provider, hook))
+        .collect())
+}
+
+pub trait ModuleFactory: Sized {
+    fn get_module() -> Module;
+}
+
+#[derive(Clone)]
+pub struct ResolvedModule {
+    pub(crate) name: &'static str,
+    /// The type that declared the module, `None` for a module built from `Module::new()`.
+    pub(crate) id: Option<TypeId>,
+    /// The version declared with [`Module::version`].
+    pub(crate) version: Option<Version>,
+    pub(crate) prefix: &'static str,
+    pub(crate) graph: Graph,
+    pub(crate) imports: Vec<Arc<Self>>,
+    pub(crate) graphed_exports: Graph,
+    #[cfg(feature = "actix")]
+    pub(crate) clients: Vec<Arc<dyn ServiceFactory>>,
+    #[cfg(feature = "axum")]
+    pub(crate) axum_clients: Vec<Arc<dyn AxumServiceFactory>>,
+    #[cfg(feature = "actix")]
+    pub(crate) middleware: Vec<Middleware>,
+    #[cfg(feature = "actix")]
+    pub(crate) interceptors: Interceptors,
+    #[cfg(feature = "actix")]
+    pub(crate) filters: ExceptionFilters,
+    #[cfg(feature = "actix")]
+    pub(crate) health_checks: Vec<RegisteredCheck>,
+    #[cfg(feature = "actix")]
+    pub(crate) scoped: Vec<ScopedProvider>,
+    /// Modules imported with [`Module::import_lazy`], whose routes are registered as catch-alls.
+    #[cfg(feature = "actix")]
+    pub(crate) lazy_modules: Vec<Arc<LazyTree>>,
+    /// Providers the framework consumes rather than other providers, kept out of
+    /// [`BuildReport::unused_providers`].
+    pub(crate) entry_points: Vec<NodeKey>,
+}
+
+impl ResolvedModule {
+    pub(crate) fn new() -> Self {
+        Self {
+            name: "<root>",
+            id: None,
+            version: None,
+            prefix: "",
+            graph: Graph::new(),
+            imports: Vec::new(),
+            graphed_exports: Graph::new(),
+            #[cfg(feature = "actix")]
+            clients: Vec::new(),
+            #[cfg(feature = "axum")]
+            axum_clients: Vec::new(),
+            #[cfg(feature = "actix")]
+            middleware: Vec::new(),
+            #[cfg(feature = "actix")]
+            interceptors: Interceptors::default(),
+            #[cfg(feature = "actix")]
+            filters: ExceptionFilters::default(),
+            #[cfg(feature = "actix")]
+            health_checks: Vec::new(),
+            #[cfg(feature = "actix")]
+            scoped: Vec::new(),
+            #[cfg(feature = "actix")]
+            lazy_modules: Vec::new(),
+            entry_points: Vec::new(),
+        }
+    }
+
+    /// This module followed by every module it transitively imports, each listed once however
+    /// many modules import it.
+    pub(crate) fn tree(&self) -> Vec<&Self> {
+        let mut modules = vec![self];
+        let mut i = 0;
+        while i < modules.len() {
+            for import in &modules[i].imports {
+                if !modules.iter().any(|m| std::ptr::eq(*m, &**import)) {
+                    modules.push(import);
+                }
+            }
+            i += 1;
+        }
+        modules
+    }
+
+    /// Which module this is.
+    pub fn info(&self) -> ModuleInfo {
+        ModuleInfo {
+            id: self.id,
+            name: self.name,
+        }
+    }
+
+    /// The types declaring the modules this module imports directly, e.g. to assert in a test
+    /// that `PaymentsModule` doesn't import `AdminModule`. Modules built from a bare
+    /// `Module::new()` have no declaring type and are left out.
+    pub fn imported_type_ids(&self) -> HashSet<TypeId> {
+        self.imports.iter().filter_map(|import| import.id).collect()
+    }
+
+    /// The type names of the modules this module imports directly, in import order.
+    pub fn imported_type_names(&self) -> Vec<&'static str> {
+        self.imports.iter().map(|import| import.name).collect()
+    }
+
+    /// This module and everything it transitively imports, as a tree of module names.
+    pub fn import_tree(&self) -> ImportTree {
+        ImportTree::of(self, &mut Vec::new())
+    }
+
+    /// The length of the longest chain of imports below this module.
+    fn depth(&self) -> usize {
+        self.imports
+            .iter()
+            .map(|import| import.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The clients of this module and of every module it transitively imports. A module imported
+    /// in several places contributes its clients once, so its routes register once.
+    #[cfg(feature = "actix")]
+    pub fn all_clients(&self) -> Vec<Arc<dyn ServiceFactory>> {
+        self.tree()
+            .into_iter()
+            .flat_map(|module| module.clients.iter().cloned())
+            .collect()
+    }
+
+    /// The clients of [`ResolvedModule::all_clients`], grouped by the path prefix they mount
+    /// under. Longer prefixes come first, since a scope registered earlier would answer the paths
+    /// of a scope nested under it.
+    #[cfg(feature = "actix")]
+    pub(crate) fn mounted_clients(&self) -> Vec<(String, Vec<Arc<dyn ServiceFactory>>)> {
+        self.mounted(|module| &module.clients)
+    }
+
+    /// The axum clients of this module and of every module it transitively imports, grouped by
+    /// the path prefix they mount under like [`ResolvedModule::mounted_clients`].
+    #[cfg(feature = "axum")]
+    pub(crate) fn mounted_axum_clients(&self) -> Vec<(String, Vec<Arc<dyn AxumServiceFactory>>)> {
+        self.mounted(|module| &module.axum_clients)
+    }
+
+    /// The modules imported with [`Module::import_lazy`] anywhere in the tree, each with the
+    /// prefix of the module that imports it and the full prefix of its catch-all, longest first.
+    #[cfg(feature = "actix")]
+    pub(crate) fn mounted_lazy(&self) -> Vec<(String, String, Arc<LazyTree>)> {
+        let mut mounted = Vec::new();
+        for (module, prefix) in self.prefixed_tree() {
+            for tree in &module.lazy_modules {
+                mounted.push((prefix.clone(), prefix.clone() + tree.prefix, tree.clone()));
+            }
+        }
+        mounted.sort_by_key(|(_, prefix, _)| std::cmp::Reverse(prefix.len()));
+        mounted
+    }
+
+    /// The modules of [`ResolvedModule::tree`], breadth-first, each with a prefix its clients
+    /// mount under: the concatenated prefixes along an import path. A module reached along paths
+    /// with different prefixes is listed once per prefix.
+    #[cfg(any(feature = "actix", feature = "axum"))]
+    fn prefixed_tree(&self) -> Vec<(&Self, String)> {
+        let mut modules = vec![(self, self.prefix.to_owned())];
+        let mut i = 0;
+        while i < modules.len() {
+            let (module, prefix) = (modules[i].0, modules[i].1.clone());
+            for import in &module.imports {
+                let prefix = prefix.clone() + import.prefix;
+                if !modules
+                    .iter()
+                    .any(|(m, p)| std::ptr::eq(*m, &**import) && *p == prefix)
+                {
+                    modules.push((import, prefix));
+                }
+            }
+            i += 1;
+        }
+        modules
+    }
+
+    #[cfg(any(feature = "actix", feature = "axum"))]
+    fn mounted<C: Clone>(&self, clients: impl Fn(&Self) -> &[C]) -> Vec<(String, Vec<C>)> {
+        let mut mounted: Vec<(String, Vec<C>)> = Vec::new();
+        for (module, prefix) in self.prefixed_tree() {
+            let clients = clients(module).iter().cloned();
+            match mounted.iter_mut().find(|(p, _)| *p == prefix) {
+                Some((_, mounted)) => mounted.extend(clients),
+                None => mounted.push((prefix, clients.collect())),
+            }
+        }
+        mounted.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        mounted
+    }
+
+    /// The middleware of this module and of every module it transitively imports, outermost first.
+    #[cfg(feature = "actix")]
+    pub(crate) fn all_middleware(&self) -> Vec<Middleware> {
+        self.tree()
+            .into_iter()
+            .flat_map(|module| module.middleware.iter().cloned())
+            .collect()
+    }
+
+    /// The scope this module provides `T` in, if it provides `T` itself.
+    pub fn scope_of<T: 'static>(&self) -> Option<Scope> {
+        #[cfg(feature = "actix")]
+        if self.scoped.iter().any(|p| p.info().id == TypeId::of::<T>()) {
+            return Some(Scope::Request);
+        }
+        self.graph.scope_of(&Graph::key::<Arc<T>>(None))
+    }
+
+    /// Whether this module exports `Arc<T>`.
+    pub fn exports<T: ?Sized + Send + Sync + 'static>(&self) -> bool {
+        self.exports_type_id(TypeId::of::<Arc<T>>())
+    }
+
+    /// Whether this module exports an unnamed node of type `id`. Nodes are keyed by the type
+    /// they're injected as, so for a provider of `T` pass `TypeId::of::<Arc<T>>()`.
+    pub fn exports_type_id(&self, id: TypeId) -> bool {
+        self.graphed_exports.keys().any(|key| key == (id, None))
+    }
+
+    /// Type names of the module's own providers, in the order they were resolved. Providers
+    /// registered with `provide::<T>` are listed as `Arc<T>`, the type they're injected as.
+    pub fn list_providers(&self) -> Vec<&'static str> {
+        self.graph.provided_types()
+    }
+
+    /// Type names of the providers the module exports, in the order they were resolved.
+    pub fn list_exports(&self) -> Vec<&'static str> {
+        self.graphed_exports.provided_types()
+    }
+
+    /// Everything visible from this module: its own graph, then its imports' exports, then globals.
+    pub(crate) fn view(&self, ctx: &ApplicationContext) -> Graph {
+        self.graph.layered(&visible(&self.imports, ctx))
+    }
+}
+
+/// The graphs a module resolves dependencies from besides its own: its imports' exports in
+/// import order, then global providers, so an explicit import overrides a global export.
+pub(crate) fn visible<'a>(
+    imports: &'a [Arc<ResolvedModule>],
+    ctx: &'a ApplicationContext,
+) -> Vec<&'a Graph> {
+    let mut graphs: Vec<&Graph> = imports.iter().map(|m| &m.graphed_exports).collect();
+    graphs.push(&ctx.global_providers);
+    graphs
+}
+
+/// Resolves a provider's node `N` into the module's own graph. A module's own provider takes
+/// precedence over a `N` exported by one of its imports or by a global module: it's constructed
+/// anyway, and the module's other providers and clients inject the local instance. Shadowing a
+/// global export is easy to do by accident, so once [`ApplicationContext::warn_on_shadowing`] is
+/// called it's logged as a warning.
+fn resolve_own<N: Injected + Send + Sync + 'static>(
+    module: &mut ResolvedModule,
+    ctx: &ApplicationContext,
+    name: Option<&'static str>,
+) -> Result<(), BuildError> {
+    if ctx.warn_on_shadowing
+        && ctx.global_providers.contains_named::<N>(name)
+        && !module.graph.contains_named::<N>(name)
+    {
+        let name = std::any::type_name::<N>();
+        crate::trace::warn(
+            name,
+            format_args!("provided locally, shadowing a global export"),
+        );
+    }
+    let graphs = visible(&module.imports, ctx);
+    module.graph.resolve_own::<N>(name, &graphs)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::sept_module::Module;
+    #[cfg(feature = "actix")]
+    use crate::sept_module::{ServiceConfig, ServiceFactory};
+    use crate::Injectable;
+
+    fn get_empty_ctx() -> ApplicationContext {
+        ApplicationContext::new()
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_client_is_reachable() {
+        #[derive(Clone, Injectable)]
+        struct TestInjectable;
+
+        impl ServiceFactory for TestInjectable {
+            fn register(&self, _: &mut ServiceConfig) {}
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new().client::<TestInjectable>().build(&mut ctx);
+        assert_eq!(resolved.clients.len(), 1);
+    }
+
+    #[test]
+    fn test_imported_provider_is_reachable() {
+        #[derive(Clone, Injectable)]
+        struct TestInjectable;
+
+        struct ExportingModule;
+        impl ModuleFactory for ExportingModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .export::<TestInjectable>()
+                    .provide::<TestInjectable>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new().import::<ExportingModule>().build(&mut ctx);
+        assert_eq!(resolved.imports.len(), 1);
+
+        assert!(resolved.imports[0]
+            .graph
+            .get_node::<Arc<TestInjectable>>()
+            .is_some());
+    }
+
+    #[test]
+    fn test_unresolved_provider_reports_import_chain() {
+        #[derive(Clone, Injectable)]
+        struct Missing;
+
+        #[derive(Clone, Injectable)]
+        struct NeedsMissing {
+            _missing: Arc<Missing>,
+        }
+
+        struct InnerModule;
+        impl ModuleFactory for InnerModule {
+            fn get_module() -> Module {
+                Module::new().provide::<NeedsMissing>()
+            }
+        }
+
+        struct OuterModule;
+        impl ModuleFactory for OuterModule {
+            fn get_module() -> Module {
+                Module::new().import::<InnerModule>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::new()
+            .import::<OuterModule>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.import_chain(),
+            vec![
+                std::any::type_name::<OuterModule>(),
+                std::any::type_name::<InnerModule>()
+            ]
+        );
+        match err.root_cause() {
+            BuildError::MissingDependency { needed_by, missing } => {
+                assert_eq!(needed_by.id, TypeId::of::<NeedsMissing>());
+                assert_eq!(missing.id, TypeId::of::<Arc<Missing>>());
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_circular_import_is_reported() {
+        struct ModuleA;
+        impl ModuleFactory for ModuleA {
+            fn get_module() -> Module {
+                Module::new().import::<ModuleB>()
+            }
+        }
+
+        struct ModuleB;
+        impl ModuleFactory for ModuleB {
+            fn get_module() -> Module {
+                Module::new().import::<ModuleA>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::from_factory::<ModuleA>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        match err.root_cause() {
+            BuildError::CircularImport { cycle } => assert_eq!(
+                cycle,
+                &vec![
+                    std::any::type_name::<ModuleA>(),
+                    std::any::type_name::<ModuleB>(),
+                    std::any::type_name::<ModuleA>(),
+                ]
+            ),
+            other => panic!("unexpected error: {}", other),
+        }
+        assert!(ctx.resolving.is_empty());
+    }
+
+    #[test]
+    fn test_differently_configured_dynamic_modules_can_nest() {
+        #[derive(Hash)]
+        struct Region(&'static str);
+
+        struct RegionModule;
+        impl RegionModule {
+            fn for_region(region: &'static str, fallback: Option<&'static str>) -> Module {
+                let module =
+                    Module::dynamic::<Self, _>(&Region(region)).provide_val(Arc::new(region));
+                match fallback {
+                    Some(fallback) => module.import_module(Self::for_region(fallback, None)),
+                    None => module,
+                }
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import_module(RegionModule::for_region("eu-west", Some("us-east")))
+            .try_build(&mut ctx)
+            .unwrap();
+        assert_eq!(ctx.modules.len(), 2);
+        let fallback = &resolved.imports[0].imports[0];
+        assert_eq!(**fallback.graph.get_node::<Arc<&str>>().unwrap(), "us-east");
+        assert!(ctx.resolving.is_empty());
+    }
+
+    #[test]
+    fn test_factory_provider_receives_dependencies() {
+        #[derive(Clone, Injectable)]
+        struct DepA;
+
+        struct Pool {
+            size: usize,
+            _dep: Arc<DepA>,
+        }
+
+        struct DatabaseModule;
+        impl ModuleFactory for DatabaseModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide_val(8usize)
+                    .provide::<DepA>()
+                    .provide_factory(|graph| Pool {
+                        size: *graph.get_node::<usize>().unwrap(),
+                        _dep: graph.get_node::<Arc<DepA>>().unwrap().clone(),
+                    })
+                    .export::<Pool>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new().import::<DatabaseModule>().build(&mut ctx);
+        let pool = resolved.imports[0]
+            .graphed_exports
+            .get_node::<Arc<Pool>>()
+            .unwrap();
+        assert_eq!(pool.size, 8);
+    }
+
+    #[tokio::test]
+    async fn test_async_provider_is_injectable() {
+        struct Pool {
+            size: usize,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Repository {
+            pool: Arc<Pool>,
+        }
+
+        struct DatabaseModule;
+        impl ModuleFactory for DatabaseModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide_val(4usize)
+                    .provide_async(|graph| {
+                        let size = *graph.get_node::<usize>().unwrap();
+                        async move { Pool { size } }
+                    })
+                    .provide::<Repository>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = ctx.build_async::<DatabaseModule>().await.unwrap();
+        let repository = resolved.graph.get_node::<Arc<Repository>>().unwrap();
+        assert_eq!(repository.pool.size, 4);
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::from_factory::<DatabaseModule>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        assert!(matches!(err, BuildError::AsyncProvider { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_async_providers_await_their_declared_dependencies() {
+        struct Pool {
+            size: usize,
+        }
+
+        struct Cache {
+            pool_size: usize,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .provide_async_with_deps(&[Graph::dependency::<Pool>()], |graph| {
+                let pool_size = graph.get_node::<Arc<Pool>>().unwrap().size;
+                async move { Cache { pool_size } }
+            })
+            .provide_async(|_| async { Pool { size: 8 } })
+            .try_build_async(&mut ctx)
+            .await
+            .unwrap();
+        let cache = resolved.graph.get_node::<Arc<Cache>>().unwrap();
+        assert_eq!(cache.pool_size, 8);
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::new()
+            .provide_async_with_deps(&[Graph::dependency::<Pool>()], |_| async {
+                Cache { pool_size: 0 }
+            })
+            .try_build_async(&mut ctx)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, BuildError::MissingDependency { .. }));
+    }
+
+    #[test]
+    fn test_named_providers_coexist() {
+        #[derive(Clone, Injectable)]
+        struct RedisClient;
+
+        #[derive(Clone, Injectable)]
+        struct SessionStore {
+            #[named("cache")]
+            cache: Arc<RedisClient>,
+            #[named("sessions")]
+            sessions: Arc<RedisClient>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .provide_named::<RedisClient>("cache")
+            .provide_named::<RedisClient>("sessions")
+            .provide::<SessionStore>()
+            .build(&mut ctx);
+
+        let cache = resolved
+            .graph
+            .get_node_named::<Arc<RedisClient>>(Some("cache"))
+            .unwrap();
+        let sessions = resolved
+            .graph
+            .get_node_named::<Arc<RedisClient>>(Some("sessions"))
+            .unwrap();
+        assert!(!Arc::ptr_eq(cache, sessions));
+        assert!(!resolved.graph.contains::<Arc<RedisClient>>());
+
+        let store = resolved.graph.get_node::<Arc<SessionStore>>().unwrap();
+        assert!(Arc::ptr_eq(&store.cache, cache));
+        assert!(Arc::ptr_eq(&store.sessions, sessions));
+    }
+
+    #[test]
+    fn test_multi_providers_accumulate_across_modules() {
+        trait Plugin: Send + Sync {
+            fn name(&self) -> &'static str;
+        }
+
+        struct Plugins;
+        impl MultiToken for Plugins {
+            type Item = dyn Plugin;
+        }
+
+        #[derive(Clone, Injectable)]
+        struct AuthPlugin;
+        impl Plugin for AuthPlugin {
+            fn name(&self) -> &'static str {
+                "auth"
+            }
+        }
+        crate::upcast!(AuthPlugin => dyn Plugin);
+
+        #[derive(Clone, Injectable)]
+        struct MetricsPlugin;
+        impl Plugin for MetricsPlugin {
+            fn name(&self) -> &'static str {
+                "metrics"
+            }
+        }
+        crate::upcast!(MetricsPlugin => dyn Plugin);
+
+        #[derive(Clone, Injectable)]
+        struct PluginHost {
+            plugins: Multi<Plugins>,
+        }
+
+        struct AuthModule;
+        impl ModuleFactory for AuthModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide_multi::<Plugins, AuthPlugin>(0)
+                    .export_multi::<Plugins>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<AuthModule>()
+            .provide_multi::<Plugins, MetricsPlugin>(0)
+            .provide::<PluginHost>()
+            .build(&mut ctx);
+
+        let host = resolved.graph.get_node::<Arc<PluginHost>>().unwrap();
+        let names: Vec<_> = host.plugins.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["auth", "metrics"]);
+    }
+
+    #[test]
+    fn test_multi_providers_are_sorted_by_order() {
+        trait Plugin: Send + Sync {
+            fn name(&self) -> &'static str;
+        }
+
+        struct Plugins;
+        impl MultiToken for Plugins {
+            type Item = dyn Plugin;
+        }
+
+        macro_rules! plugin {
+            ($ty:ident, $name:literal) => {
+                #[derive(Injectable)]
+                struct $ty;
+                impl Plugin for $ty {
+                    fn name(&self) -> &'static str {
+                        $name
+                    }
+                }
+                crate::upcast!($ty => dyn Plugin);
+            };
+        }
+        plugin!(Cors, "cors");
+        plugin!(Auth, "auth");
+        plugin!(Metrics, "metrics");
+
+        struct MetricsModule;
+        impl ModuleFactory for MetricsModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide_multi::<Plugins, Metrics>(30)
+                    .export_multi::<Plugins>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<MetricsModule>()
+            .provide_multi::<Plugins, Auth>(20)
+            .provide_multi::<Plugins, Cors>(-10)
+            .build(&mut ctx);
+
+        let plugins = resolved.graph.get_node::<Multi<Plugins>>().unwrap();
+        let names: Vec<_> = plugins.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["cors", "auth", "metrics"]);
+    }
+
+    #[test]
+    fn test_multi_fields_merge_imports_without_a_local_contribution() {
+        trait Plugin: Send + Sync {
+            fn name(&self) -> &'static str;
+        }
+
+        struct Plugins;
+        impl MultiToken for Plugins {
+            type Item = dyn Plugin;
+        }
+
+        macro_rules! plugin_module {
+            ($module:ident, $ty:ident, $name:literal) => {
+                #[derive(Injectable)]
+                struct $ty;
+                impl Plugin for $ty {
+                    fn name(&self) -> &'static str {
+                        $name
+                    }
+                }
+                crate::upcast!($ty => dyn Plugin);
+
+                struct $module;
+                impl ModuleFactory for $module {
+                    fn get_module() -> Module {
+                        Module::new()
+                            .provide_multi::<Plugins, $ty>(0)
+                            .export_multi::<Plugins>()
+                    }
+                }
+            };
+        }
+        plugin_module!(AuthModule, Auth, "auth");
+        plugin_module!(MetricsModule, Metrics, "metrics");
+
+        #[derive(Injectable)]
+        struct PluginHost {
+            plugins: Multi<Plugins>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<AuthModule>()
+            .import::<MetricsModule>()
+            .provide::<PluginHost>()
+            .build(&mut ctx);
+
+        let host = resolved.graph.get_node::<Arc<PluginHost>>().unwrap();
+        let names: Vec<_> = host.plugins.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["auth", "metrics"]);
+    }
+
+    #[tokio::test]
+    async fn test_init_hooks_run_in_dependency_order() {
+        use crate::lifecycle::InitError;
+
+        type InitLog = std::sync::Mutex<Vec<&'static str>>;
+
+        #[derive(Clone, Injectable)]
+        struct ServiceB {
+            log: Arc<InitLog>,
+        }
+        impl OnModuleInit for ServiceB {
+            async fn on_init(&self) -> Result<(), InitError> {
+                self.log.lock().unwrap().push("b");
+                Ok(())
+            }
+        }
+
+        #[derive(Clone, Injectable)]
+        struct ServiceA {
+            log: Arc<InitLog>,
+            _b: Arc<ServiceB>,
+        }
+        impl OnModuleInit for ServiceA {
+            async fn on_init(&self) -> Result<(), InitError> {
+                self.log.lock().unwrap().push("a");
+                Ok(())
+            }
+        }
+
+        let log = Arc::new(InitLog::default());
+        let mut ctx = get_empty_ctx();
+        Module::new()
+            .provide_val(log.clone())
+            .provide::<ServiceB>()
+            .provide::<ServiceA>()
+            .on_init::<ServiceA>()
+            .on_init::<ServiceB>()
+            .build(&mut ctx);
+        assert!(log.lock().unwrap().is_empty());
+
+        ctx.init_all().await.unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_destroy_hooks_run_once_in_reverse_order() {
+        type DestroyLog = std::sync::Mutex<Vec<&'static str>>;
+
+        #[derive(Clone, Injectable)]
+        struct Connection {
+            log: Arc<DestroyLog>,
+        }
+        impl OnModuleDestroy for Connection {
+            async fn on_destroy(&self) {
+                self.log.lock().unwrap().push("connection");
+            }
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Buffer {
+            log: Arc<DestroyLog>,
+            _connection: Arc<Connection>,
+        }
+        impl OnModuleDestroy for Buffer {
+            async fn on_destroy(&self) {
+                self.log.lock().unwrap().push("buffer");
+            }
+        }
+
+        struct SharedModule;
+        impl ModuleFactory for SharedModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide::<Connection>()
+                    .provide::<Buffer>()
+                    .on_destroy::<Connection>()
+                    .on_destroy::<Buffer>()
+            }
+        }
+
+        struct LeftModule;
+        impl ModuleFactory for LeftModule {
+            fn get_module() -> Module {
+                Module::new().import::<SharedModule>()
+            }
+        }
+
+        struct RightModule;
+        impl ModuleFactory for RightModule {
+            fn get_module() -> Module {
+                Module::new().import::<SharedModule>()
+            }
+        }
+
+        let log = Arc::new(DestroyLog::default());
+        let mut ctx = get_empty_ctx();
+        ctx.global_providers.provide(Arc::new(log.clone()));
+        Module::new()
+            .import::<LeftModule>()
+            .import::<RightModule>()
+            .build(&mut ctx);
+
+        ctx.shutdown().await;
+        ctx.shutdown().await;
+        assert_eq!(*log.lock().unwrap(), vec!["buffer", "connection"]);
+    }
+
+    #[test]
+    fn test_global_exports_are_visible_without_import() {
+        #[derive(Clone, Injectable)]
+        struct Config;
+
+        #[derive(Clone, Injectable)]
+        struct Service {
+            config: Arc<Config>,
+        }
+
+        struct ConfigModule;
+        impl ModuleFactory for ConfigModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .global()
+                    .provide::<Config>()
+                    .export::<Config>()
+            }
+        }
+
+        struct LocalConfigModule;
+        impl ModuleFactory for LocalConfigModule {
+            fn get_module() -> Module {
+                Module::new().provide_factory(|_| Config).export::<Config>()
+            }
+        }
+
+        struct FeatureModule;
+        impl ModuleFactory for FeatureModule {
+            fn get_module() -> Module {
+                Module::new().provide::<Service>()
+            }
+        }
+
+        struct OverridingModule;
+        impl ModuleFactory for OverridingModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .import::<LocalConfigModule>()
+                    .provide::<Service>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<ConfigModule>()
+            .import::<FeatureModule>()
+            .import::<OverridingModule>()
+            .build(&mut ctx);
+
+        let global = resolved.imports[0].graph.get_node::<Arc<Config>>().unwrap();
+        let feature = resolved.imports[1]
+            .graph
+            .get_node::<Arc<Service>>()
+            .unwrap();
+        assert!(Arc::ptr_eq(&feature.config, global));
+
+        let overriding = resolved.imports[2]
+            .graph
+            .get_node::<Arc<Service>>()
+            .unwrap();
+        let local = resolved.imports[2].imports[0]
+            .graph
+            .get_node::<Arc<Config>>()
+            .unwrap();
+        assert!(Arc::ptr_eq(&overriding.config, local));
+    }
+
+    #[test]
+    fn test_restored_contexts_dont_share_later_builds() {
+        #[derive(Injectable)]
+        struct Config;
+
+        #[derive(Injectable)]
+        struct Service;
+
+        struct ConfigModule;
+        impl ModuleFactory for ConfigModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .global()
+                    .provide::<Config>()
+                    .export::<Config>()
+            }
+        }
+
+        struct ServiceModule;
+        impl ModuleFactory for ServiceModule {
+            fn get_module() -> Module {
+                Module::new().provide::<Service>().export::<Service>()
+            }
+        }
+
+        let mut base = get_empty_ctx();
+        let base_root = Module::new().import::<ConfigModule>().build(&mut base);
+        let snapshot = base.snapshot();
+
+        let mut first = ApplicationContext::from_snapshot(&snapshot);
+        assert!(first.global_providers.contains::<Arc<Config>>());
+        let root = Module::new()
+            .import::<ConfigModule>()
+            .import::<ServiceModule>()
+            .build(&mut first);
+        assert!(Arc::ptr_eq(&root.imports[0], &base_root.imports[0]));
+        first.global_providers.provide(Arc::new(Arc::new(Service)));
+        assert_eq!(first.modules.len(), 2);
+
+        let second = ApplicationContext::from_snapshot(&snapshot);
+        assert!(!second.global_providers.contains::<Arc<Service>>());
+        assert_eq!(second.modules.len(), 1);
+    }
+
+    #[test]
+    fn test_local_providers_shadow_global_exports() {
+        #[derive(Injectable)]
+        struct Config;
+
+        #[derive(Injectable)]
+        struct Service {
+            config: Arc<Config>,
+        }
+
+        struct GlobalConfigModule;
+        impl ModuleFactory for GlobalConfigModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .global()
+                    .provide::<Config>()
+                    .export::<Config>()
+            }
+        }
+
+        struct ShadowingModule;
+        impl ModuleFactory for ShadowingModule {
+            fn get_module() -> Module {
+                Module::new().provide::<Config>().provide::<Service>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<GlobalConfigModule>()
+            .import::<ShadowingModule>()
+            .build(&mut ctx);
+
+        let global = resolved.imports[0].graph.get_node::<Arc<Config>>().unwrap();
+        let shadowing = &resolved.imports[1].graph;
+        let local = shadowing.get_node::<Arc<Config>>().unwrap();
+        assert!(!Arc::ptr_eq(local, global));
+        let service = shadowing.get_node::<Arc<Service>>().unwrap();
+        assert!(Arc::ptr_eq(&service.config, local));
+    }
+
+    #[test]
+    fn test_dynamic_modules_are_keyed_by_config() {
+        #[derive(Clone, Hash)]
+        struct DbConfig {
+            url: &'static str,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Pool {
+            config: Arc<DbConfig>,
+        }
+
+        struct DatabaseModule;
+        impl DatabaseModule {
+            fn for_root(config: DbConfig) -> Module {
+                Module::dynamic::<Self, _>(&config)
+                    .provide_val(Arc::new(config))
+                    .provide::<Pool>()
+                    .export::<Pool>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import_module(DatabaseModule::for_root(DbConfig {
+                url: "postgres://a",
+            }))
+            .import_module(DatabaseModule::for_root(DbConfig {
+                url: "postgres://b",
+            }))
+            .import_module(DatabaseModule::for_root(DbConfig {
+                url: "postgres://a",
+            }))
+            .build(&mut ctx);
+
+        assert_eq!(ctx.modules.len(), 2);
+        let pool = |i: usize| {
+            resolved.imports[i]
+                .graph
+                .get_node::<Arc<Pool>>()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(pool(0).config.url, "postgres://a");
+        assert_eq!(pool(1).config.url, "postgres://b");
+        assert!(Arc::ptr_eq(&resolved.imports[0], &resolved.imports[2]));
+    }
+
+    #[test]
+    fn test_missing_dependency_names_consumer_and_dependency() {
+        struct Database;
+
+        #[derive(Clone, Injectable)]
+        struct Repository {
+            _db: Arc<Database>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::new()
+            .provide::<Repository>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        let message = err.to_string();
+        assert!(message.contains(std::any::type_name::<Repository>()));
+        assert!(message.contains(std::any::type_name::<Arc<Database>>()));
+    }
+
+    #[test]
+    fn test_providers_resolve_regardless_of_declaration_order() {
+        #[derive(Clone, Injectable)]
+        struct Config;
+
+        #[derive(Clone, Injectable)]
+        struct Repository {
+            _config: Arc<Config>,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Service {
+            _repository: Arc<Repository>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .provide::<Service>()
+            .provide::<Repository>()
+            .provide::<Config>()
+            .try_build(&mut ctx)
+            .unwrap();
+        assert!(resolved.graph.contains::<Arc<Service>>());
+        assert!(
+            resolved.graph.position(&Graph::key::<Arc<Config>>(None))
+                < resolved.graph.position(&Graph::key::<Arc<Service>>(None))
+        );
+    }
+
+    #[test]
+    fn test_to_dot_records_dependency_edges() {
+        #[derive(Clone, Injectable)]
+        struct Pool;
+
+        #[derive(Clone, Injectable)]
+        struct Repository {
+            _pool: Arc<Pool>,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Service {
+            _repository: Arc<Repository>,
+        }
+
+        struct DatabaseModule;
+        impl ModuleFactory for DatabaseModule {
+            fn get_module() -> Module {
+                Module::new().provide::<Pool>().export::<Pool>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let root = Module::new()
+            .import::<DatabaseModule>()
+            .provide::<Repository>()
+            .provide::<Service>()
+            .try_build(&mut ctx)
+            .unwrap();
+
+        let dot = root.graph.to_dot();
+        assert!(dot.starts_with("digraph sept {"));
+        assert!(dot.contains(&format!(
+            "n0 [label=\"{}\", shape=box]",
+            std::any::type_name::<Arc<Repository>>()
+        )));
+        assert!(dot.contains("n1 -> n0;"));
+        assert!(dot.contains(&format!(
+            "n2 [label=\"{}\", shape=plaintext]",
+            std::any::type_name::<Arc<Pool>>()
+        )));
+        assert!(dot.contains("n0 -> n2;"));
+
+        let dot = ctx.to_dot(&root);
+        assert!(dot.contains("subgraph cluster_0 {\n    label=\"<root>\";"));
+        assert!(dot.contains(&format!(
+            "subgraph cluster_1 {{\n    label=\"{}\";",
+            std::any::type_name::<DatabaseModule>()
+        )));
+        assert!(dot.contains("m0_0 -> m1_0;"));
+        assert!(dot.contains("m0_1 -> m0_0;"));
+    }
+
+    #[test]
+    fn test_list_providers_and_exports() {
+        #[derive(Clone, Injectable)]
+        struct Pool;
+
+        #[derive(Clone, Injectable)]
+        struct Repository {
+            _pool: Arc<Pool>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .provide_val(3u8)
+            .provide::<Repository>()
+            .provide::<Pool>()
+            .export::<Repository>()
+            .try_build(&mut ctx)
+            .unwrap();
+        assert_eq!(
+            resolved.list_providers(),
+            vec![
+                "u8",
+                std::any::type_name::<Arc<Pool>>(),
+                std::any::type_name::<Arc<Repository>>()
+            ]
+        );
+        assert_eq!(
+            resolved.list_exports(),
+            vec![std::any::type_name::<Arc<Repository>>()]
+        );
+    }
+
+    #[test]
+    fn test_transient_provider_is_constructed_per_consumer() {
+        #[derive(Clone, Injectable)]
+        struct Config;
+
+        #[derive(Clone, Injectable)]
+        struct RequestId {
+            config: Arc<Config>,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct First {
+            id: Arc<RequestId>,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Second {
+            id: Arc<RequestId>,
+        }
+
+        struct IdModule;
+        impl ModuleFactory for IdModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide::<Config>()
+                    .provide_transient::<RequestId>()
+                    .export::<RequestId>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<IdModule>()
+            .provide::<First>()
+            .provide::<Second>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let first = resolved.graph.get_node::<Arc<First>>().unwrap();
+        let second = resolved.graph.get_node::<Arc<Second>>().unwrap();
+        assert!(!Arc::ptr_eq(&first.id, &second.id));
+        assert!(Arc::ptr_eq(&first.id.config, &second.id.config));
+    }
+
+    #[test]
+    fn test_optional_dependency_resolves_with_or_without_provider() {
+        #[derive(Clone, Injectable)]
+        struct Metrics;
+
+        #[derive(Clone, Injectable)]
+        struct Service {
+            metrics: Option<Arc<Metrics>>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let without = Module::new()
+            .provide::<Service>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let service = without.graph.get_node::<Arc<Service>>().unwrap();
+        assert!(service.metrics.is_none());
+
+        let mut ctx = get_empty_ctx();
+        let with = Module::new()
+            .provide::<Metrics>()
+            .provide::<Service>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let service = with.graph.get_node::<Arc<Service>>().unwrap();
+        let metrics = with.graph.get_node::<Arc<Metrics>>().unwrap();
+        assert!(Arc::ptr_eq(service.metrics.as_ref().unwrap(), metrics));
+    }
+
+    #[test]
+    fn test_reexport_imported_provider() {
+        #[derive(Clone, Injectable)]
+        struct AuthService;
+
+        #[derive(Clone, Injectable)]
+        struct Handler {
+            auth: Arc<AuthService>,
+        }
+
+        struct AuthModule;
+        impl ModuleFactory for AuthModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide::<AuthService>()
+                    .export::<AuthService>()
+            }
+        }
+
+        struct FacadeModule;
+        impl ModuleFactory for FacadeModule {
+            fn get_module() -> Module {
+                Module::new().import::<AuthModule>().export::<AuthService>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<FacadeModule>()
+            .provide::<Handler>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let handler = resolved.graph.get_node::<Arc<Handler>>().unwrap();
+        let auth = ctx.modules[&(TypeId::of::<AuthModule>(), None)]
+            .graph
+            .get_node::<Arc<AuthService>>()
+            .unwrap();
+        assert!(Arc::ptr_eq(&handler.auth, auth));
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_all_clients_collects_imported_clients() {
+        #[derive(Clone, Injectable)]
+        struct RootController;
+        #[derive(Clone, Injectable)]
+        struct MiddleController;
+        #[derive(Clone, Injectable)]
+        struct LeafController;
+
+        impl ServiceFactory for RootController {
+            fn register(&self, _: &mut ServiceConfig) {}
+        }
+        impl ServiceFactory for MiddleController {
+            fn register(&self, _: &mut ServiceConfig) {}
+        }
+        impl ServiceFactory for LeafController {
+            fn register(&self, _: &mut ServiceConfig) {}
+        }
+
+        struct LeafModule;
+        impl ModuleFactory for LeafModule {
+            fn get_module() -> Module {
+                Module::new().client::<LeafController>()
+            }
+        }
+
+        struct MiddleModule;
+        impl ModuleFactory for MiddleModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .import::<LeafModule>()
+                    .client::<MiddleController>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<MiddleModule>()
+            .client::<RootController>()
+            .try_build(&mut ctx)
+            .unwrap();
+        assert_eq!(resolved.clients.len(), 1);
+        assert_eq!(resolved.all_clients().len(), 3);
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_diamond_import_registers_shared_clients_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static REGISTERED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Clone, Injectable)]
+        struct SharedController;
+
+        impl ServiceFactory for SharedController {
+            fn register(&self, _: &mut ServiceConfig) {
+                REGISTERED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        struct D;
+        impl ModuleFactory for D {
+            fn get_module() -> Module {
+                Module::new().client::<SharedController>()
+            }
+        }
+
+        struct B;
+        impl ModuleFactory for B {
+            fn get_module() -> Module {
+                Module::new().import::<D>()
+            }
+        }
+
+        struct C;
+        impl ModuleFactory for C {
+            fn get_module() -> Module {
+                Module::new().import::<D>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let a = Module::new()
+            .import::<B>()
+            .import::<C>()
+            .try_build(&mut ctx)
+            .unwrap();
+        assert!(Arc::ptr_eq(
+            &a.imports[0].imports[0],
+            &a.imports[1].imports[0]
+        ));
+        assert_eq!(a.all_clients().len(), 1);
+
+        actix_web::App::new()
+            .configure(|cfg| crate::sept_application::SeptApplication::configure(Arc::new(a), cfg));
+        assert_eq!(REGISTERED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_unmarked_fields_are_defaulted_alongside_injected_ones() {
+        #[derive(Clone, Injectable)]
+        struct Repository;
+
+        #[derive(Clone, Injectable)]
+        struct Service {
+            #[inject]
+            repository: Arc<Repository>,
+            requests: usize,
+            label: Option<String>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .provide::<Repository>()
+            .provide::<Service>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let service = resolved.graph.get_node::<Arc<Service>>().unwrap();
+        let repository = resolved.graph.get_node::<Arc<Repository>>().unwrap();
+        assert!(Arc::ptr_eq(&service.repository, repository));
+        assert_eq!(service.requests, 0);
+        assert!(service.label.is_none());
+    }
+
+    #[cfg(feature = "actix")]
+    #[actix_rt::test]
+    async fn test_controller_registers_annotated_routes() {
+        use crate::{routes, Controller};
+        use actix_web::{test, web, App};
+
+        #[derive(Clone, Injectable)]
+        struct UserService;
+
+        impl UserService {
+            fn name(&self, id: u32) -> String {
+                format!("user {}", id)
+            }
+        }
+
+        #[derive(Clone, Injectable, Controller)]
+        #[controller("/api")]
+        struct UserController {
+            users: Arc<UserService>,
+        }
+
+        #[routes]
+        impl UserController {
+            #[get("/users/{id}")]
+            async fn get_user(&self, id: web::Path<u32>) -> String {
+                self.users.name(id.into_inner())
+            }
+        }
+
+        #[derive(Clone, Injectable, Controller)]
+        struct HealthController;
+
+        #[routes]
+        impl HealthController {
+            #[get("/health")]
+            fn health(&self) -> &'static str {
+                "ok"
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let module = Arc::new(
+            Module::new()
+                .provide::<UserService>()
+                .client::<UserController>()
+                .client::<HealthController>()
+                .build(&mut ctx),
+        );
+        let app = test::init_service(App::new().configure(|cfg| {
+            crate::sept_application::SeptApplication::configure(module.clone(), cfg)
+        }))
+        .await;
+
+        let req = test::TestRequest::get().uri("/api/users/7").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "user 7");
+        let req = test::TestRequest::get().uri("/health").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "ok");
+        let req = test::TestRequest::post().uri("/health").to_request();
+        assert!(test::call_service(&app, req)
+            .await
+            .status()
+            .is_client_error());
+    }
+
+    #[test]
+    fn test_module_attribute_generates_factory() {
+        use crate::module;
+
+        #[derive(Clone, Injectable)]
+        struct Pool;
+
+        #[derive(Clone, Injectable)]
+        struct UserService {
+            _pool: Arc<Pool>,
+        }
+
+        #[module(providers = [Pool], exports = [Pool])]
+        struct DatabaseModule;
+
+        #[module(imports = [DatabaseModule], providers = [UserService],
+                 exports = [UserService, DatabaseModule])]
+        struct UserModule;
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::from_factory::<UserModule>()
+            .try_build(&mut ctx)
+            .unwrap();
+        assert!(resolved.graphed_exports.contains::<Arc<UserService>>());
+        assert!(resolved.graphed_exports.contains::<Arc<Pool>>());
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_module_attribute_exports_controllers() {
+        use crate::module;
+
+        #[derive(Clone, Injectable)]
+        struct StatusController;
+
+        impl ServiceFactory for StatusController {
+            fn register(&self, _: &mut ServiceConfig) {}
+        }
+
+        #[module(controllers = [StatusController], exports = [StatusController])]
+        struct StatusModule;
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::from_factory::<StatusModule>()
+            .try_build(&mut ctx)
+            .unwrap();
+        assert_eq!(resolved.clients.len(), 1);
+        assert!(resolved.graphed_exports.contains::<Arc<StatusController>>());
+    }
+
+    #[test]
+    fn test_module_attribute_declares_global_modules() {
+        use crate::module;
+
+        #[derive(Injectable)]
+        struct Settings;
+
+        #[derive(Injectable)]
+        struct AuditLog;
+
+        #[derive(Injectable)]
+        struct Mailer {
+            settings: Arc<Settings>,
+            _audit: Arc<AuditLog>,
+        }
+
+        #[module(global, providers = [Settings], exports = [Settings])]
+        struct SettingsModule;
+
+        #[module(imports = [SettingsModule])]
+        struct UsersModule;
+
+        #[module(providers = [Mailer])]
+        struct MailModule;
+
+        #[module]
+        #[global]
+        #[providers(AuditLog)]
+        #[exports(AuditLog)]
+        struct AuditModule;
+
+        #[module(imports = [UsersModule, SettingsModule, AuditModule, MailModule])]
+        struct AppModule;
+
+        let mut ctx = get_empty_ctx();
+        let app = Module::from_factory::<AppModule>().build(&mut ctx);
+        let settings = app.imports[0].imports[0]
+            .graph
+            .get_node::<Arc<Settings>>()
+            .unwrap();
+        assert!(Arc::ptr_eq(&app.imports[0].imports[0], &app.imports[1]));
+        let mailer = app.imports[3].graph.get_node::<Arc<Mailer>>().unwrap();
+        assert!(Arc::ptr_eq(&mailer.settings, settings));
+        assert_eq!(ctx.global_providers.len(), 2);
+    }
+
+    #[test]
+    fn test_provide_if_toggles_optional_dependency() {
+        #[derive(Clone, Injectable)]
+        struct MetricsService;
+
+        #[derive(Clone, Injectable)]
+        struct Handler {
+            metrics: Option<Arc<MetricsService>>,
+            flag: Option<Arc<bool>>,
+        }
+
+        for enabled in [true, false] {
+            let mut ctx = get_empty_ctx();
+            let resolved = Module::new()
+                .provide_if::<MetricsService>(enabled)
+                .provide_val_if(Arc::new(enabled), enabled)
+                .provide::<Handler>()
+                .try_build(&mut ctx)
+                .unwrap();
+            let handler = resolved.graph.get_node::<Arc<Handler>>().unwrap();
+            assert_eq!(handler.metrics.is_some(), enabled);
+            assert_eq!(handler.flag.is_some(), enabled);
+            assert_eq!(resolved.graph.contains::<Arc<MetricsService>>(), enabled);
+        }
+    }
+
+    #[test]
+    fn test_exports_query() {
+        #[derive(Clone, Injectable)]
+        struct Public;
+
+        #[derive(Clone, Injectable)]
+        struct Private;
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .provide::<Public>()
+            .provide::<Private>()
+            .export::<Public>()
+            .try_build(&mut ctx)
+            .unwrap();
+        assert!(resolved.exports::<Public>());
+        assert!(!resolved.exports::<Private>());
+        assert!(resolved.exports_type_id(TypeId::of::<Arc<Public>>()));
+        assert!(!resolved.exports_type_id(TypeId::of::<Arc<Private>>()));
+    }
+
+    #[test]
+    fn test_duplicate_provider_is_rejected_unless_replaced() {
+        #[derive(Clone, Injectable)]
+        struct Cache;
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::new()
+            .provide::<Cache>()
+            .provide::<Cache>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            BuildError::DuplicateProvider { provider, .. } if provider.id == TypeId::of::<Cache>()
+        ));
+
+        let err = Module::new()
+            .provide::<Cache>()
+            .provide_val(Cache)
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        assert!(matches!(err, BuildError::DuplicateProvider { .. }));
+
+        let resolved = Module::new()
+            .provide_val(Cache)
+            .replace::<Cache>()
+            .try_build(&mut ctx)
+            .unwrap();
+        assert!(resolved.graph.contains::<Arc<Cache>>());
+        assert!(!resolved.graph.contains::<Cache>());
+    }
+
+    #[test]
+    fn test_provide_as_injects_trait_object() {
+        trait Repository: Send + Sync {
+            fn name(&self) -> &'static str;
+        }
+
+        #[derive(Clone, Injectable)]
+        struct PostgresRepo;
+        impl Repository for PostgresRepo {
+            fn name(&self) -> &'static str {
+                "postgres"
+            }
+        }
+        crate::upcast!(PostgresRepo => dyn Repository);
+
+        #[derive(Clone, Injectable)]
+        struct UserService {
+            repo: Arc<dyn Repository>,
+        }
+
+        struct RepoModule;
+        impl ModuleFactory for RepoModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide_as::<PostgresRepo, dyn Repository>()
+                    .export::<dyn Repository>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<RepoModule>()
+            .provide::<UserService>()
+            .build(&mut ctx);
+
+        let service = resolved.graph.get_node::<Arc<UserService>>().unwrap();
+        assert_eq!(service.repo.name(), "postgres");
+        assert!(resolved.imports[0].exports::<dyn Repository>());
+        assert!(!resolved.imports[0].exports::<PostgresRepo>());
+    }
+
+    #[test]
+    fn test_provide_as_multi_shares_one_instance() {
+        trait UserRepository: Send + Sync {}
+        trait AuditLog: Send + Sync {}
+
+        #[derive(Injectable)]
+        struct PostgresRepo;
+        impl UserRepository for PostgresRepo {}
+        impl AuditLog for PostgresRepo {}
+        crate::upcast!(PostgresRepo => dyn UserRepository, dyn AuditLog);
+
+        let resolved = crate::provide_as_multi!(
+            Module::new(),
+            PostgresRepo => [dyn UserRepository, dyn AuditLog],
+        )
+        .build(&mut get_empty_ctx());
+
+        let graph = &resolved.graph;
+        let users: *const () =
+            Arc::as_ptr(graph.get_node::<Arc<dyn UserRepository>>().unwrap()).cast();
+        let audit: *const () = Arc::as_ptr(graph.get_node::<Arc<dyn AuditLog>>().unwrap()).cast();
+        let concrete: *const () =
+            Arc::as_ptr(graph.get_node::<Arc<PostgresRepo>>().unwrap()).cast();
+        assert_eq!(users, audit);
+        assert_eq!(users, concrete);
+    }
+
+    #[test]
+    fn test_exported_transient_keeps_transitive_dependencies() {
+        #[derive(Clone, Injectable)]
+        struct Pool;
+
+        #[derive(Clone, Injectable)]
+        struct Connection {
+            pool: Arc<Pool>,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Transaction {
+            connection: Arc<Connection>,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Unrelated;
+
+        #[derive(Clone, Injectable)]
+        struct Handler {
+            tx: Arc<Transaction>,
+        }
+
+        struct DbModule;
+        impl ModuleFactory for DbModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide::<Pool>()
+                    .provide::<Unrelated>()
+                    .provide_transient::<Connection>()
+                    .provide_transient::<Transaction>()
+                    .export::<Transaction>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let db = Module::from_factory::<DbModule>().build(&mut ctx);
+        let view = db.view(&ctx);
+        let closure = view.dependency_closure([Graph::key::<Arc<Transaction>>(None)]);
+        assert!(closure.contains(&Graph::key::<Arc<Connection>>(None)));
+        assert!(closure.contains(&Graph::key::<Arc<Pool>>(None)));
+        assert!(!closure.contains(&Graph::key::<Arc<Unrelated>>(None)));
+
+        let resolved = Module::new()
+            .import::<DbModule>()
+            .provide::<Handler>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let handler = resolved.graph.get_node::<Arc<Handler>>().unwrap();
+        let pool = resolved.imports[0].graph.get_node::<Arc<Pool>>().unwrap();
+        assert!(Arc::ptr_eq(&handler.tx.connection.pool, pool));
+        assert!(!resolved.imports[0].exports::<Connection>());
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_report_counts_modules_providers_and_clients() {
+        #[derive(Clone, Injectable)]
+        struct Config;
+
+        #[derive(Clone, Injectable)]
+        struct Repo;
+
+        #[derive(Clone, Injectable)]
+        struct Api;
+        impl ServiceFactory for Api {
+            fn register(&self, _: &mut ServiceConfig) {}
+        }
+
+        struct ConfigModule;
+        impl ModuleFactory for ConfigModule {
+            fn get_module() -> Module {
+                Module::new().provide::<Config>().export::<Config>()
+            }
+        }
+
+        struct RepoModule;
+        impl ModuleFactory for RepoModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .import::<ConfigModule>()
+                    .provide::<Repo>()
+                    .export::<Repo>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<RepoModule>()
+            .import::<ConfigModule>()
+            .client::<Api>()
+            .build(&mut ctx);
+
+        let report = ctx.report(&resolved);
+        assert_eq!(report.modules, 3);
+        assert_eq!(report.providers, 2);
+        assert_eq!(report.clients, 1);
+        assert_eq!(report.max_depth, 2);
+        assert_eq!(
+            report.per_module[std::any::type_name::<RepoModule>()],
+            ModuleReport {
+                providers: 1,
+                clients: 0,
+            }
+        );
+        assert_eq!(report.per_module["<root>"].clients, 1);
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_providers_can_depend_on_clients() {
+        #[derive(Clone, Injectable)]
+        struct UsersController;
+        impl ServiceFactory for UsersController {
+            fn register(&self, _: &mut ServiceConfig) {}
+        }
+
+        #[derive(Clone, Injectable)]
+        struct RouteAudit {
+            users: Arc<UsersController>,
+        }
+
+        struct UsersModule;
+        impl ModuleFactory for UsersModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide::<RouteAudit>()
+                    .client::<UsersController>()
+                    .export::<UsersController>()
+            }
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Admin {
+            users: Arc<UsersController>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<UsersModule>()
+            .provide::<Admin>()
+            .build(&mut ctx);
+
+        let users = &resolved.imports[0];
+        let audit = users.graph.get_node::<Arc<RouteAudit>>().unwrap();
+        let admin = resolved.graph.get_node::<Arc<Admin>>().unwrap();
+        assert!(Arc::ptr_eq(&audit.users, &admin.users));
+        assert_eq!(resolved.all_clients().len(), 1);
+    }
+
+    #[cfg(feature = "actix")]
+    #[actix_rt::test]
+    async fn test_mount_prefix_concatenates_through_imports() {
+        use crate::{routes, Controller};
+        use actix_web::{test, App};
+
+        #[derive(Clone, Injectable, Controller)]
+        struct UserController;
+
+        #[routes]
+        impl UserController {
+            #[get("/users")]
+            fn users(&self) -> &'static str {
+                "users"
+            }
+        }
+
+        #[derive(Clone, Injectable, Controller)]
+        struct StatsController;
+
+        #[routes]
+        impl StatsController {
+            #[get("/stats")]
+            fn stats(&self) -> &'static str {
+                "stats"
+            }
+        }
+
+        struct AdminModule;
+        impl ModuleFactory for AdminModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .mount_prefix("/admin")
+                    .client::<StatsController>()
+            }
+        }
+
+        struct V2Module;
+        impl ModuleFactory for V2Module {
+            fn get_module() -> Module {
+                Module::new()
+                    .mount_prefix("/v2")
+                    .import::<AdminModule>()
+                    .client::<UserController>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let module = Arc::new(Module::new().import::<V2Module>().build(&mut ctx));
+        let app = test::init_service(App::new().configure(|cfg| {
+            crate::sept_application::SeptApplication::configure(module.clone(), cfg)
+        }))
+        .await;
+
+        let req = test::TestRequest::get().uri("/v2/users").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "users");
+        let req = test::TestRequest::get().uri("/v2/admin/stats").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "stats");
+        let req = test::TestRequest::get().uri("/users").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+
+    #[cfg(feature = "actix")]
+    #[actix_rt::test]
+    async fn test_shared_modules_mount_under_every_import_prefix() {
+        use crate::{routes, Controller};
+        use actix_web::{test, App};
+
+        #[derive(Clone, Injectable, Controller)]
+        struct ItemController;
+
+        #[routes]
+        impl ItemController {
+            #[get("/items")]
+            fn items(&self) -> &'static str {
+                "items"
+            }
+        }
+
+        struct SharedModule;
+        impl ModuleFactory for SharedModule {
+            fn get_module() -> Module {
+                Module::new().client::<ItemController>()
+            }
+        }
+
+        struct V1Module;
+        impl ModuleFactory for V1Module {
+            fn get_module() -> Module {
+                Module::new().mount_prefix("/v1").import::<SharedModule>()
+            }
+        }
+
+        struct V2Module;
+        impl ModuleFactory for V2Module {
+            fn get_module() -> Module {
+                Module::new().mount_prefix("/v2").import::<SharedModule>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let module = Module::new()
+            .import::<V1Module>()
+            .import::<V2Module>()
+            .build(&mut ctx);
+        let module = Arc::new(module);
+        let app = test::init_service(App::new().configure(|cfg| {
+            crate::sept_application::SeptApplication::configure(module.clone(), cfg)
+        }))
+        .await;
+
+        for uri in ["/v1/items", "/v2/items"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            assert_eq!(test::call_and_read_body(&app, req).await, "items");
+        }
+    }
+
+    #[test]
+    fn test_provide_val_accepts_non_clone_values() {
+        struct Handle {
+            writes: std::sync::Mutex<Vec<&'static str>>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .provide_val(Handle {
+                writes: std::sync::Mutex::new(Vec::new()),
+            })
+            .try_build(&mut ctx)
+            .unwrap();
+        let handle = resolved.graph.get_node::<Handle>().unwrap();
+        handle.writes.lock().unwrap().push("written");
+        assert_eq!(*handle.writes.lock().unwrap(), ["written"]);
+
+        let handle = || Handle {
+            writes: std::sync::Mutex::new(Vec::new()),
+        };
+        let resolved = Module::new()
+            .provide_val_if(handle(), false)
+            .try_build(&mut get_empty_ctx())
+            .unwrap();
+        assert!(resolved.graph.get_node::<Handle>().is_none());
+        let resolved = Module::new()
+            .provide_val_if(handle(), true)
+            .try_build(&mut get_empty_ctx())
+            .unwrap();
+        assert!(resolved.graph.get_node::<Handle>().is_some());
+    }
+
+    #[test]
+    fn test_export_without_provider_fails_the_build() {
+        #[derive(Clone, Injectable)]
+        struct Mailer;
+
+        #[derive(Clone, Injectable)]
+        struct Mail;
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::new()
+            .provide::<Mail>()
+            .export::<Mail>()
+            .export::<Mailer>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            BuildError::ExportNotProvided { export, .. } if export.id == TypeId::of::<Mailer>()
+        ));
+    }
+
+    #[test]
+    fn test_generic_providers_are_distinct_per_type_argument() {
+        use std::marker::PhantomData;
+
+        #[derive(Injectable)]
+        struct Database;
+
+        struct User;
+        struct Order;
+
+        #[derive(Injectable)]
+        struct Repository<T> {
+            db: Arc<Database>,
+            _entity: PhantomData<T>,
+        }
+
+        #[derive(Injectable)]
+        struct Checkout {
+            users: Arc<Repository<User>>,
+            orders: Arc<Repository<Order>>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .provide::<Database>()
+            .provide::<Repository<User>>()
+            .provide::<Repository<Order>>()
+            .provide::<Checkout>()
+            .try_build(&mut ctx)
+            .unwrap();
+        let checkout = resolved.graph.get_node::<Arc<Checkout>>().unwrap();
+        let users = resolved.graph.get_node::<Arc<Repository<User>>>().unwrap();
+        let orders = resolved.graph.get_node::<Arc<Repository<Order>>>().unwrap();
+        assert!(Arc::ptr_eq(&checkout.users, users));
+        assert!(Arc::ptr_eq(&checkout.orders, orders));
+        assert!(Arc::ptr_eq(&users.db, &orders.db));
+    }
+
+    #[test]
+    fn test_mutual_dependencies_report_the_cycle() {
+        #[derive(Injectable)]
+        struct Orders {
+            _billing: Arc<Billing>,
+        }
+
+        #[derive(Injectable)]
+        struct Billing {
+            _orders: Arc<Orders>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::new()
+            .provide::<Orders>()
+            .provide::<Billing>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        let BuildError::CircularDependency { cycle } = err else {
+            panic!("expected a circular dependency, got {}", err);
+        };
+        let orders = std::any::type_name::<Arc<Orders>>();
+        let billing = std::any::type_name::<Arc<Billing>>();
+        assert_eq!(cycle, [orders, billing, orders]);
+    }
+
+    #[test]
+    fn test_mutual_transients_fail_instead_of_overflowing() {
+        #[derive(Injectable)]
+        struct Ping {
+            _pong: Arc<Pong>,
+        }
+
+        #[derive(Injectable)]
+        struct Pong {
+            _ping: Arc<Ping>,
+        }
+
+        #[derive(Injectable)]
+        struct Game {
+            _ping: Arc<Ping>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::new()
+            .provide_transient::<Ping>()
+            .provide_transient::<Pong>()
+            .provide::<Game>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        let BuildError::CircularDependency { cycle } = err else {
+            panic!("expected a circular dependency, got {}", err);
+        };
+        let ping = std::any::type_name::<Arc<Ping>>();
+        let pong = std::any::type_name::<Arc<Pong>>();
+        assert_eq!(cycle, [ping, pong, ping]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_init_hook_stops_startup_and_cleans_up() {
+        use crate::lifecycle::InitError;
+
+        type Log = std::sync::Mutex<Vec<&'static str>>;
+
+        #[derive(Injectable)]
+        struct Cache {
+            log: Arc<Log>,
+        }
+        impl OnModuleInit for Cache {
+            async fn on_init(&self) -> Result<(), InitError> {
+                self.log.lock().unwrap().push("init cache");
+                Ok(())
+            }
+        }
+        impl OnModuleDestroy for Cache {
+            async fn on_destroy(&self) {
+                self.log.lock().unwrap().push("destroy cache");
+            }
+        }
+
+        #[derive(Injectable)]
+        struct Database {
+            _cache: Arc<Cache>,
+        }
+        impl OnModuleInit for Database {
+            async fn on_init(&self) -> Result<(), InitError> {
+                Err("database unreachable".into())
+            }
+        }
+        impl OnModuleDestroy for Database {
+            async fn on_destroy(&self) {
+                unreachable!("an uninitialized provider isn't destroyed");
+            }
+        }
+
+        #[derive(Injectable)]
+        struct Users {
+            log: Arc<Log>,
+            _database: Arc<Database>,
+        }
+        impl OnModuleInit for Users {
+            async fn on_init(&self) -> Result<(), InitError> {
+                self.log.lock().unwrap().push("init users");
+                Ok(())
+            }
+        }
+
+        let log = Arc::new(Log::default());
+        let mut ctx = get_empty_ctx();
+        Module::new()
+            .provide_val(log.clone())
+            .provide::<Cache>()
+            .provide::<Database>()
+            .provide::<Users>()
+            .on_init::<Users>()
+            .on_init::<Database>()
+            .on_init::<Cache>()
+            .on_destroy::<Database>()
+            .on_destroy::<Cache>()
+            .build(&mut ctx);
+
+        let err = ctx.init_all().await.unwrap_err();
+        assert!(matches!(
+            &err,
+            BuildError::InitFailed { provider, .. } if provider.id == TypeId::of::<Database>()
+        ));
+        assert!(err.to_string().ends_with("database unreachable"));
+        assert_eq!(*log.lock().unwrap(), ["init cache", "destroy cache"]);
+    }
+
+    #[test]
+    fn test_builds_are_deterministic_run_to_run() {
+        #[derive(Clone, Injectable)]
+        struct A;
+        #[derive(Clone, Injectable)]
+        struct B;
+        #[derive(Clone, Injectable)]
+        struct C;
+        #[derive(Clone, Injectable)]
+        struct D;
+
+        let transients = || {
+            let mut ctx = ApplicationContext::new();
+            let resolved = Module::new()
+                .provide_transient::<A>()
+                .provide_transient::<B>()
+                .provide_transient::<C>()
+                .provide_transient::<D>()
+                .export::<A>()
+                .export::<B>()
+                .export::<C>()
+                .export::<D>()
+                .build(&mut ctx);
+            resolved.graphed_exports.keys().collect::<Vec<_>>()
+        };
+        let missing = || {
+            let mut ctx = ApplicationContext::new();
+            let result = Module::new()
+                .export::<A>()
+                .export::<B>()
+                .export::<C>()
+                .export::<D>()
+                .try_build(&mut ctx);
+            result.err().unwrap().to_string()
+        };
+
+        let (first_transients, first_missing) = (transients(), missing());
+        for _ in 0..16 {
+            assert_eq!(transients(), first_transients);
+            assert_eq!(missing(), first_missing);
+        }
+    }
+
+    #[test]
+    fn test_configured_instances_export_named_providers() {
+        #[derive(Clone, Hash)]
+        struct Upstream {
+            name: &'static str,
+            url: &'static str,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct HttpClient {
+            upstream: Arc<Upstream>,
+        }
+
+        struct HttpClientModule;
+        impl HttpClientModule {
+            fn for_upstream(upstream: Upstream) -> Module {
+                Module::dynamic::<Self, _>(&upstream)
+                    .provide_val(Arc::new(upstream.clone()))
+                    .provide::<HttpClient>()
+                    .export_as::<HttpClient>(upstream.name)
+            }
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Aggregator {
+            #[named("github")]
+            github: Arc<HttpClient>,
+            #[named("gitlab")]
+            gitlab: Arc<HttpClient>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import_module(HttpClientModule::for_upstream(Upstream {
+                name: "github",
+                url: "https://api.github.com",
+            }))
+            .import_module(HttpClientModule::for_upstream(Upstream {
+                name: "gitlab",
+                url: "https://gitlab.com/api",
+            }))
+            .provide::<Aggregator>()
+            .build(&mut ctx);
+
+        assert_eq!(ctx.modules.len(), 2);
+        let aggregator = resolved.graph.get_node::<Arc<Aggregator>>().unwrap();
+        assert_eq!(aggregator.github.upstream.url, "https://api.github.com");
+        assert_eq!(aggregator.gitlab.upstream.url, "https://gitlab.com/api");
+        assert!(!resolved.imports[0].exports::<HttpClient>());
+    }
+
+    #[test]
+    fn test_shared_dependency_is_constructed_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CONSTRUCTED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Clock;
+
+        impl Injected for Clock {
+            type Output = Self;
+
+            fn resolve(_: &mut Graph, _: &[&Graph]) -> Result<Self, BuildError> {
+                CONSTRUCTED.fetch_add(1, Ordering::SeqCst);
+                Ok(Self)
+            }
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Scheduler {
+            clock: Arc<Clock>,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Auditor {
+            clock: Arc<Clock>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        // The dependents come first, so both are retried once `Clock` resolves.
+        let resolved = Module::new()
+            .provide::<Scheduler>()
+            .provide::<Auditor>()
+            .provide::<Clock>()
+            .build(&mut ctx);
+
+        assert_eq!(CONSTRUCTED.load(Ordering::SeqCst), 1);
+        let scheduler = resolved.graph.get_node::<Arc<Scheduler>>().unwrap();
+        let auditor = resolved.graph.get_node::<Arc<Auditor>>().unwrap();
+        assert!(Arc::ptr_eq(&scheduler.clock, &auditor.clock));
+    }
+
+    #[test]
+    fn test_panicking_import_is_reported_as_failed_import() {
+        struct BrokenModule;
+        impl ModuleFactory for BrokenModule {
+            fn get_module() -> Module {
+                panic!("DATABASE_URL is not set")
+            }
+        }
+
+        struct OuterModule;
+        impl ModuleFactory for OuterModule {
+            fn get_module() -> Module {
+                Module::new().import::<BrokenModule>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let err = Module::new()
+            .import::<OuterModule>()
+            .try_build(&mut ctx)
+            .err()
+            .unwrap();
+        let broken = std::any::type_name::<BrokenModule>();
+        assert_eq!(
+            err.import_chain(),
+            vec![std::any::type_name::<OuterModule>(), broken]
+        );
+        match err.root_cause() {
+            BuildError::ModulePanicked { module, message } => {
+                assert_eq!(*module, broken);
+                assert_eq!(message, "DATABASE_URL is not set");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_optional_import_uses_built_module_or_skips() {
+        #[derive(Clone, Injectable)]
+        struct Metrics;
+
+        struct MetricsModule;
+        impl ModuleFactory for MetricsModule {
+            fn get_module() -> Module {
+                Module::new().provide::<Metrics>().export::<Metrics>()
+            }
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Checkout {
+            metrics: Option<Arc<Metrics>>,
+        }
+
+        struct CheckoutModule;
+        impl ModuleFactory for CheckoutModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .import_optional::<MetricsModule>()
+                    .provide::<Checkout>()
+                    .export::<Checkout>()
+            }
+        }
+
+        let checkout = |root: Module| {
+            let mut ctx = get_empty_ctx();
+            let resolved = root.import::<CheckoutModule>().build(&mut ctx);
+            let checkout = resolved.imports.last().unwrap();
+            checkout.graph.get_node::<Arc<Checkout>>().unwrap().clone()
+        };
+
+        assert!(checkout(Module::new().import::<MetricsModule>())
+            .metrics
+            .is_some());
+        assert!(checkout(Module::new()).metrics.is_none());
+    }
+
+    #[cfg(feature = "actix")]
+    #[actix_rt::test]
+    async fn test_route_handlers_use_injected_fields_and_extractors() {
+        use crate::{routes, Controller};
+        use actix_web::{test, web, App};
+
+        #[derive(serde::Deserialize)]
+        struct Paging {
+            limit: usize,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct NewComment {
+            body: String,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct CommentService;
+
+        impl CommentService {
+            fn render(&self, post: u32, limit: usize, body: &str) -> String {
+                format!("post {} (limit {}): {}", post, limit, body)
+            }
+        }
+
+        #[derive(Clone, Injectable, Controller)]
+        struct CommentController {
+            comments: Arc<CommentService>,
+        }
+
+        #[routes]
+        impl CommentController {
+            #[post("/posts/{id}/comments")]
+            async fn create(
+                &self,
+                id: web::Path<u32>,
+                paging: web::Query<Paging>,
+                comment: web::Json<NewComment>,
+            ) -> String {
+                self.comments
+                    .render(id.into_inner(), paging.limit, &comment.body)
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let module = Arc::new(
+            Module::new()
+                .provide::<CommentService>()
+                .client::<CommentController>()
+                .build(&mut ctx),
+        );
+        let app = test::init_service(App::new().configure(|cfg| {
+            crate::sept_application::SeptApplication::configure(module.clone(), cfg)
+        }))
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/posts/3/comments?limit=10")
+            .set_json(serde_json::json!({ "body": "nice" }))
+            .to_request();
+        assert_eq!(
+            test::call_and_read_body(&app, req).await,
+            "post 3 (limit 10): nice"
+        );
+    }
+
+    #[test]
+    fn test_report_times_providers_when_requested() {
+        struct Slow;
+
+        #[derive(Clone, Injectable)]
+        struct Fast;
+
+        let module = || {
+            Module::new().provide::<Fast>().provide_factory(|_| {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                Slow
+            })
+        };
+
+        let mut ctx = get_empty_ctx();
+        let resolved = module().build(&mut ctx);
+        assert!(ctx.report(&resolved).provider_timings.is_empty());
+
+        let mut ctx = get_empty_ctx();
+        ctx.record_timings();
+        let resolved = module().build(&mut ctx);
+        let report = ctx.report(&resolved);
+        assert_eq!(report.provider_timings.len(), 2);
+        let (slowest, took) = report.slowest(1)[0];
+        assert_eq!(slowest, std::any::type_name::<Arc<Slow>>());
+        assert!(took >= std::time::Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_report_times_async_providers_and_multi_contributions() {
+        struct Pool;
+
+        struct Plugins;
+        impl MultiToken for Plugins {
+            type Item = Audit;
+        }
+
+        #[derive(Injectable)]
+        struct Audit;
+        crate::upcast!(Audit => Audit);
+
+        let mut ctx = get_empty_ctx();
+        ctx.record_timings();
+        let resolved = Module::new()
+            .provide_async(|_| async { Pool })
+            .provide_multi::<Plugins, Audit>(0)
+            .try_build_async(&mut ctx)
+            .await
+            .unwrap();
+        let timings = ctx.report(&resolved).provider_timings;
+        assert!(timings.contains_key(std::any::type_name::<Arc<Pool>>()));
+        assert!(timings.contains_key(std::any::type_name::<Multi<Plugins>>()));
+    }
+
+    #[test]
+    fn test_provider_can_inject_all_exports_of_an_import() {
+        #[derive(Clone, Injectable)]
+        struct Stripe;
+        #[derive(Clone, Injectable)]
+        struct Paypal;
+        #[derive(Clone, Injectable)]
+        struct Internal;
+
+        struct PaymentsModule;
+        impl ModuleFactory for PaymentsModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide::<Stripe>()
+                    .provide::<Paypal>()
+                    .provide::<Internal>()
+                    .export::<Stripe>()
+                    .export::<Paypal>()
+            }
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Gateways {
+            payments: Exports<PaymentsModule>,
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<PaymentsModule>()
+            .provide::<Gateways>()
+            .build(&mut ctx);
+
+        let gateways = resolved.graph.get_node::<Arc<Gateways>>().unwrap();
+        assert_eq!(
+            gateways.payments.names(),
+            vec![
+                std::any::type_name::<Arc<Stripe>>(),
+                std::any::type_name::<Arc<Paypal>>()
+            ]
+        );
+        assert!(gateways.payments.get::<Stripe>().is_some());
+        assert!(gateways.payments.get::<Internal>().is_none());
+        assert!(gateways
+            .payments
+            .iter()
+            .any(|(_, node)| node.downcast_ref::<Arc<Paypal>>().is_some()));
+        assert!(!resolved
+            .list_providers()
+            .iter()
+            .any(|name| name.contains("Exports")));
+    }
+
+    #[test]
+    fn test_tokens_distinguish_providers_of_one_type() {
+        struct RedisUrl(&'static str);
+
+        #[derive(Clone, Injectable)]
+        struct RedisClient {
+            url: Arc<RedisUrl>,
+        }
+
+        struct CacheRedis;
+        impl Token for CacheRedis {}
+        struct SessionRedis;
+        impl Token for SessionRedis {}
+
+        #[derive(Clone, Injectable)]
+        struct SessionStore {
+            cache: Tokened<CacheRedis, RedisClient>,
+            sessions: Tokened<SessionRedis, RedisClient>,
+        }
+
+        struct RedisModule;
+        impl ModuleFactory for RedisModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide_val(Arc::new(RedisUrl("redis://local")))
+                    .provide_with_token::<CacheRedis, RedisClient>()
+                    .provide_with_token::<SessionRedis, RedisClient>()
+                    .export_with_token::<CacheRedis, RedisClient>()
+                    .export_with_token::<SessionRedis, RedisClient>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new()
+            .import::<RedisModule>()
+            .provide::<SessionStore>()
+            .build(&mut ctx);
+
+        let store = resolved.graph.get_node::<Arc<SessionStore>>().unwrap();
+        assert_eq!(store.cache.url.0, "redis://local");
+        assert!(!Arc::ptr_eq(store.cache.get(), store.sessions.get()));
+        assert!(!resolved.imports[0].graph.contains::<Arc<RedisClient>>());
+    }
+
+    #[test]
+    fn test_report_lists_unused_providers() {
+        trait Store: Send + Sync {}
+
+        #[derive(Clone, Injectable)]
+        struct MemoryStore;
+        impl Store for MemoryStore {}
+        crate::upcast!(MemoryStore => dyn Store);
+
+        #[derive(Clone, Injectable)]
+        struct Orphan;
+
+        #[derive(Clone, Injectable)]
+        struct Clock;
+
+        #[derive(Clone, Injectable)]
+        struct Service {
+            _store: Arc<dyn Store>,
+            #[named("wall")]
+            _clock: Arc<Clock>,
+        }
+
+        struct FeatureModule;
+        impl ModuleFactory for FeatureModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .provide_as::<MemoryStore, dyn Store>()
+                    .provide::<Orphan>()
+                    .provide::<Clock>()
+                    .export_as::<Clock>("wall")
+                    .provide::<Service>()
+                    .export::<Service>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let resolved = Module::new().import::<FeatureModule>().build(&mut ctx);
+        assert_eq!(
+            ctx.report(&resolved).unused_providers,
+            vec![std::any::type_name::<Arc<Orphan>>()]
+        );
+    }
+
+    #[test]
+    fn test_report_counts_dependencies_of_deferred_providers() {
+        #[derive(Injectable)]
+        struct Pool;
+        #[derive(Injectable)]
+        struct Conn {
+            _pool: Arc<Pool>,
+        }
+        #[cfg(feature = "actix")]
+        #[derive(Injectable)]
+        struct Cache;
+        #[cfg(feature = "actix")]
+        #[derive(Injectable)]
+        struct Session {
+            _cache: Arc<Cache>,
+        }
+        #[derive(Injectable)]
+        struct Index;
+        #[derive(Injectable)]
+        struct Heavy {
+            _index: Arc<Index>,
+        }
+        #[derive(Injectable)]
+        struct Clock;
+        #[derive(Injectable)]
+        struct Widget {
+            _clock: Arc<Clock>,
+        }
+
+        let module = Module::new()
+            .provide::<Pool>()
+            .provide_transient::<Conn>()
+            .provide::<Index>()
+            .provide_lazy::<Heavy>()
+            .export::<Lazy<Heavy>>()
+            .provide::<Clock>()
+            .provide_on_demand::<Widget>()
+            .export::<Factory<Widget>>();
+        #[cfg(feature = "actix")]
+        let module = module.provide::<Cache>().provide_scoped::<Session>();
+        let mut ctx = get_empty_ctx();
+        let resolved = module.build(&mut ctx);
+        assert!(ctx.report(&resolved).unused_providers.is_empty());
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_provide_with_scope_records_each_scope() {
+        #[derive(Injectable)]
+        struct Config;
+        #[derive(Injectable)]
+        struct RequestId;
+        #[derive(Injectable)]
+        struct Scratch;
+
+        let mut ctx = get_empty_ctx();
+        let module = Module::new()
+            .provide_with_scope::<Config>(Scope::Singleton)
+            .provide_with_scope::<RequestId>(Scope::Request)
+            .provide_with_scope::<Scratch>(Scope::Transient)
+            .build(&mut ctx);
+
+        assert_eq!(module.scope_of::<Config>(), Some(Scope::Singleton));
+        assert_eq!(module.scope_of::<RequestId>(), Some(Scope::Request));
+        assert_eq!(module.scope_of::<Scratch>(), Some(Scope::Transient));
+        assert_eq!(module.scope_of::<String>(), None);
+    }
+
+    #[test]
+    fn test_export_all_exports_each_type() {
+        #[derive(Injectable)]
+        struct Users;
+        #[derive(Injectable)]
+        struct Orders;
+        #[derive(Injectable)]
+        struct Internal;
+
+        let mut ctx = get_empty_ctx();
+        let module = crate::export_all!(
+            Module::new()
+                .provide::<Users>()
+                .provide::<Orders>()
+                .provide::<Internal>(),
+            Users,
+            Orders,
+        )
+        .build(&mut ctx);
+
+        assert!(module.exports::<Users>());
+        assert!(module.exports::<Orders>());
+        assert!(!module.exports::<Internal>());
+    }
+
+    #[test]
+    fn test_alias_resolves_to_the_target_instance() {
+        trait LegacyMailer: Send + Sync {}
+
+        #[derive(Injectable)]
+        struct Mailer;
+        impl LegacyMailer for Mailer {}
+        crate::upcast!(Mailer => dyn LegacyMailer);
+
+        #[derive(Injectable)]
+        struct Signup {
+            mailer: Arc<dyn LegacyMailer>,
+        }
+
+        struct MailModule;
+        impl ModuleFactory for MailModule {
+            fn get_module() -> Module {
+                Module::new().provide::<Mailer>().export::<Mailer>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let module = Module::new()
+            .import::<MailModule>()
+            .provide::<Signup>()
+            .alias::<dyn LegacyMailer, Mailer>()
+            .build(&mut ctx);
+
+        let mailer = module.imports[0].graph.get_node::<Arc<Mailer>>().unwrap();
+        let alias = module.graph.get_node::<Arc<dyn LegacyMailer>>().unwrap();
+        let signup = module.graph.get_node::<Arc<Signup>>().unwrap();
+        assert!(std::ptr::addr_eq(Arc::as_ptr(mailer), Arc::as_ptr(alias)));
+        assert!(Arc::ptr_eq(alias, &signup.mailer));
+    }
+
+    #[test]
+    fn test_provide_one_of_picks_the_first_matching_candidate() {
+        trait Mailer: Send + Sync {
+            fn send(&self) -> &'static str;
+        }
+
+        #[derive(Injectable)]
+        struct SmtpMailer;
+        impl Mailer for SmtpMailer {
+            fn send(&self) -> &'static str {
+                "smtp"
+            }
+        }
+
+        #[derive(Injectable)]
+        struct MockMailer;
+        impl Mailer for MockMailer {
+            fn send(&self) -> &'static str {
+                "mock"
+            }
+        }
+        crate::upcast!(SmtpMailer => dyn Mailer);
+        crate::upcast!(MockMailer => dyn Mailer);
+
+        #[derive(Injectable)]
+        struct Notifier {
+            mailer: Arc<dyn Mailer>,
+        }
+
+        let sent = |mock: bool| {
+            let module = Module::new()
+                .provide_one_of::<dyn Mailer>(|mailers| {
+                    mailers.when::<MockMailer>(mock).otherwise::<SmtpMailer>()
+                })
+                .provide::<Notifier>()
+                .build(&mut get_empty_ctx());
+            module
+                .graph
+                .get_node::<Arc<Notifier>>()
+                .unwrap()
+                .mailer
+                .send()
+        };
+        assert_eq!(sent(true), "mock");
+        assert_eq!(sent(false), "smtp");
+    }
+
+    #[test]
+    fn test_versioned_imports_check_the_declared_version() {
+        struct BillingModule;
+        impl ModuleFactory for BillingModule {
+            fn get_module() -> Module {
+                Module::new().version("2.3.1")
+            }
+        }
+        struct LegacyModule;
+        impl ModuleFactory for LegacyModule {
+            fn get_module() -> Module {
+                Module::new()
+            }
+        }
+
+        let build = |module: Module| module.try_build(&mut get_empty_ctx());
+        assert!(build(Module::new().import_versioned::<BillingModule>("^2.1")).is_ok());
+        assert!(build(Module::new().import_versioned::<BillingModule>(">=2, <3")).is_ok());
+
+        let err = build(Module::new().import_versioned::<BillingModule>("^3"))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            &err,
+            BuildError::VersionMismatch { module, required, version, .. }
+                if module.ends_with("BillingModule")
+                    && required == "^3"
+                    && version.as_deref() == Some("2.3.1")
+        ));
+        assert!(err
+            .to_string()
+            .ends_with("^3, but it declares version 2.3.1"));
+
+        let err = build(Module::new().import_versioned::<LegacyModule>("^1"))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            BuildError::VersionMismatch { version: None, .. }
+        ));
+
+        let err = build(Module::new().import_versioned::<BillingModule>("^two"))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            &err,
+            BuildError::InvalidVersion { module, version, .. }
+                if *module == "<root>" && version == "^two"
+        ));
+
+        struct MalformedModule;
+        impl ModuleFactory for MalformedModule {
+            fn get_module() -> Module {
+                Module::new().version("2.x")
+            }
+        }
+        let err = build(Module::new().import::<MalformedModule>())
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.root_cause(),
+            BuildError::InvalidVersion { module, version, .. }
+                if module.ends_with("MalformedModule") && version == "2.x"
+        ));
+    }
+
+    #[test]
+    fn test_imported_type_ids_expose_forbidden_imports() {
+        struct AdminModule;
+        impl ModuleFactory for AdminModule {
+            fn get_module() -> Module {
+                Module::new()
+            }
+        }
+        struct LedgerModule;
+        impl ModuleFactory for LedgerModule {
+            fn get_module() -> Module {
+                Module::new()
+            }
+        }
+        struct PaymentsModule;
+        impl ModuleFactory for PaymentsModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .import::<LedgerModule>()
+                    .import::<AdminModule>()
+                    .import_module(Module::new())
+            }
+        }
+
+        let payments = Module::from_factory::<PaymentsModule>().build(&mut get_empty_ctx());
+        let imported = payments.imported_type_ids();
+        assert_eq!(imported.len(), 2);
+        assert!(imported.contains(&TypeId::of::<LedgerModule>()));
+        assert!(imported.contains(&TypeId::of::<AdminModule>()));
+        assert_eq!(
+            payments.imported_type_names()[..2],
+            [
+                std::any::type_name::<LedgerModule>(),
+                std::any::type_name::<AdminModule>(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_tree_marks_shared_modules() {
+        struct DbModule;
+        impl ModuleFactory for DbModule {
+            fn get_module() -> Module {
+                Module::new()
+            }
+        }
+        struct UsersModule;
+        impl ModuleFactory for UsersModule {
+            fn get_module() -> Module {
+                Module::new().import::<DbModule>()
+            }
+        }
+        struct OrdersModule;
+        impl ModuleFactory for OrdersModule {
+            fn get_module() -> Module {
+                Module::new().import::<DbModule>()
+            }
+        }
+
+        let mut ctx = get_empty_ctx();
+        let module = Module::new()
+            .import::<UsersModule>()
+            .import::<OrdersModule>()
+            .build(&mut ctx);
+
+        let leaf = |name, shared| ImportTree {
+            name,
+            shared,
+            imports: Vec::new(),
+        };
+        let db = std::any::type_name::<DbModule>();
+        let tree = module.import_tree();
+        assert_eq!(
+            tree,
+            ImportTree {
+                name: "<root>",
+                shared: false,
+                imports: vec![
+                    ImportTree {
+                        name: std::any::type_name::<UsersModule>(),
+                        shared: false,
+                        imports: vec![leaf(db, false)],
+                    },
+                    ImportTree {
+                        name: std::any::type_name::<OrdersModule>(),
+                        shared: false,
+                        imports: vec![leaf(db, true)],
+                    },
+                ],
+            }
+        );
+        assert!(tree
+            .to_string()
+            .ends_with(&format!("    {} (shared)\n", db)));
+    }
+
+    #[test]
+    fn test_declared_dependencies_order_factories() {
+        struct Config(&'static str);
+        struct Greeter(String);
+
+        let mut ctx = get_empty_ctx();
+        let module = Module::new()
+            .provide_factory_with_deps(&[Graph::dependency::<Config>()], |graph| {
+                let config = graph.get_node::<Arc<Config>>().unwrap();
+                Greeter(format!("hello from {}", config.0))
+            })
+            .provide_factory(|_| Config("prod"))
+            .build(&mut ctx);
+
+        let greeter = module.graph.get_node::<Arc<Greeter>>().unwrap();
+        assert_eq!(greeter.0, "hello from prod");
+        assert!(!ctx
+            .report(&module)
+            .unused_providers
+            .iter()
+            .any(|p| p.contains("Config")));
+
+        let err = Module::new()
+            .provide_factory_with_deps(&[Graph::dependency::<Config>()], |_| Greeter(String::new()))
+            .try_build(&mut get_empty_ctx())
+            .err()
+            .unwrap();
+        assert!(matches!(err, BuildError::MissingDependency { .. }));
+    }
+
+    #[test]
+    fn test_provide_or_falls_back_when_resolution_fails() {
+        struct Endpoint(&'static str);
+
+        #[derive(Injectable)]
+        struct Exporter {
+            endpoint: Arc<Endpoint>,
+        }
+
+        #[derive(Injectable)]
+        struct Metrics {
+            exporter: Arc<Exporter>,
+        }
+
+        let build = |module: Module| {
+            let module = module
+                .provide::<Metrics>()
+                .provide_or(Exporter {
+                    endpoint: Arc::new(Endpoint("noop")),
+                })
+                .build(&mut get_empty_ctx());
+            let metrics = module.graph.get_node::<Arc<Metrics>>().unwrap();
+            metrics.exporter.endpoint.0
+        };
+
+        assert_eq!(
+            build(Module::new().provide_val(Arc::new(Endpoint("otlp")))),
+            "otlp"
+        );
+        assert_eq!(build(Module::new()), "noop");
     }
 }
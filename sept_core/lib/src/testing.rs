@@ -0,0 +1,280 @@
+use crate::error::BuildError;
+use crate::graph::Graph;
+use crate::sept_module::{ApplicationContext, Module, ModuleFactory, ResolvedModule};
+use std::sync::Arc;
+
+/// Wraps a [`Module`] for tests, allowing providers to be swapped for mocks before it's built.
+pub struct ModuleBuilder {
+    module: Module,
+}
+
+impl ModuleBuilder {
+    pub fn new(module: Module) -> Self {
+        Self { module }
+    }
+
+    /// Starts from the module declared by `T`.
+    pub fn of<T: ModuleFactory + 'static>() -> Self {
+        Self::new(Module::from_factory::<T>())
+    }
+
+    /// Injects `value` wherever the module would resolve `Arc<T>`, in place of whatever its own
+    /// `provide::<T>` or its imports would supply. `T` may be a trait object, so a mock can stand
+    /// in for a `dyn Repository` dependency.
+    pub fn override_provider<T>(mut self, value: Arc<T>) -> Self
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.module = self.module.override_provider(value);
+        self
+    }
+
+    /// Like [`ModuleBuilder::override_provider`], but only for the module declared by `M`, wherever
+    /// it's imported in the tree: `M` and the modules importing its exports see `value`, while
+    /// sibling modules keep resolving the real `Arc<T>`. `M` is built once per context, so every
+    /// module importing it shares the override, and it has no effect on an `M` the context
+    /// already built.
+    pub fn override_provider_in<M, T>(mut self, value: Arc<T>) -> Self
+    where
+        M: ModuleFactory + 'static,
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.module = self.module.override_provider_in::<M, T>(value);
+        self
+    }
+
+    pub fn try_build(self, ctx: &mut ApplicationContext) -> Result<ResolvedModule, BuildError> {
+        self.module.try_build(ctx)
+    }
+
+    pub fn build(self, ctx: &mut ApplicationContext) -> ResolvedModule {
+        self.module.build(ctx)
+    }
+
+    pub fn into_module(self) -> Module {
+        self.module
+    }
+}
+
+/// Builds a single module against a fresh [`ApplicationContext`] and inspects the result, without
+/// starting an application.
+#[derive(Default)]
+pub struct TestHarness {
+    ctx: ApplicationContext,
+    module: Option<ResolvedModule>,
+}
+
+impl TestHarness {
+    pub fn new() -> Self {
+        Self {
+            ctx: ApplicationContext::new(),
+            module: None,
+        }
+    }
+
+    /// Registers `value` as a global `Arc<T>`, visible to every module the harness builds,
+    /// replacing a global `Arc<T>` registered earlier.
+    pub fn provide_global<T>(mut self, value: Arc<T>) -> Self
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.remove_global::<T>();
+        self.ctx.global_providers.provide(Arc::new(value));
+        self
+    }
+
+    /// Removes the global `Arc<T>`, returning whether there was one. Modules built afterwards no
+    /// longer see it; modules already built keep the instance they resolved.
+    pub fn remove_global<T>(&mut self) -> bool
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        self.ctx.global_owners.remove(&Graph::key::<Arc<T>>(None));
+        self.ctx.global_providers.remove::<Arc<T>>()
+    }
+
+    /// Builds the module declared by `T`, replacing any previously built module.
+    pub fn build<T: ModuleFactory + 'static>(&mut self) -> Result<&ResolvedModule, BuildError> {
+        self.build_module(Module::from_factory::<T>())
+    }
+
+    pub fn build_module(&mut self, module: Module) -> Result<&ResolvedModule, BuildError> {
+        let resolved = module.try_build(&mut self.ctx)?;
+        Ok(self.module.insert(resolved))
+    }
+
+    pub fn module(&self) -> Option<&ResolvedModule> {
+        self.module.as_ref()
+    }
+
+    pub fn context(&self) -> &ApplicationContext {
+        &self.ctx
+    }
+
+    /// Gets `Arc<T>` as the built module would inject it: from its own providers, then its
+    /// imports' exports, then globals.
+    pub fn get<T: ?Sized + Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let module = self.module.as_ref()?;
+        module.view(&self.ctx).get_node::<Arc<T>>().cloned()
+    }
+
+    /// Whether the built module exports `Arc<T>`.
+    pub fn exports<T: ?Sized + Send + Sync + 'static>(&self) -> bool {
+        self.module
+            .as_ref()
+            .is_some_and(ResolvedModule::exports::<T>)
+    }
+
+    pub fn assert_exports<T: ?Sized + Send + Sync + 'static>(&self) {
+        assert!(
+            self.exports::<T>(),
+            "module `{}` does not export `{}`",
+            self.module
+                .as_ref()
+                .map_or("<unbuilt>", |module| module.name),
+            std::any::type_name::<T>()
+        );
+    }
+
+    /// Number of clients registered by the built module itself.
+    #[cfg(feature = "actix")]
+    pub fn client_count(&self) -> usize {
+        self.module
+            .as_ref()
+            .map_or(0, |module| module.clients.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as sept;
+    use crate::Injectable;
+
+    trait Repository: Send + Sync {
+        fn find(&self) -> &'static str;
+    }
+
+    struct PostgresRepository;
+    impl Repository for PostgresRepository {
+        fn find(&self) -> &'static str {
+            "postgres"
+        }
+    }
+
+    struct MockRepository;
+    impl Repository for MockRepository {
+        fn find(&self) -> &'static str {
+            "mock"
+        }
+    }
+
+    #[derive(Clone, Injectable)]
+    struct UserService {
+        repository: Arc<dyn Repository>,
+    }
+
+    struct RepositoryModule;
+    impl ModuleFactory for RepositoryModule {
+        fn get_module() -> Module {
+            let repository: Arc<dyn Repository> = Arc::new(PostgresRepository);
+            Module::new()
+                .export_val(&repository)
+                .provide_val(repository)
+        }
+    }
+
+    struct UserModule;
+    impl ModuleFactory for UserModule {
+        fn get_module() -> Module {
+            Module::new()
+                .import::<RepositoryModule>()
+                .provide::<UserService>()
+        }
+    }
+
+    #[test]
+    fn test_override_replaces_imported_provider() {
+        let mut ctx = ApplicationContext::new();
+        let real = ModuleBuilder::of::<UserModule>().build(&mut ctx);
+        let service = real.graph.get_node::<Arc<UserService>>().unwrap();
+        assert_eq!(service.repository.find(), "postgres");
+
+        let mut ctx = ApplicationContext::new();
+        let mocked = ModuleBuilder::of::<UserModule>()
+            .override_provider::<dyn Repository>(Arc::new(MockRepository))
+            .build(&mut ctx);
+        let service = mocked.graph.get_node::<Arc<UserService>>().unwrap();
+        assert_eq!(service.repository.find(), "mock");
+    }
+
+    #[test]
+    fn test_nested_override_only_applies_to_its_module() {
+        struct AuditModule;
+        impl ModuleFactory for AuditModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .import::<RepositoryModule>()
+                    .provide::<UserService>()
+                    .export::<UserService>()
+            }
+        }
+
+        struct AppModule;
+        impl ModuleFactory for AppModule {
+            fn get_module() -> Module {
+                Module::new().import::<UserModule>().import::<AuditModule>()
+            }
+        }
+
+        let mut ctx = ApplicationContext::new();
+        let app = ModuleBuilder::of::<AppModule>()
+            .override_provider_in::<UserModule, dyn Repository>(Arc::new(MockRepository))
+            .build(&mut ctx);
+        let find = |i: usize| {
+            let service = app.imports[i].graph.get_node::<Arc<UserService>>().unwrap();
+            service.repository.find()
+        };
+        assert_eq!(find(0), "mock");
+        assert_eq!(find(1), "postgres");
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_harness_resolves_and_inspects_module() {
+        struct Settings {
+            region: &'static str,
+        }
+
+        #[derive(Clone, Injectable)]
+        struct Client {
+            _settings: Arc<Settings>,
+            _repository: Arc<dyn Repository>,
+        }
+
+        impl crate::sept_module::ServiceFactory for Client {
+            fn register(&self, _: &mut actix_web::web::ServiceConfig) {}
+        }
+
+        struct ClientModule;
+        impl ModuleFactory for ClientModule {
+            fn get_module() -> Module {
+                Module::new()
+                    .import::<RepositoryModule>()
+                    .client::<Client>()
+            }
+        }
+
+        let mut harness = TestHarness::new().provide_global(Arc::new(Settings { region: "eu" }));
+        assert!(harness.get::<Settings>().is_none());
+        harness.build::<ClientModule>().unwrap();
+
+        assert_eq!(harness.client_count(), 1);
+        assert!(!harness.exports::<dyn Repository>());
+        assert_eq!(harness.get::<dyn Repository>().unwrap().find(), "postgres");
+        assert_eq!(harness.get::<Settings>().unwrap().region, "eu");
+
+        harness.build::<RepositoryModule>().unwrap();
+        harness.assert_exports::<dyn Repository>();
+    }
+}
@@ -0,0 +1,294 @@
+//! Spans around module builds and provider construction, emitted with the `tracing` feature,
+//! and the warnings logged about providers.
+//!
+//! Spans nest: an imported module's span is a child of its importer's, a provider's span a child
+//! of its module's, and a dependency constructed while resolving a provider, such as a transient,
+//! a child of that provider's. Each span records its `elapsed_us` when it closes. Once a module
+//! is built, a debug event lists what it provides, exports and imports. Without the feature,
+//! every function here compiles to nothing.
+
+use crate::sept_module::ResolvedModule;
+use std::future::Future;
+
+#[cfg(feature = "tracing")]
+pub(crate) struct Span {
+    span: tracing::span::EnteredSpan,
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed().as_micros() as u64;
+        self.span.record("elapsed_us", elapsed);
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Span;
+
+/// Enters a span for constructing the provider `name`.
+#[cfg(feature = "tracing")]
+pub(crate) fn provider(name: &'static str) -> Span {
+    Span {
+        span: tracing::debug_span!(
+            "sept::provider",
+            provider = name,
+            elapsed_us = tracing::field::Empty
+        )
+        .entered(),
+        start: std::time::Instant::now(),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn provider(_name: &'static str) -> Span {
+    Span
+}
+
+/// Enters a span for building the module `name`.
+#[cfg(feature = "tracing")]
+pub(crate) fn module(name: &'static str) -> Span {
+    Span {
+        span: module_span(name).entered(),
+        start: std::time::Instant::now(),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn module(_name: &'static str) -> Span {
+    Span
+}
+
+#[cfg(feature = "tracing")]
+fn module_span(name: &'static str) -> tracing::Span {
+    tracing::debug_span!(
+        "sept::module",
+        module = name,
+        elapsed_us = tracing::field::Empty
+    )
+}
+
+/// Runs `build` in a span for building the module `name`, entered only while it's polled.
+#[cfg(feature = "tracing")]
+pub(crate) async fn module_async<F: Future>(name: &'static str, build: F) -> F::Output {
+    use tracing::Instrument;
+
+    let span = module_span(name);
+    let start = std::time::Instant::now();
+    let output = build.instrument(span.clone()).await;
+    span.record("elapsed_us", start.elapsed().as_micros() as u64);
+    output
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) async fn module_async<F: Future>(_name: &'static str, build: F) -> F::Output {
+    build.await
+}
+
+/// Logs `module: provides [..], exports [..], imports [..]` at debug level, collecting the names
+/// only if the event is enabled.
+#[cfg(feature = "tracing")]
+pub(crate) fn module_built(module: &ResolvedModule) {
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return;
+    }
+    let imports: Vec<_> = module.imports.iter().map(|import| import.name).collect();
+    tracing::debug!(
+        "module {}: provides [{}], exports [{}], imports [{}]",
+        module.name,
+        module.list_providers().join(", "),
+        module.list_exports().join(", "),
+        imports.join(", ")
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn module_built(_module: &ResolvedModule) {}
+
+/// Emits a `tracing` warning event about the provider `provider`.
+#[cfg(feature = "tracing")]
+pub(crate) fn warn(provider: &'static str, message: std::fmt::Arguments<'_>) {
+    tracing::warn!(provider, "{}", message);
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn warn(_provider: &'static str, _message: std::fmt::Arguments<'_>) {}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate as sept;
+    use crate::sept_module::{ApplicationContext, Module, ModuleFactory};
+    use crate::Injectable;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, Layer};
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::registry::LookupSpan;
+
+    /// The `provider` or `module` field of a span, without its path.
+    #[derive(Default)]
+    struct Subject(String);
+
+    impl Visit for Subject {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "provider" || field.name() == "module" {
+                let name = value.trim_end_matches('>').rsplit("::").next().unwrap();
+                self.0 = name.to_owned();
+            }
+        }
+
+        fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    /// `(span, parent)` subjects, in the order the spans opened.
+    type Spans = Vec<(String, Option<String>)>;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Spans>>);
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut subject = Subject::default();
+            attrs.record(&mut subject);
+            let span = ctx.span(id).unwrap();
+            let parent = span
+                .parent()
+                .and_then(|parent| parent.extensions().get::<String>().cloned());
+            span.extensions_mut().insert(subject.0.clone());
+            self.0.lock().unwrap().push((subject.0, parent));
+        }
+    }
+
+    /// The messages of events, in the order they were emitted.
+    #[derive(Clone, Default)]
+    struct Events(Arc<Mutex<Vec<String>>>);
+
+    struct Message<'a>(&'a mut String);
+
+    impl Visit for Message<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                *self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for Events {
+        fn on_event(&self, event: &tracing::Event<'_>, _: Context<'_, S>) {
+            let mut message = String::new();
+            event.record(&mut Message(&mut message));
+            self.0.lock().unwrap().push(message);
+        }
+    }
+
+    #[derive(Clone, Injectable)]
+    struct Pool;
+
+    #[derive(Clone, Injectable)]
+    struct Connection {
+        _pool: Arc<Pool>,
+    }
+
+    #[derive(Clone, Injectable)]
+    struct Repository {
+        _connection: Arc<Connection>,
+    }
+
+    struct PoolModule;
+    impl ModuleFactory for PoolModule {
+        fn get_module() -> Module {
+            Module::new().provide::<Pool>().export::<Pool>()
+        }
+    }
+
+    struct DbModule;
+    impl ModuleFactory for DbModule {
+        fn get_module() -> Module {
+            Module::new()
+                .import::<PoolModule>()
+                .provide_transient::<Connection>()
+                .provide::<Repository>()
+        }
+    }
+
+    #[test]
+    fn test_spans_nest_under_modules_and_dependents() {
+        let recorder = Recorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut ctx = ApplicationContext::new();
+            Module::from_factory::<DbModule>().build(&mut ctx);
+        });
+        let spans = recorder.0.lock().unwrap().clone();
+        let expected = [
+            ("DbModule", None),
+            ("PoolModule", Some("DbModule")),
+            ("Pool", Some("PoolModule")),
+            ("Repository", Some("DbModule")),
+            ("Connection", Some("Repository")),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(span, parent)| (span.to_owned(), parent.map(str::to_owned)))
+            .collect();
+        assert_eq!(spans, expected);
+    }
+
+    #[test]
+    fn test_built_modules_log_their_wiring() {
+        let events = Events::default();
+        let subscriber = tracing_subscriber::registry().with(events.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut ctx = ApplicationContext::new();
+            Module::from_factory::<DbModule>().build(&mut ctx);
+        });
+        let events = events.0.lock().unwrap().clone();
+        assert_eq!(events.len(), 2);
+        let pool = std::any::type_name::<Arc<Pool>>();
+        assert!(events[0].contains("PoolModule: provides ["));
+        assert!(events[0].ends_with(&format!("exports [{}], imports []", pool)));
+        assert!(events[1].contains("DbModule: provides ["));
+        assert!(events[1].contains("Repository"));
+        assert!(events[1].ends_with("PoolModule]"));
+    }
+
+    #[test]
+    fn test_shadowing_a_global_export_warns_once_enabled() {
+        struct GlobalPoolModule;
+        impl ModuleFactory for GlobalPoolModule {
+            fn get_module() -> Module {
+                Module::new().global().provide::<Pool>().export::<Pool>()
+            }
+        }
+
+        let shadowing_warnings = |warn: bool| {
+            let events = Events::default();
+            let subscriber = tracing_subscriber::registry().with(events.clone());
+            tracing::subscriber::with_default(subscriber, || {
+                let mut ctx = ApplicationContext::new();
+                if warn {
+                    ctx.warn_on_shadowing();
+                }
+                Module::new()
+                    .import::<GlobalPoolModule>()
+                    .provide::<Pool>()
+                    .build(&mut ctx);
+            });
+            let events = events.0.lock().unwrap();
+            events
+                .iter()
+                .filter(|e| e.ends_with("shadowing a global export"))
+                .count()
+        };
+        assert_eq!(shadowing_warnings(false), 0);
+        assert_eq!(shadowing_warnings(true), 1);
+    }
+}
@@ -0,0 +1,12 @@
+use sept::{module, Injectable};
+
+#[derive(Injectable)]
+struct UserService;
+
+#[derive(Injectable)]
+struct AuditLog;
+
+#[module(providers = [UserService], exports = [UserService, AuditLog])]
+struct UserModule;
+
+fn main() {}
@@ -0,0 +1,7 @@
+use sept::sept_module::Module;
+
+struct UserService;
+
+fn main() {
+    let _ = Module::new().provide::<UserService>();
+}